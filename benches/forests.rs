@@ -0,0 +1,34 @@
+use criterion::{black_box, criterion_group, Criterion};
+use rand::prelude::{Rng, SeedableRng, StdRng};
+
+use seansemble::{
+    core::{FeatureRow, Model, TrainingRow},
+    ensemble::ExtraTreesRegressor,
+};
+
+pub fn forest_transform(c: &mut Criterion) {
+    let mut rng: StdRng = SeedableRng::seed_from_u64(0);
+
+    let nr = 10;
+    let row = |rng: &mut StdRng| -> Vec<f64> { (0..nr).map(|_| rng.gen()).collect() };
+    let data: Vec<_> = (0..1000)
+        .map(|_| {
+            let values = row(&mut rng);
+            let label: f64 = values.iter().sum::<f64>() + rng.gen::<f64>();
+            TrainingRow::new(values, label, None)
+        })
+        .collect();
+    let inputs: Vec<FeatureRow> = (0..1000).map(|_| row(&mut rng).into()).collect();
+
+    let forest = ExtraTreesRegressor::new(100).fit_model(&data, &mut rng).unwrap();
+    let compiled = forest.compile().unwrap();
+
+    c.bench_function("Forest Transform", |b| {
+        b.iter(|| forest.transform(black_box(&inputs)).unwrap())
+    });
+    c.bench_function("Compiled Forest Transform", |b| {
+        b.iter(|| compiled.transform(black_box(&inputs)).unwrap())
+    });
+}
+
+criterion_group!(forests, forest_transform);
@@ -2,6 +2,8 @@
 // extern crate criterion;
 // extern crate seansemble;
 
-//pub mod splitters;
+pub mod forests;
+pub mod linear;
+pub mod splitters;
 
-//criterion_main!(splitters::splitters);
+criterion::criterion_main!(forests::forests, linear::linear, splitters::splitters);
@@ -0,0 +1,31 @@
+use criterion::{black_box, criterion_group, Criterion};
+use rand::prelude::{Rng, SeedableRng, StdRng};
+
+use seansemble::{
+    core::{Learner, TrainingRow},
+    linear::{GeneralizedLinearLearner, GlmFamily, LinearRegressionLearner},
+};
+
+pub fn linear_learners(c: &mut Criterion) {
+    let mut rng: StdRng = SeedableRng::seed_from_u64(0);
+
+    let nr = 10;
+    let data: Vec<_> = (0..1000)
+        .map(|_| {
+            let reals: Vec<f64> = (0..nr).map(|_| rng.gen_range(0.0..1.0)).collect();
+            let label: f64 = rng.gen_range(0.0..100.0);
+            let weight: f64 = rng.gen();
+            TrainingRow::new(reals, label, Some(weight))
+        })
+        .collect();
+
+    let linear = LinearRegressionLearner::new(true, None);
+    c.bench_function("Linear Regression", |b| {
+        b.iter(|| linear.fit(black_box(&data), &mut rng).unwrap())
+    });
+
+    let glm = GeneralizedLinearLearner::new(GlmFamily::Poisson, None, true, None);
+    c.bench_function("Poisson GLM", |b| b.iter(|| glm.fit(black_box(&data), &mut rng).unwrap()));
+}
+
+criterion_group!(linear, linear_learners);
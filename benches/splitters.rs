@@ -2,8 +2,8 @@ use criterion::{black_box, criterion_group, Criterion};
 use rand::prelude::{Rng, SeedableRng, StdRng};
 
 use seansemble::{
-    core::TrainingRow,
-    trees::splits::{RegressionSplitter, Splitter},
+    core::{AnyValue, TrainingRow},
+    trees::splits::{HistogramSplitter, RegressionSplitter, Splitter},
 };
 
 pub fn regression_splitter(c: &mut Criterion) {
@@ -12,19 +12,39 @@ pub fn regression_splitter(c: &mut Criterion) {
     let (nr, nc) = (10, 10);
     let data: Vec<_> = (0..100)
         .map(|_| {
-            let reals = (0..nr).map(|_| rng.gen_range(0.0..10.0)).collect();
-            let categoricals = (0..nc).map(|_| rng.gen_range(0..5)).collect();
+            let mut values: Vec<AnyValue> =
+                (0..nr).map(|_| rng.gen_range(0.0..10.0).into()).collect();
+            values.extend((0..nc).map(|_| AnyValue::from(rng.gen_range(0..5))));
             let label: f64 = rng.gen_range(0.0..100.0);
             let weight: f64 = rng.gen();
-            TrainingRow::new(reals, categoricals, label, weight)
+            TrainingRow::new(values, label, Some(weight))
         })
         .collect();
 
-    let mut splitter = RegressionSplitter::new(true, Some(&mut rng));
+    let mut splitter = RegressionSplitter::new(true);
 
     c.bench_function("Regression Splitter", move |b| {
-        b.iter(|| splitter.find_best_split(black_box(&data), 10, 2))
+        b.iter(|| splitter.find_best_split(black_box(&data), 20, 2, &mut rng))
     });
 }
 
-criterion_group!(splitters, regression_splitter);
+pub fn histogram_splitter(c: &mut Criterion) {
+    let mut rng: StdRng = SeedableRng::seed_from_u64(0);
+
+    let nr = 10;
+    let data: Vec<_> = (0..10000)
+        .map(|_| {
+            let values: Vec<AnyValue> = (0..nr).map(|_| rng.gen_range(0.0..10.0).into()).collect();
+            let label: f64 = rng.gen_range(0.0..100.0);
+            TrainingRow::new(values, label, None)
+        })
+        .collect();
+
+    let mut splitter = HistogramSplitter::new(255);
+
+    c.bench_function("Histogram Splitter", move |b| {
+        b.iter(|| splitter.find_best_split(black_box(&data), nr, 2, &mut rng))
+    });
+}
+
+criterion_group!(splitters, regression_splitter, histogram_splitter);
@@ -0,0 +1,3 @@
+mod naive;
+
+pub use self::naive::*;
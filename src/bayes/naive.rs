@@ -0,0 +1,379 @@
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::core::{
+    AnyValue, FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow,
+};
+
+/// Per-class likelihood of a single feature.
+#[derive(Clone, Debug)]
+enum FeatureLikelihood {
+    /// Gaussian likelihood with a mean and variance per class, or none for classes without a
+    /// finite value of the feature.
+    Gaussian { params: Vec<Option<(f64, f64)>> },
+    /// Laplace-smoothed categorical likelihood with a log probability per (class, level),
+    /// and a fallback log probability per class for levels unseen during training.
+    Categorical { log_probs: Vec<HashMap<usize, f64>>, unseen: Vec<f64> },
+}
+
+impl FeatureLikelihood {
+    fn log_likelihood(&self, class: usize, value: &AnyValue) -> f64 {
+        match (self, value) {
+            (Self::Gaussian { params }, AnyValue::Real(x)) if x.is_finite() => {
+                match params[class] {
+                    Some((mean, var)) => -0.5 * ((2.0 * PI * var).ln() + (x - mean).powi(2) / var),
+                    None => 0.0,
+                }
+            }
+            (Self::Categorical { log_probs, unseen }, AnyValue::Categorical(level)) => {
+                log_probs[class].get(level).copied().unwrap_or(unseen[class])
+            }
+            // Missing or mismatched values carry no information
+            _ => 0.0,
+        }
+    }
+}
+
+/// A naive Bayes classifier over mixed real and categorical features.
+///
+/// Real features are modeled with per-class Gaussians and categorical features with
+/// Laplace-smoothed per-class categorical distributions, chosen from the variant of each column.
+#[derive(Clone, Copy, Debug)]
+pub struct NaiveBayesLearner {
+    alpha: f64,
+    var_smoothing: f64,
+}
+
+impl NaiveBayesLearner {
+    pub fn new(alpha: Option<f64>) -> Self {
+        Self { alpha: alpha.unwrap_or(1.0).max(0.0), var_smoothing: 1e-9 }
+    }
+
+    /// Fraction of the largest feature variance added to all variances for stability.
+    pub fn with_var_smoothing(mut self, var_smoothing: f64) -> Self {
+        self.var_smoothing = var_smoothing;
+        self
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(&self, data: &[TrainingRow<usize>]) -> Result<NaiveBayesModel> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+
+        // Index classes densely in order of their label
+        let mut classes: Vec<usize> = data.iter().map(|row| row.label).collect();
+        classes.sort_unstable();
+        classes.dedup();
+        let class_index: HashMap<usize, usize> =
+            classes.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+        let nc = classes.len();
+
+        let mut class_weights = vec![0.0; nc];
+        for row in data {
+            class_weights[class_index[&row.label]] += row.weight.unwrap_or(1.0);
+        }
+        let total_weight: f64 = class_weights.iter().sum();
+        let log_priors = class_weights.iter().map(|w| (w / total_weight).ln()).collect();
+
+        let head = &data[0].features;
+        let mut likelihoods: Vec<FeatureLikelihood> = (0..head.data.len())
+            .map(|idx| {
+                let mut sums = vec![(0.0, 0.0, 0.0); nc];
+                let mut counts: Vec<HashMap<usize, f64>> = vec![HashMap::new(); nc];
+                for row in data {
+                    let (c, w) = (class_index[&row.label], row.weight.unwrap_or(1.0));
+                    match row.features[idx] {
+                        AnyValue::Real(x) if x.is_finite() => {
+                            let (sw, sx, sxx) = &mut sums[c];
+                            *sw += w;
+                            *sx += w * x;
+                            *sxx += w * x * x;
+                        }
+                        AnyValue::Categorical(level) => *counts[c].entry(level).or_insert(0.0) += w,
+                        _ => {}
+                    }
+                }
+
+                if head[idx].is_real() {
+                    // A class without any finite value of the feature is uninformed
+                    let params = sums
+                        .iter()
+                        .map(|&(sw, sx, sxx)| {
+                            (sw > 0.0).then(|| {
+                                let mean = sx / sw;
+                                (mean, (sxx / sw - mean * mean).max(0.0))
+                            })
+                        })
+                        .collect();
+                    FeatureLikelihood::Gaussian { params }
+                } else {
+                    // One extra level is reserved for categories unseen during training
+                    let levels: HashSet<usize> =
+                        counts.iter().flat_map(|c| c.keys()).copied().collect();
+                    let num_levels = levels.len() + 1;
+                    let (log_probs, unseen) = counts
+                        .into_iter()
+                        .map(|level_counts| {
+                            let total: f64 = level_counts.values().sum();
+                            let denom = total + self.alpha * num_levels as f64;
+                            if denom == 0.0 {
+                                // Without smoothing, a class with no observed level is uninformed
+                                let log_probs = levels.iter().map(|&level| (level, 0.0)).collect();
+                                return (log_probs, 0.0);
+                            }
+                            let log_prob = |n: f64| ((n + self.alpha) / denom).ln();
+                            let log_probs = levels
+                                .iter()
+                                .map(|&level| {
+                                    let n = level_counts.get(&level).copied().unwrap_or(0.0);
+                                    (level, log_prob(n))
+                                })
+                                .collect();
+                            // Without smoothing, levels never seen in training carry no information
+                            let unseen = if self.alpha > 0.0 { log_prob(0.0) } else { 0.0 };
+                            (log_probs, unseen)
+                        })
+                        .unzip();
+                    FeatureLikelihood::Categorical { log_probs, unseen }
+                }
+            })
+            .collect();
+
+        // Floor the variances relative to the largest one, so constant features stay finite
+        let max_var = likelihoods
+            .iter()
+            .filter_map(|l| match l {
+                FeatureLikelihood::Gaussian { params } => {
+                    params.iter().flatten().map(|&(_, var)| var).reduce(f64::max)
+                }
+                _ => None,
+            })
+            .fold(0.0, f64::max);
+        let epsilon = (self.var_smoothing * max_var).max(f64::MIN_POSITIVE);
+        for likelihood in likelihoods.iter_mut() {
+            if let FeatureLikelihood::Gaussian { params } = likelihood {
+                params.iter_mut().flatten().for_each(|(_, var)| *var += epsilon);
+            }
+        }
+
+        Ok(NaiveBayesModel { classes, log_priors, likelihoods })
+    }
+}
+
+impl Default for NaiveBayesLearner {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Learner<usize> for NaiveBayesLearner {
+    fn fit(
+        &self,
+        data: &[TrainingRow<usize>],
+        _rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<usize>>> {
+        Ok(Box::new(self.fit_model(data)?))
+    }
+}
+
+/// A model produced by a naive Bayes learner.
+#[derive(Clone, Debug)]
+pub struct NaiveBayesModel {
+    classes: Vec<usize>,
+    log_priors: Vec<f64>,
+    likelihoods: Vec<FeatureLikelihood>,
+}
+
+impl NaiveBayesModel {
+    /// The class labels seen during training, in ascending order.
+    pub fn classes(&self) -> &[usize] {
+        &self.classes
+    }
+
+    /// Posterior probabilities of each training class for a row, in the order of `classes`.
+    fn posterior(&self, row: &FeatureRow) -> Vec<f64> {
+        let log_joint: Vec<f64> = (0..self.classes.len())
+            .map(|c| {
+                self.log_priors[c]
+                    + self
+                        .likelihoods
+                        .iter()
+                        .zip(row.data.iter())
+                        .map(|(likelihood, value)| likelihood.log_likelihood(c, value))
+                        .sum::<f64>()
+            })
+            .collect();
+
+        // Without smoothing, a row may be impossible for every class, and only the priors remain
+        let max = log_joint.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let log_joint = if max == f64::NEG_INFINITY { self.log_priors.clone() } else { log_joint };
+
+        // Normalize with the log-sum-exp trick
+        let max = log_joint.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let unnormalized: Vec<f64> = log_joint.iter().map(|l| (l - max).exp()).collect();
+        let total: f64 = unnormalized.iter().sum();
+        unnormalized.into_iter().map(|p| p / total).collect()
+    }
+}
+
+impl Model<usize> for NaiveBayesModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<usize>>> {
+        let num_labels = self.classes.last().map_or(0, |&c| c + 1);
+        let (result, probabilities) = inputs
+            .iter()
+            .map(|row| {
+                let posterior = self.posterior(row);
+                let best = posterior.iter().enumerate().fold(0, |best, (c, p)| {
+                    if *p > posterior[best] {
+                        c
+                    } else {
+                        best
+                    }
+                });
+
+                let mut by_label = vec![0.0; num_labels];
+                self.classes.iter().zip(posterior).for_each(|(&label, p)| by_label[label] = p);
+                (self.classes[best], by_label)
+            })
+            .unzip();
+
+        Ok(Box::new(NaiveBayesPrediction { result, probabilities }))
+    }
+}
+
+/// A prediction result for a naive Bayes model.
+#[derive(Clone, Debug)]
+pub struct NaiveBayesPrediction {
+    result: Vec<usize>,
+    probabilities: Vec<Vec<f64>>,
+}
+
+impl Prediction<usize> for NaiveBayesPrediction {
+    fn expected(&self) -> Vec<usize> {
+        self.result.clone()
+    }
+
+    fn probabilities(&self) -> Option<Vec<Vec<f64>>> {
+        Some(self.probabilities.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_distr::{Distribution, Normal};
+
+    use super::*;
+
+    #[test]
+    fn test_mixed_features() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let noise = Normal::new(0.0, 1.0).unwrap();
+
+        // Class 1 has a shifted real feature and mostly level 7 of the categorical feature
+        let data: Vec<TrainingRow<usize>> = (0..200)
+            .map(|i| {
+                let label = 1 + i % 2;
+                let x = noise.sample(&mut rng) + 4.0 * label as f64;
+                let level = if i % 5 == 0 { 3 } else { 5 + 2 * (label - 1) };
+                TrainingRow::new(vec![AnyValue::from(x), AnyValue::from(level)], label, None)
+            })
+            .collect();
+
+        let model = NaiveBayesLearner::default().fit(&data, &mut rng).unwrap();
+        let inputs: Vec<FeatureRow> = vec![
+            vec![AnyValue::from(4.0), AnyValue::from(5)].into(),
+            vec![AnyValue::from(8.0), AnyValue::from(7)].into(),
+            vec![AnyValue::from(6.0), AnyValue::from(99)].into(),
+        ];
+        let prediction = model.transform(&inputs).unwrap();
+        assert_eq!(prediction.expected()[..2], [1, 2]);
+
+        let probabilities = prediction.probabilities().unwrap();
+        for p in probabilities.iter() {
+            assert_eq!(p.len(), 3);
+            assert_eq!(p[0], 0.0);
+            assert!((p.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        }
+        assert!(probabilities[0][1] > 0.99 && probabilities[1][2] > 0.99);
+        assert!((probabilities[2][1] - 0.5).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_weights_shift_priors() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data = vec![
+            TrainingRow::new(vec![AnyValue::from(0)], 0, Some(3.0)),
+            TrainingRow::new(vec![AnyValue::from(0)], 1, Some(1.0)),
+        ];
+
+        let model = NaiveBayesLearner::new(Some(0.0)).fit(&data, &mut rng).unwrap();
+        let probabilities = model.transform(&[data[0].features.clone()]).unwrap().probabilities();
+        let p = &probabilities.unwrap()[0];
+        assert!((p[0] - 0.75).abs() < 1e-12 && (p[1] - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_unsmoothed_levels() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Class 2 never observes the categorical feature, and level 9 is never seen at all
+        let missing = AnyValue::Real(f64::NAN);
+        let data = vec![
+            TrainingRow::new(vec![AnyValue::from(1), AnyValue::from(4)], 1, Some(3.0)),
+            TrainingRow::new(vec![AnyValue::from(2), AnyValue::from(5)], 1, Some(3.0)),
+            TrainingRow::new(vec![missing, AnyValue::from(5)], 2, Some(1.0)),
+            TrainingRow::new(vec![missing, AnyValue::from(6)], 2, Some(1.0)),
+        ];
+        let inputs: Vec<FeatureRow> = vec![
+            vec![AnyValue::from(9), AnyValue::from(5)].into(),
+            vec![AnyValue::from(1), AnyValue::from(5)].into(),
+            vec![AnyValue::from(2), AnyValue::from(6)].into(),
+            vec![AnyValue::from(9), AnyValue::from(9)].into(),
+        ];
+
+        for alpha in [0.0, 1.0] {
+            let model = NaiveBayesLearner::new(Some(alpha)).fit(&data, &mut rng).unwrap();
+            let probabilities = model.transform(&inputs).unwrap().probabilities().unwrap();
+            for p in probabilities.iter() {
+                assert!(p.iter().all(|p| p.is_finite()));
+                assert!((p.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            }
+        }
+
+        // Without smoothing, unseen levels leave the classes as the other feature has them
+        let model = NaiveBayesLearner::new(Some(0.0)).fit(&data, &mut rng).unwrap();
+        let probabilities = model.transform(&inputs).unwrap().probabilities().unwrap();
+        assert!((probabilities[0][1] - 0.75).abs() < 1e-12);
+        assert!((probabilities[3][1] - 0.75).abs() < 1e-12);
+        assert_eq!(probabilities[2][1], 0.0);
+    }
+
+    #[test]
+    fn test_uninformed_gaussians() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Class 2 never observes the first feature, so only the second one separates the classes
+        let data: Vec<TrainingRow<usize>> = (0..10)
+            .flat_map(|i| {
+                let x = i as f64;
+                [
+                    TrainingRow::new(vec![100.0 + x, x], 1, None),
+                    TrainingRow::new(vec![f64::NAN, 50.0 + x], 2, None),
+                ]
+            })
+            .collect();
+
+        let model = NaiveBayesLearner::default().fit(&data, &mut rng).unwrap();
+        let inputs: Vec<FeatureRow> = vec![vec![105.0, 55.0].into(), vec![105.0, 5.0].into()];
+        let prediction = model.transform(&inputs).unwrap();
+        assert_eq!(prediction.expected(), vec![2, 1]);
+        let probabilities = prediction.probabilities().unwrap();
+        assert!(probabilities[0][2] > 0.99 && probabilities[1][1] > 0.99);
+    }
+}
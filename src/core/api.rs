@@ -1,19 +1,25 @@
 use rand::Rng;
 
-use super::{row::FeatureRow, Result, TrainingRow};
+use super::{row::FeatureRow, EmpiricalDistribution, Result, TrainingRow};
 
-pub trait Learner<T> {
+pub trait Learner<T>: Send + Sync {
     fn fit(&self, data: &[TrainingRow<T>], rng: &mut impl Rng) -> Result<Box<dyn Model<T>>>
     where
         Self: Sized;
 }
 
-pub trait Model<T> {
+pub trait Model<T>: Send + Sync {
     fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<T>>>;
 
     fn loss(&self) -> Option<f64> {
         None
     }
+
+    /// The outputs predicted for every input by a model that ignores its inputs: the value of
+    /// each output for real labels, or the class probabilities for class labels.
+    fn constant_outputs(&self) -> Option<Vec<f64>> {
+        None
+    }
 }
 
 pub trait Prediction<T> {
@@ -22,4 +28,14 @@ pub trait Prediction<T> {
     fn uncertainty(&self) -> Option<Vec<T>> {
         None
     }
+
+    /// Per-row class probabilities, where the inner vector is indexed by class label.
+    fn probabilities(&self) -> Option<Vec<Vec<f64>>> {
+        None
+    }
+
+    /// Per-row empirical predictive distributions of a real label.
+    fn distributions(&self) -> Option<Vec<EmpiricalDistribution>> {
+        None
+    }
 }
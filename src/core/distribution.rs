@@ -0,0 +1,131 @@
+use super::Prediction;
+
+/// A weighted empirical distribution of real values.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EmpiricalDistribution {
+    values: Vec<(f64, f64)>,
+}
+
+impl EmpiricalDistribution {
+    /// Build a distribution from `(value, weight)` pairs, ignoring non-finite values and
+    /// non-positive weights.
+    pub fn new(values: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        let mut values: Vec<(f64, f64)> =
+            values.into_iter().filter(|(x, w)| x.is_finite() && *w > 0.0).collect();
+        values.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { values }
+    }
+
+    /// An equally weighted mixture of distributions, where each component has unit total weight.
+    pub fn mixture<'a>(components: impl IntoIterator<Item = &'a EmpiricalDistribution>) -> Self {
+        Self::new(components.into_iter().flat_map(|component| {
+            let total = component.total_weight();
+            component.values.iter().map(move |&(x, w)| (x, w / total))
+        }))
+    }
+
+    /// The `(value, weight)` pairs in increasing order of value.
+    pub fn values(&self) -> &[(f64, f64)] {
+        &self.values
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn total_weight(&self) -> f64 {
+        self.values.iter().map(|(_, w)| w).sum()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.values.iter().map(|(x, w)| x * w).sum::<f64>() / self.total_weight()
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        let var = self.values.iter().map(|(x, w)| w * (x - mean).powi(2)).sum::<f64>()
+            / self.total_weight();
+        var.sqrt()
+    }
+
+    /// The lowest value whose cumulative weight reaches the fraction `q` of the total weight.
+    pub fn quantile(&self, q: f64) -> f64 {
+        let target = q.clamp(0.0, 1.0) * self.total_weight();
+
+        let mut cumulative = 0.0;
+        for &(x, w) in self.values.iter() {
+            cumulative += w;
+            if cumulative >= target {
+                return x;
+            }
+        }
+        self.values.last().map_or(f64::NAN, |(x, _)| *x)
+    }
+
+    /// The central interval holding a fraction `1 - alpha` of the weight.
+    pub fn interval(&self, alpha: f64) -> (f64, f64) {
+        let alpha = alpha.clamp(0.0, 1.0);
+        (self.quantile(0.5 * alpha), self.quantile(1.0 - 0.5 * alpha))
+    }
+}
+
+/// A prediction result holding the predictive distribution of each row.
+///
+/// The expected value is the mean of each distribution and the uncertainty its standard deviation.
+#[derive(Clone, Debug)]
+pub struct QuantilePrediction {
+    distributions: Vec<EmpiricalDistribution>,
+}
+
+impl QuantilePrediction {
+    pub fn new(distributions: Vec<EmpiricalDistribution>) -> Self {
+        Self { distributions }
+    }
+
+    /// The conditional quantile `q` of each row.
+    pub fn quantile(&self, q: f64) -> Vec<f64> {
+        self.distributions.iter().map(|d| d.quantile(q)).collect()
+    }
+
+    /// The central `1 - alpha` prediction interval of each row.
+    pub fn interval(&self, alpha: f64) -> Vec<(f64, f64)> {
+        self.distributions.iter().map(|d| d.interval(alpha)).collect()
+    }
+}
+
+impl Prediction<f64> for QuantilePrediction {
+    fn expected(&self) -> Vec<f64> {
+        self.distributions.iter().map(|d| d.mean()).collect()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<f64>> {
+        Some(self.distributions.iter().map(|d| d.std_dev()).collect())
+    }
+
+    fn distributions(&self) -> Option<Vec<EmpiricalDistribution>> {
+        Some(self.distributions.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles() {
+        let dist =
+            EmpiricalDistribution::new(vec![(3.0, 1.0), (1.0, 1.0), (2.0, 2.0), (f64::NAN, 1.0)]);
+        assert_eq!(dist.values(), &[(1.0, 1.0), (2.0, 2.0), (3.0, 1.0)]);
+        assert_eq!(dist.quantile(0.0), 1.0);
+        assert_eq!(dist.quantile(0.5), 2.0);
+        assert_eq!(dist.quantile(0.8), 3.0);
+        assert_eq!(dist.interval(0.5), (1.0, 2.0));
+        assert_eq!(dist.mean(), 2.0);
+
+        // Each component of a mixture carries the same total weight
+        let other = EmpiricalDistribution::new(vec![(10.0, 100.0)]);
+        let mixture = EmpiricalDistribution::mixture([&dist, &other]);
+        assert_eq!(mixture.quantile(0.5), 3.0);
+        assert_eq!(mixture.quantile(0.51), 10.0);
+    }
+}
@@ -17,6 +17,8 @@ pub enum ModelingError {
     TransformError(ErrString),
     #[error("SolutionError: {0}")]
     SolutionError(ErrString),
+    #[error("CompileError: {0}")]
+    CompileError(ErrString),
     #[error("GenericError: {0}")]
     Generic(ErrString),
 }
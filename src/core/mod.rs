@@ -1,9 +1,16 @@
 mod api;
+mod distribution;
 mod error;
+mod monotonicity;
+mod outputs;
 mod row;
 mod values;
 
 pub use self::api::{Learner, Model, Prediction};
+pub use self::distribution::{EmpiricalDistribution, QuantilePrediction};
 pub use self::error::{ModelingError, Result};
+pub use self::monotonicity::Monotonicity;
+pub(crate) use self::outputs::fit_per_output;
+pub use self::outputs::{MultiTaskLabel, PerOutputModel, PerOutputPrediction, RegressionLabel};
 pub use self::row::{FeatureRow, TrainingRow};
 pub use self::values::AnyValue;
@@ -0,0 +1,22 @@
+/// The direction in which predictions must move as a real feature increases, used by monotonic
+/// tree constraints and isotonic regression.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Monotonicity {
+    #[default]
+    None,
+    Increasing,
+    Decreasing,
+}
+
+impl Monotonicity {
+    /// Whether a split with these mean outputs on each side respects the direction, where the
+    /// left side holds the smaller feature values.
+    pub fn allows(&self, left_means: &[f64], right_means: &[f64]) -> bool {
+        let mut pairs = left_means.iter().zip(right_means);
+        match self {
+            Self::None => true,
+            Self::Increasing => pairs.all(|(l, r)| l <= r),
+            Self::Decreasing => pairs.all(|(l, r)| l >= r),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use rand::Rng;
+
+use super::{AnyValue, FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+
+/// A real-valued label with one or more outputs.
+pub trait RegressionLabel: Clone + Send + Sync {
+    fn outputs(&self) -> &[f64];
+
+    /// Assemble a label from the values of its outputs.
+    fn from_outputs(outputs: Vec<f64>) -> Self;
+}
+
+impl RegressionLabel for f64 {
+    fn outputs(&self) -> &[f64] {
+        std::slice::from_ref(self)
+    }
+
+    fn from_outputs(outputs: Vec<f64>) -> Self {
+        outputs.first().copied().unwrap_or(f64::NAN)
+    }
+}
+
+impl RegressionLabel for Vec<f64> {
+    fn outputs(&self) -> &[f64] {
+        self
+    }
+
+    fn from_outputs(outputs: Vec<f64>) -> Self {
+        outputs
+    }
+}
+
+/// A label with one optional real or categorical value per task.
+pub type MultiTaskLabel = Vec<Option<AnyValue>>;
+
+/// Fit a single-output learner independently to each output of multi-output data.
+pub(crate) fn fit_per_output<L: Learner<f64>>(
+    learner: &L,
+    data: &[TrainingRow<Vec<f64>>],
+    rng: &mut impl Rng,
+) -> Result<Box<dyn Model<Vec<f64>>>> {
+    let no = data.first().map_or(0, |row| row.label.len());
+    if data.iter().any(|row| row.label.len() != no) {
+        return Err(ModelingError::FitError(
+            "All training rows must have the same number of outputs.".into(),
+        ));
+    }
+
+    let models = (0..no)
+        .map(|k| {
+            let output_data: Vec<TrainingRow<f64>> = data
+                .iter()
+                .map(|row| TrainingRow::new(row.features.clone(), row.label[k], row.weight))
+                .collect();
+            learner.fit(&output_data, rng)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Box::new(PerOutputModel { models }))
+}
+
+/// A multi-output model made of one single-output model per output.
+pub struct PerOutputModel {
+    models: Vec<Box<dyn Model<f64>>>,
+}
+
+impl Model<Vec<f64>> for PerOutputModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<Vec<f64>>>> {
+        let predictions =
+            self.models.iter().map(|model| model.transform(inputs)).collect::<Result<Vec<_>>>()?;
+
+        let transpose = |columns: Vec<Vec<f64>>| -> Vec<Vec<f64>> {
+            (0..inputs.len()).map(|i| columns.iter().map(|c| c[i]).collect()).collect()
+        };
+        let result = transpose(predictions.iter().map(|p| p.expected()).collect());
+        let uncertainty =
+            predictions.iter().map(|p| p.uncertainty()).collect::<Option<Vec<_>>>().map(transpose);
+
+        Ok(Box::new(PerOutputPrediction { result, uncertainty }))
+    }
+
+    /// The total loss over the outputs, when every output model reports one.
+    fn loss(&self) -> Option<f64> {
+        self.models.iter().map(|model| model.loss()).sum()
+    }
+
+    fn constant_outputs(&self) -> Option<Vec<f64>> {
+        let outputs = self.models.iter().map(|model| model.constant_outputs());
+        outputs.collect::<Option<Vec<_>>>().map(|outputs| outputs.concat())
+    }
+}
+
+/// A prediction result for a per-output model.
+#[derive(Clone, Debug)]
+pub struct PerOutputPrediction {
+    result: Vec<Vec<f64>>,
+    uncertainty: Option<Vec<Vec<f64>>>,
+}
+
+impl Prediction<Vec<f64>> for PerOutputPrediction {
+    fn expected(&self) -> Vec<Vec<f64>> {
+        self.result.clone()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<Vec<f64>>> {
+        self.uncertainty.clone()
+    }
+}
@@ -1,7 +1,4 @@
-use std::{
-    any::Any,
-    ops::{Index, IndexMut},
-};
+use std::ops::{Index, IndexMut};
 
 use super::AnyValue;
 
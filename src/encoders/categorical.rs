@@ -54,7 +54,7 @@ mod tests {
         // Test round trip
         for (i, val) in values.iter().enumerate() {
             let code = i + 1;
-            let e = encoder.encode(&val);
+            let e = encoder.encode(val);
             assert_eq!(e, code);
 
             let d = encoder.decode(code).unwrap();
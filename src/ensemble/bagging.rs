@@ -0,0 +1,416 @@
+use std::marker::PhantomData;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::core::{
+    EmpiricalDistribution, FeatureRow, Learner, Model, ModelingError, Prediction,
+    QuantilePrediction, Result, TrainingRow,
+};
+use crate::trees::learners::CompileTree;
+use crate::utils::{par_map, task_seeds};
+
+use super::CompiledForest;
+
+/// A learner fitting an ensemble of models to bootstrap samples of the training data.
+///
+/// Bootstrap counts are applied as multipliers of the row weights, so rows drawn several times
+/// keep a single copy in each sample.
+#[derive(Clone, Debug)]
+pub struct BaggedLearner<L> {
+    learner: L,
+    num_bags: usize,
+    bootstrap: bool,
+}
+
+impl<L> BaggedLearner<L> {
+    pub fn new(learner: L, num_bags: usize) -> Self {
+        Self { learner, num_bags, bootstrap: true }
+    }
+
+    /// Whether each member is fit to a bootstrap sample, rather than all of the data.
+    pub fn with_bootstrap(mut self, bootstrap: bool) -> Self {
+        self.bootstrap = bootstrap;
+        self
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    ///
+    /// Each member draws from its own random stream, seeded in order from `rng`, so the members
+    /// are the same whether or not they are fit in parallel.
+    pub fn fit_model<T: Clone + Send + Sync>(
+        &self,
+        data: &[TrainingRow<T>],
+        rng: &mut impl Rng,
+    ) -> Result<BaggedModel<T>>
+    where
+        L: Learner<T>,
+    {
+        self.fit_members(data, rng, |learner, data, rng| learner.fit(data, rng))
+    }
+
+    /// Fit a model to the data with `fit`, keeping the concrete type of the members, such as
+    /// tree models that can be compiled.
+    pub fn fit_model_with<T: Clone + Send + Sync, M: Send>(
+        &self,
+        data: &[TrainingRow<T>],
+        rng: &mut impl Rng,
+        fit: impl Fn(&L, &[TrainingRow<T>], &mut StdRng) -> Result<M> + Send + Sync,
+    ) -> Result<BaggedModel<T, M>>
+    where
+        L: Sync,
+    {
+        self.fit_members(data, rng, |learner, data, rng| fit(learner, data, rng).map(Box::new))
+    }
+
+    fn fit_members<T: Clone + Send + Sync, M: ?Sized + Send>(
+        &self,
+        data: &[TrainingRow<T>],
+        rng: &mut impl Rng,
+        fit: impl Fn(&L, &[TrainingRow<T>], &mut StdRng) -> Result<Box<M>> + Send + Sync,
+    ) -> Result<BaggedModel<T, M>>
+    where
+        L: Sync,
+    {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+        if self.num_bags == 0 {
+            return Err(ModelingError::FitError("The number of bags must be positive.".into()));
+        }
+
+        let seeds = task_seeds(rng, self.num_bags);
+        let models = par_map(&seeds, |&seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            if self.bootstrap {
+                fit(&self.learner, &bootstrap_sample(data, &mut rng), &mut rng)
+            } else {
+                fit(&self.learner, data, &mut rng)
+            }
+        })
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+
+        Ok(BaggedModel { models, label: PhantomData })
+    }
+}
+
+impl<L: Learner<f64>> Learner<f64> for BaggedLearner<L> {
+    fn fit(&self, data: &[TrainingRow<f64>], rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
+    }
+}
+
+impl<L: Learner<usize>> Learner<usize> for BaggedLearner<L> {
+    fn fit(
+        &self,
+        data: &[TrainingRow<usize>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<usize>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
+    }
+}
+
+/// Draw `data.len()` rows with replacement, scaling the weight of each row by its count.
+fn bootstrap_sample<T: Clone>(data: &[TrainingRow<T>], rng: &mut impl Rng) -> Vec<TrainingRow<T>> {
+    let mut counts = vec![0usize; data.len()];
+    for _ in 0..data.len() {
+        counts[rng.gen_range(0..data.len())] += 1;
+    }
+
+    data.iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(row, count)| {
+            let weight = row.weight.unwrap_or(1.0) * count as f64;
+            TrainingRow { weight: Some(weight), ..row.clone() }
+        })
+        .collect()
+}
+
+/// A model produced by a bagged learner, with members of type `M`.
+pub struct BaggedModel<T, M: ?Sized = dyn Model<T>> {
+    models: Vec<Box<M>>,
+    label: PhantomData<fn() -> T>,
+}
+
+impl<T, M: ?Sized> BaggedModel<T, M> {
+    /// The models fit to each bootstrap sample.
+    pub fn models(&self) -> &[Box<M>] {
+        &self.models
+    }
+}
+
+impl<T, M: CompileTree + ?Sized> BaggedModel<T, M> {
+    /// Compile every member for fast inference, which requires trees with constant leaves.
+    pub fn compile(&self) -> Result<CompiledForest<T>> {
+        let trees = self.models.iter().map(|model| model.compile()).collect::<Result<_>>()?;
+        CompiledForest::new(trees)
+    }
+}
+
+impl<M: Model<f64> + ?Sized> BaggedModel<f64, M> {
+    /// Predict the distribution of the label for each row, as an equally weighted mixture of the
+    /// distributions predicted by the members.
+    ///
+    /// With trees whose leaves keep their training labels, this is a quantile regression forest.
+    pub fn predict_quantiles(&self, inputs: &[FeatureRow]) -> Result<QuantilePrediction> {
+        let distributions = self.predict(inputs)?.distributions.ok_or_else(|| {
+            ModelingError::PredictError("The members do not predict distributions.".into())
+        })?;
+        Ok(QuantilePrediction::new(distributions))
+    }
+
+    /// Predict the inputs, returning the concrete prediction type.
+    pub fn predict(&self, inputs: &[FeatureRow]) -> Result<BaggedPrediction> {
+        let predictions =
+            self.models.iter().map(|model| model.transform(inputs)).collect::<Result<Vec<_>>>()?;
+        let expected: Vec<Vec<f64>> = predictions.iter().map(|p| p.expected()).collect();
+
+        let nm = expected.len() as f64;
+        let (result, uncertainty): (Vec<f64>, Vec<f64>) = (0..inputs.len())
+            .map(|i| {
+                let mean = expected.iter().map(|values| values[i]).sum::<f64>() / nm;
+                let var =
+                    expected.iter().map(|values| (values[i] - mean).powi(2)).sum::<f64>() / nm;
+                (mean, var.sqrt())
+            })
+            .unzip();
+
+        let member_distributions =
+            predictions.iter().map(|p| p.distributions()).collect::<Option<Vec<_>>>();
+        let distributions = member_distributions.map(|members| {
+            (0..inputs.len())
+                .map(|i| EmpiricalDistribution::mixture(members.iter().map(|dists| &dists[i])))
+                .collect()
+        });
+
+        Ok(BaggedPrediction { result, uncertainty, distributions })
+    }
+}
+
+impl<M: Model<f64> + ?Sized> Model<f64> for BaggedModel<f64, M> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        Ok(Box::new(self.predict(inputs)?))
+    }
+}
+
+impl<M: Model<usize> + ?Sized> BaggedModel<usize, M> {
+    /// Predict the inputs, returning the concrete prediction type.
+    ///
+    /// The class probabilities are the mean over the members, where members without probabilities
+    /// vote for their predicted class, and the predicted class is the most probable one.
+    pub fn predict(&self, inputs: &[FeatureRow]) -> Result<BaggedClassification> {
+        let mut probabilities: Vec<Vec<f64>> = vec![vec![]; inputs.len()];
+        for model in self.models.iter() {
+            let prediction = model.transform(inputs)?;
+            let member = prediction.probabilities().unwrap_or_else(|| {
+                let one_hot = |label: usize| {
+                    let mut p = vec![0.0; label + 1];
+                    p[label] = 1.0;
+                    p
+                };
+                prediction.expected().into_iter().map(one_hot).collect()
+            });
+            for (total, p) in probabilities.iter_mut().zip(member) {
+                total.resize(total.len().max(p.len()), 0.0);
+                total.iter_mut().zip(p).for_each(|(t, p)| *t += p);
+            }
+        }
+
+        let nm = self.models.len() as f64;
+        probabilities.iter_mut().for_each(|p| p.iter_mut().for_each(|p| *p /= nm));
+        let result = probabilities
+            .iter()
+            .map(|p| {
+                p.iter()
+                    .enumerate()
+                    .fold(
+                        (0, f64::NEG_INFINITY),
+                        |best, (k, &pk)| if pk > best.1 { (k, pk) } else { best },
+                    )
+                    .0
+            })
+            .collect();
+
+        Ok(BaggedClassification { result, probabilities })
+    }
+}
+
+impl<M: Model<usize> + ?Sized> Model<usize> for BaggedModel<usize, M> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<usize>>> {
+        Ok(Box::new(self.predict(inputs)?))
+    }
+}
+
+/// A prediction result for a bagged model.
+///
+/// The expected value is the mean over the members and the uncertainty their standard deviation.
+#[derive(Clone, Debug)]
+pub struct BaggedPrediction {
+    pub(super) result: Vec<f64>,
+    pub(super) uncertainty: Vec<f64>,
+    pub(super) distributions: Option<Vec<EmpiricalDistribution>>,
+}
+
+impl Prediction<f64> for BaggedPrediction {
+    fn expected(&self) -> Vec<f64> {
+        self.result.clone()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<f64>> {
+        Some(self.uncertainty.clone())
+    }
+
+    fn distributions(&self) -> Option<Vec<EmpiricalDistribution>> {
+        self.distributions.clone()
+    }
+}
+
+/// A prediction result for a bagged classification model, with the class probabilities averaged
+/// over the members.
+#[derive(Clone, Debug)]
+pub struct BaggedClassification {
+    pub(super) result: Vec<usize>,
+    pub(super) probabilities: Vec<Vec<f64>>,
+}
+
+impl Prediction<usize> for BaggedClassification {
+    fn expected(&self) -> Vec<usize> {
+        self.result.clone()
+    }
+
+    fn probabilities(&self) -> Option<Vec<Vec<f64>>> {
+        Some(self.probabilities.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_distr::{Distribution, Exp};
+
+    use super::*;
+    use crate::linear::GuessTheMeanLearner;
+    use crate::trees::leaf::RegressionLeafLearner;
+    use crate::trees::learners::{DecisionTreeParameters, RegressionTreeLearner};
+    use crate::trees::splits::RegressionSplitter;
+
+    #[test]
+    fn test_quantile_forest() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Skewed noise whose scale grows with the feature
+        let noise = Exp::new(1.0).unwrap();
+        let data: Vec<TrainingRow<f64>> = (0..400)
+            .map(|i| {
+                let x = (i % 20) as f64;
+                let y = x + (1.0 + x) * noise.sample(&mut rng);
+                TrainingRow::new(vec![x], y, None)
+            })
+            .collect();
+
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(10);
+        let splitter = RegressionSplitter::new(false);
+        let tree = RegressionTreeLearner::new(splitter, RegressionLeafLearner::empirical(), params);
+        let forest = BaggedLearner::new(tree, 20).fit_model(&data, &mut rng).unwrap();
+
+        let inputs: Vec<FeatureRow> = vec![vec![1.0].into(), vec![18.0].into()];
+        let prediction = forest.predict_quantiles(&inputs).unwrap();
+        let median = prediction.quantile(0.5);
+        let intervals = prediction.interval(0.2);
+
+        // The median of x + (1 + x) * Exp(1) is x + (1 + x) * ln 2, below the mean
+        assert!((median[0] - (1.0 + 2.0 * 2f64.ln())).abs() < 0.5);
+        assert!((median[1] - (18.0 + 19.0 * 2f64.ln())).abs() < 5.0);
+        assert!(median[1] < prediction.expected()[1]);
+
+        // The intervals are asymmetric about the median and widen with the noise
+        for (m, (lo, hi)) in median.iter().zip(intervals.iter()) {
+            assert!(lo < m && m < hi);
+            assert!(hi - m > m - lo);
+        }
+        assert!(intervals[1].1 - intervals[1].0 > 5.0 * (intervals[0].1 - intervals[0].0));
+    }
+
+    /// Run `f` on a pool of `num_threads` threads with the `rayon` feature.
+    fn with_threads<R: Send>(num_threads: usize, f: impl FnOnce() -> R + Send) -> R {
+        #[cfg(feature = "rayon")]
+        {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(num_threads).build().unwrap();
+            pool.install(f)
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            let _ = num_threads;
+            f()
+        }
+    }
+
+    #[test]
+    fn test_reproducible_fit() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<TrainingRow<f64>> = (0..300)
+            .map(|_| {
+                let (a, b) = (rng.gen::<f64>(), rng.gen::<f64>());
+                TrainingRow::new(vec![a, b], a * b + rng.gen::<f64>(), None)
+            })
+            .collect();
+        let inputs: Vec<FeatureRow> =
+            (0..2500).map(|_| vec![rng.gen::<f64>(), rng.gen::<f64>()].into()).collect();
+
+        // Randomized pivots and bootstrap samples draw from the streams of each member
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(5);
+        let splitter = RegressionSplitter::new(true);
+        let leaf = RegressionLeafLearner::mean(GuessTheMeanLearner::default());
+        let forest = BaggedLearner::new(RegressionTreeLearner::new(splitter, leaf, params), 8);
+
+        let predict = |num_threads: usize| {
+            with_threads(num_threads, || {
+                let mut rng = StdRng::seed_from_u64(1);
+                let model = forest.fit_model(&data, &mut rng).unwrap();
+                model.predict(&inputs).unwrap().expected()
+            })
+        };
+        let expected = predict(1);
+        assert_eq!(expected, predict(4));
+
+        // Predictions in batches match those of single rows
+        let model = forest.fit_model(&data, &mut StdRng::seed_from_u64(1)).unwrap();
+        for i in [0, 1023, 1024, 2499] {
+            let single = model.predict(&inputs[i..(i + 1)]).unwrap().expected();
+            assert_eq!(single[0], expected[i]);
+        }
+    }
+
+    #[test]
+    fn test_seeded_members() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<TrainingRow<f64>> = (0..100)
+            .map(|_| {
+                let (a, b) = (rng.gen::<f64>(), rng.gen::<f64>());
+                TrainingRow::new(vec![a, b], a + b, None)
+            })
+            .collect();
+        let inputs: Vec<FeatureRow> = vec![vec![0.2, 0.7].into(), vec![0.9, 0.4].into()];
+
+        // Without bootstrap, the members differ only by the features drawn at each split
+        let params = DecisionTreeParameters::default().with_num_features(1);
+        let leaf = RegressionLeafLearner::mean(GuessTheMeanLearner::default());
+        let tree = RegressionTreeLearner::new(RegressionSplitter::new(false), leaf, params);
+        let forest = BaggedLearner::new(tree, 5).with_bootstrap(false);
+
+        let predict = |seed: u64| {
+            let model = forest.fit_model(&data, &mut StdRng::seed_from_u64(seed)).unwrap();
+            let members: Vec<Vec<f64>> =
+                model.models().iter().map(|m| m.transform(&inputs).unwrap().expected()).collect();
+            (model.predict(&inputs).unwrap().expected(), members)
+        };
+        let (expected, members) = predict(3);
+        assert!(members.iter().any(|member| *member != members[0]));
+        assert_eq!(predict(3).0, expected);
+        assert_ne!(predict(4).0, expected);
+    }
+}
@@ -0,0 +1,198 @@
+use std::marker::PhantomData;
+
+use crate::core::{FeatureRow, Model, ModelingError, Prediction, Result};
+use crate::trees::learners::{CompiledTree, TRANSFORM_BATCH_SIZE};
+use crate::utils::par_map;
+
+use super::{BaggedClassification, BaggedPrediction};
+
+/// An ensemble of compiled trees, predicting as the [`BaggedModel`](super::BaggedModel) it was
+/// compiled from.
+///
+/// Each batch of [`TRANSFORM_BATCH_SIZE`] rows is run through every tree in turn, accumulating
+/// the leaf outputs in place, and the batches are predicted in parallel with the `rayon` feature.
+/// Predictive distributions are not kept.
+#[derive(Clone, Debug)]
+pub struct CompiledForest<T> {
+    trees: Vec<CompiledTree>,
+    width: usize,
+    label: PhantomData<fn() -> T>,
+}
+
+impl<T> CompiledForest<T> {
+    pub fn new(trees: Vec<CompiledTree>) -> Result<Self> {
+        if trees.is_empty() {
+            return Err(ModelingError::CompileError(
+                "Cannot compile a forest without trees.".into(),
+            ));
+        }
+        let width = trees.iter().map(|tree| tree.width()).max().unwrap_or(0);
+        Ok(Self { trees, width, label: PhantomData })
+    }
+
+    pub fn trees(&self) -> &[CompiledTree] {
+        &self.trees
+    }
+
+    /// The mean and standard deviation of each leaf output over the trees, flattened by row.
+    fn moments(&self, inputs: &[FeatureRow]) -> (Vec<f64>, Vec<f64>) {
+        let batches: Vec<&[FeatureRow]> = inputs.chunks(TRANSFORM_BATCH_SIZE).collect();
+        let moments = par_map(&batches, |batch| {
+            let mut mean = vec![0.0; batch.len() * self.width];
+            let mut m2 = vec![0.0; batch.len() * self.width];
+            for (k, tree) in self.trees.iter().enumerate() {
+                for (i, outputs) in tree.predict(batch).into_iter().enumerate() {
+                    // Narrower leaves are missing the largest classes, with zero probability
+                    for j in 0..self.width {
+                        let (x, at) = (outputs.get(j).copied().unwrap_or(0.0), i * self.width + j);
+                        let delta = x - mean[at];
+                        mean[at] += delta / (k + 1) as f64;
+                        m2[at] += delta * (x - mean[at]);
+                    }
+                }
+            }
+            (mean, m2)
+        });
+
+        let nt = self.trees.len() as f64;
+        let (mut mean, mut std) = (vec![], vec![]);
+        for (batch_mean, batch_m2) in moments {
+            mean.extend(batch_mean);
+            std.extend(batch_m2.into_iter().map(|m2| (m2 / nt).sqrt()));
+        }
+        (mean, std)
+    }
+}
+
+impl CompiledForest<f64> {
+    /// Predict the inputs, returning the concrete prediction type.
+    pub fn predict(&self, inputs: &[FeatureRow]) -> Result<BaggedPrediction> {
+        if self.width != 1 {
+            return Err(ModelingError::PredictError(
+                "The trees must predict a single output.".into(),
+            ));
+        }
+        let (result, uncertainty) = self.moments(inputs);
+        Ok(BaggedPrediction { result, uncertainty, distributions: None })
+    }
+}
+
+impl Model<f64> for CompiledForest<f64> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        Ok(Box::new(self.predict(inputs)?))
+    }
+}
+
+impl CompiledForest<usize> {
+    /// Predict the inputs, returning the concrete prediction type.
+    ///
+    /// The class probabilities are the mean over the trees, and the predicted class is the most
+    /// probable one.
+    pub fn predict(&self, inputs: &[FeatureRow]) -> Result<BaggedClassification> {
+        let (mean, _) = self.moments(inputs);
+        let probabilities: Vec<Vec<f64>> = match self.width {
+            0 => vec![vec![]; inputs.len()],
+            width => mean.chunks(width).map(|p| p.to_vec()).collect(),
+        };
+        let result = probabilities
+            .iter()
+            .map(|p| {
+                p.iter()
+                    .enumerate()
+                    .fold(
+                        (0, f64::NEG_INFINITY),
+                        |best, (k, &pk)| if pk > best.1 { (k, pk) } else { best },
+                    )
+                    .0
+            })
+            .collect();
+
+        Ok(BaggedClassification { result, probabilities })
+    }
+}
+
+impl Model<usize> for CompiledForest<usize> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<usize>>> {
+        Ok(Box::new(self.predict(inputs)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::approx_eq;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::core::{AnyValue, TrainingRow};
+    use crate::ensemble::{BaggedLearner, ExtraTreesClassifier};
+    use crate::trees::leaf::RegressionLeafLearner;
+    use crate::trees::learners::{DecisionTreeParameters, RegressionTreeLearner};
+    use crate::trees::splits::RegressionSplitter;
+
+    fn random_row(rng: &mut StdRng) -> FeatureRow {
+        let features = vec![
+            AnyValue::Real(rng.gen_range(0.0..1.0)),
+            AnyValue::Real(rng.gen_range(0.0..1.0)),
+            AnyValue::Categorical(rng.gen_range(1..5)),
+        ];
+        FeatureRow::new(features)
+    }
+
+    #[test]
+    fn compiled_forests() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let rows: Vec<FeatureRow> = (0..400).map(|_| random_row(&mut rng)).collect();
+        let inputs: Vec<FeatureRow> = (0..2500).map(|_| random_row(&mut rng)).collect();
+        let score = |row: &FeatureRow| {
+            row[0].as_real().unwrap() + row[1].as_real().unwrap()
+                - row[2].as_categorical().unwrap() as f64 / 4.0
+        };
+
+        // Bagged regression trees, including an empirical leaf that compiles to its mean
+        let data: Vec<TrainingRow<f64>> =
+            rows.iter().map(|row| TrainingRow::new(row.clone(), score(row), None)).collect();
+        let params = DecisionTreeParameters::default();
+        let splitter = RegressionSplitter::new(true);
+        let tree = RegressionTreeLearner::new(splitter, RegressionLeafLearner::empirical(), params);
+        let fit = |tree: &RegressionTreeLearner, data: &[TrainingRow<f64>], rng: &mut StdRng| {
+            tree.fit_model(data, rng)
+        };
+        let forest = BaggedLearner::new(tree, 20).fit_model_with(&data, &mut rng, fit).unwrap();
+        let compiled = forest.compile().unwrap();
+        assert_eq!(compiled.trees().len(), 20);
+
+        let expected = forest.predict(&inputs).unwrap();
+        let predicted = compiled.predict(&inputs).unwrap();
+        let pairs = |a: Vec<f64>, b: Vec<f64>| a.into_iter().zip(b).collect::<Vec<_>>();
+        for (e, p) in pairs(expected.expected(), predicted.expected()) {
+            assert!(approx_eq!(f64, e, p, epsilon = 1e-10));
+        }
+        for (e, p) in pairs(expected.uncertainty().unwrap(), predicted.uncertainty().unwrap()) {
+            assert!(approx_eq!(f64, e, p, epsilon = 1e-10));
+        }
+        assert!(predicted.distributions().is_none());
+
+        // Extremely randomized classification trees, whose leaves may miss some classes
+        let data: Vec<TrainingRow<usize>> = rows
+            .iter()
+            .map(|row| {
+                let label = if score(row) < 0.0 { 1 } else { 2 + (score(row) > 1.0) as usize };
+                TrainingRow::new(row.clone(), label, None)
+            })
+            .collect();
+        let forest = ExtraTreesClassifier::new(20).fit_model(&data, &mut rng).unwrap();
+        let compiled = forest.compile().unwrap();
+
+        let expected = forest.predict(&inputs).unwrap();
+        let predicted = compiled.predict(&inputs).unwrap();
+        assert_eq!(predicted.expected(), expected.expected());
+        let probabilities = expected.probabilities().unwrap();
+        for (e, p) in probabilities.iter().zip(predicted.probabilities().unwrap()) {
+            assert_eq!(p.len(), 4);
+            for (k, pk) in p.iter().enumerate() {
+                let ek = e.get(k).copied().unwrap_or(0.0);
+                assert!(approx_eq!(f64, ek, *pk, epsilon = 1e-10));
+            }
+        }
+    }
+}
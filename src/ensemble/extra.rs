@@ -0,0 +1,203 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::{BaggedLearner, BaggedModel};
+use crate::core::{Learner, Model, ModelingError, Result, TrainingRow};
+use crate::linear::{ClassificationBaseline, GuessTheMeanLearner};
+use crate::trees::leaf::{ClassificationLeafLearner, RegressionLeafLearner};
+use crate::trees::learners::{
+    ClassificationTreeLearner, ClassificationTreeModel, DecisionTreeParameters,
+    RegressionTreeLearner, RegressionTreeModel,
+};
+use crate::trees::splits::ExtraTreesSplitter;
+
+/// An ensemble of extremely randomized regression trees.
+///
+/// Every tree is grown on all of the training data by default, with an
+/// [`ExtraTreesSplitter`] drawing from the random stream of the tree. The prediction is the mean
+/// over the trees and the uncertainty their standard deviation.
+#[derive(Clone, Debug)]
+pub struct ExtraTreesRegressor {
+    num_trees: usize,
+    learner: RegressionLeafLearner,
+    params: DecisionTreeParameters,
+    bootstrap: bool,
+}
+
+impl ExtraTreesRegressor {
+    pub fn new(num_trees: usize) -> Self {
+        Self {
+            num_trees,
+            learner: RegressionLeafLearner::mean(GuessTheMeanLearner::default()),
+            params: DecisionTreeParameters::default(),
+            bootstrap: false,
+        }
+    }
+
+    /// The learner fit to the rows in each leaf.
+    pub fn with_leaf_learner(mut self, learner: RegressionLeafLearner) -> Self {
+        self.learner = learner;
+        self
+    }
+
+    /// The hyperparameters of every tree.
+    pub fn with_params(mut self, params: DecisionTreeParameters) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Whether each tree is grown on a bootstrap sample, rather than all of the data.
+    pub fn with_bootstrap(mut self, bootstrap: bool) -> Self {
+        self.bootstrap = bootstrap;
+        self
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(
+        &self,
+        data: &[TrainingRow<f64>],
+        rng: &mut impl Rng,
+    ) -> Result<BaggedModel<f64, RegressionTreeModel<f64>>> {
+        let tree = RegressionTreeLearner::new(
+            ExtraTreesSplitter::new(),
+            self.learner.clone(),
+            self.params,
+        );
+        let fit = |tree: &RegressionTreeLearner<_>, data: &[_], rng: &mut StdRng| {
+            tree.fit_model(data, rng)
+        };
+        fit_trees(data, self.num_trees, self.bootstrap, tree, rng, fit)
+    }
+}
+
+impl Learner<f64> for ExtraTreesRegressor {
+    fn fit(&self, data: &[TrainingRow<f64>], rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
+    }
+}
+
+/// An ensemble of extremely randomized classification trees.
+///
+/// Every tree is grown on all of the training data by default, with an
+/// [`ExtraTreesSplitter`] drawing from the random stream of the tree. The leaves predict the class
+/// proportions of their rows, and the ensemble the mean of those probabilities.
+#[derive(Clone, Debug)]
+pub struct ExtraTreesClassifier {
+    num_trees: usize,
+    learner: ClassificationLeafLearner,
+    params: DecisionTreeParameters,
+    bootstrap: bool,
+}
+
+impl ExtraTreesClassifier {
+    pub fn new(num_trees: usize) -> Self {
+        let baseline =
+            GuessTheMeanLearner::default().with_classification(ClassificationBaseline::Prior);
+        Self {
+            num_trees,
+            learner: ClassificationLeafLearner::mean(baseline),
+            params: DecisionTreeParameters::default(),
+            bootstrap: false,
+        }
+    }
+
+    /// The learner fit to the rows in each leaf.
+    pub fn with_leaf_learner(mut self, learner: ClassificationLeafLearner) -> Self {
+        self.learner = learner;
+        self
+    }
+
+    /// The hyperparameters of every tree.
+    pub fn with_params(mut self, params: DecisionTreeParameters) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Whether each tree is grown on a bootstrap sample, rather than all of the data.
+    pub fn with_bootstrap(mut self, bootstrap: bool) -> Self {
+        self.bootstrap = bootstrap;
+        self
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(
+        &self,
+        data: &[TrainingRow<usize>],
+        rng: &mut impl Rng,
+    ) -> Result<BaggedModel<usize, ClassificationTreeModel>> {
+        let tree = ClassificationTreeLearner::new(
+            ExtraTreesSplitter::new(),
+            self.learner.clone(),
+            self.params,
+        );
+        let fit = |tree: &ClassificationTreeLearner<_>, data: &[_], rng: &mut StdRng| {
+            tree.fit_model(data, rng)
+        };
+        fit_trees(data, self.num_trees, self.bootstrap, tree, rng, fit)
+    }
+}
+
+impl Learner<usize> for ExtraTreesClassifier {
+    fn fit(
+        &self,
+        data: &[TrainingRow<usize>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<usize>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
+    }
+}
+
+/// Fit `num_trees` copies of a tree learner with `fit`, as a bagged ensemble.
+fn fit_trees<T: Clone + Send + Sync, L: Sync, M: Send>(
+    data: &[TrainingRow<T>],
+    num_trees: usize,
+    bootstrap: bool,
+    learner: L,
+    rng: &mut impl Rng,
+    fit: impl Fn(&L, &[TrainingRow<T>], &mut StdRng) -> Result<M> + Send + Sync,
+) -> Result<BaggedModel<T, M>> {
+    if num_trees == 0 {
+        return Err(ModelingError::FitError("The number of trees must be positive.".into()));
+    }
+    BaggedLearner::new(learner, num_trees).with_bootstrap(bootstrap).fit_model_with(data, rng, fit)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::core::{FeatureRow, Prediction};
+
+    #[test]
+    fn test_extra_trees() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let xs: Vec<Vec<f64>> =
+            (0..300).map(|_| vec![rng.gen_range(0.0..6.0), rng.gen_range(0.0..1.0)]).collect();
+        let inputs: Vec<FeatureRow> =
+            vec![vec![0.5, 0.5].into(), vec![1.6, 0.5].into(), vec![4.7, 0.5].into()];
+
+        // The forest follows a smooth function closely, with trees randomized independently
+        let data: Vec<TrainingRow<f64>> =
+            xs.iter().map(|x| TrainingRow::new(x.clone(), x[0].sin(), None)).collect();
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(3);
+        let forest = ExtraTreesRegressor::new(30).with_params(params).fit_model(&data, &mut rng);
+        let prediction = forest.unwrap().predict(&inputs).unwrap();
+        for (p, x) in prediction.expected().iter().zip([0.5f64, 1.6, 4.7]) {
+            assert!((p - x.sin()).abs() < 0.1);
+        }
+        assert!(prediction.uncertainty().unwrap().iter().all(|&u| u > 0.0));
+
+        // Classes in bands of the first feature
+        let data: Vec<TrainingRow<usize>> = xs
+            .iter()
+            .map(|x| TrainingRow::new(x.clone(), 1 + (x[0] / 2.0) as usize, None))
+            .collect();
+        let forest = ExtraTreesClassifier::new(30).fit_model(&data, &mut rng).unwrap();
+        let prediction = forest.predict(&inputs).unwrap();
+        assert_eq!(prediction.expected(), vec![1, 1, 3]);
+        for p in prediction.probabilities().unwrap() {
+            assert!((p.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+}
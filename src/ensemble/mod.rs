@@ -0,0 +1,7 @@
+mod bagging;
+mod compiled;
+mod extra;
+
+pub use self::bagging::*;
+pub use self::compiled::*;
+pub use self::extra::*;
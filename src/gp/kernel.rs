@@ -0,0 +1,96 @@
+/// Covariance function over the real features of a Gaussian process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// Squared exponential kernel, `exp(-r^2 / 2l^2)`.
+    RBF,
+    /// Matern kernel with smoothness 1/2, the Ornstein-Uhlenbeck kernel.
+    Matern12,
+    /// Matern kernel with smoothness 3/2.
+    Matern32,
+    /// Matern kernel with smoothness 5/2.
+    Matern52,
+    /// Linear kernel with a bias, `1 + x.x' / l^2`.
+    Linear,
+}
+
+impl Kernel {
+    /// Evaluate the kernel between two real vectors at the given length scale.
+    pub fn eval(&self, x: &[f64], y: &[f64], length_scale: f64) -> f64 {
+        if let Self::Linear = self {
+            let dot: f64 = x.iter().zip(y.iter()).map(|(a, b)| a * b).sum();
+            return 1.0 + dot / (length_scale * length_scale);
+        }
+
+        let sq_dist: f64 = x.iter().zip(y.iter()).map(|(a, b)| (a - b) * (a - b)).sum();
+        let r = sq_dist.sqrt() / length_scale;
+        match self {
+            Self::RBF => (-0.5 * r * r).exp(),
+            Self::Matern12 => (-r).exp(),
+            Self::Matern32 => {
+                let s = 3f64.sqrt() * r;
+                (1.0 + s) * (-s).exp()
+            }
+            Self::Matern52 => {
+                let s = 5f64.sqrt() * r;
+                (1.0 + s + s * s / 3.0) * (-s).exp()
+            }
+            Self::Linear => unreachable!(),
+        }
+    }
+}
+
+/// Hamming-style kernel over categorical features, `exp(-h / l)`.
+///
+/// Here `h` is the fraction of categorical features that differ between the two inputs.
+pub fn hamming_kernel(x: &[usize], y: &[usize], length_scale: f64) -> f64 {
+    if x.is_empty() {
+        return 1.0;
+    }
+    let mismatches = x.iter().zip(y.iter()).filter(|(a, b)| a != b).count();
+    (-(mismatches as f64) / (x.len() as f64) / length_scale).exp()
+}
+
+/// Hyperparameters of the composite Gaussian process kernel.
+///
+/// The full covariance is `signal_variance * k_real * k_categorical`,
+/// plus `noise_variance / weight` on the diagonal for each training row.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KernelParameters {
+    pub signal_variance: f64,
+    pub length_scale: f64,
+    pub categorical_length_scale: f64,
+    pub noise_variance: f64,
+}
+
+impl KernelParameters {
+    pub(crate) fn to_log(self) -> [f64; 4] {
+        [
+            self.signal_variance.ln(),
+            self.length_scale.ln(),
+            self.categorical_length_scale.ln(),
+            self.noise_variance.ln(),
+        ]
+    }
+
+    pub(crate) fn from_log(log_params: &[f64]) -> Self {
+        // Keep the search away from degenerate kernels
+        let bounded = |x: f64| x.clamp(-12.0, 12.0).exp();
+        Self {
+            signal_variance: bounded(log_params[0]),
+            length_scale: bounded(log_params[1]),
+            categorical_length_scale: bounded(log_params[2]),
+            noise_variance: bounded(log_params[3]),
+        }
+    }
+}
+
+impl Default for KernelParameters {
+    fn default() -> Self {
+        Self {
+            signal_variance: 1.0,
+            length_scale: 1.0,
+            categorical_length_scale: 1.0,
+            noise_variance: 0.01,
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod kernel;
+mod regression;
+
+pub use self::kernel::*;
+pub use self::regression::*;
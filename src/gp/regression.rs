@@ -0,0 +1,366 @@
+use nalgebra::{Cholesky, DMatrix, DVector, Dynamic};
+use rand::Rng;
+
+use super::kernel::{hamming_kernel, Kernel, KernelParameters};
+use crate::core::{FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+use crate::utils::nelder_mead;
+
+/// Diagonal jitter keeping the covariance matrix numerically positive definite.
+const JITTER: f64 = 1e-10;
+
+/// Real and categorical features of a row, with the real features standardized.
+#[derive(Clone, Debug)]
+struct EncodedRow {
+    reals: Vec<f64>,
+    categoricals: Vec<usize>,
+}
+
+/// Column layout and standardization of the training features.
+#[derive(Clone, Debug)]
+struct FeatureEncoder {
+    real_indices: Vec<usize>,
+    categorical_indices: Vec<usize>,
+    means: Vec<f64>,
+    scales: Vec<f64>,
+}
+
+impl FeatureEncoder {
+    fn new<T>(data: &[TrainingRow<T>]) -> Self {
+        let real_indices = data[0].features.real_indices();
+        let categorical_indices = data[0].features.categorical_indices();
+
+        let n = data.len() as f64;
+        let (means, scales) = real_indices
+            .iter()
+            .map(|&idx| {
+                let values: Vec<f64> =
+                    data.iter().map(|row| row.features[idx].as_real().unwrap_or(0.0)).collect();
+                let mean = values.iter().sum::<f64>() / n;
+                let var = values.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n;
+                (mean, if var > 0.0 { var.sqrt() } else { 1.0 })
+            })
+            .unzip();
+
+        Self { real_indices, categorical_indices, means, scales }
+    }
+
+    fn encode(&self, row: &FeatureRow) -> EncodedRow {
+        let reals = self
+            .real_indices
+            .iter()
+            .zip(self.means.iter().zip(self.scales.iter()))
+            .map(|(&idx, (mean, scale))| (row[idx].as_real().unwrap_or(*mean) - mean) / scale)
+            .collect();
+        let categoricals = self
+            .categorical_indices
+            .iter()
+            .map(|&idx| row[idx].as_categorical().unwrap_or(usize::MAX))
+            .collect();
+        EncodedRow { reals, categoricals }
+    }
+}
+
+fn covariance(kernel: Kernel, params: &KernelParameters, x: &EncodedRow, y: &EncodedRow) -> f64 {
+    let real_part =
+        if x.reals.is_empty() { 1.0 } else { kernel.eval(&x.reals, &y.reals, params.length_scale) };
+    let categorical_part =
+        hamming_kernel(&x.categoricals, &y.categoricals, params.categorical_length_scale);
+    params.signal_variance * real_part * categorical_part
+}
+
+/// The Cholesky factor of the training covariance, with `K^-1 y` and the negative log marginal likelihood.
+fn factorize(
+    kernel: Kernel,
+    params: &KernelParameters,
+    rows: &[EncodedRow],
+    y: &DVector<f64>,
+    w: &DVector<f64>,
+) -> Option<(Cholesky<f64, Dynamic>, DVector<f64>, f64)> {
+    let n = rows.len();
+    let mut K = DMatrix::zeros(n, n);
+    for i in 0..n {
+        for j in 0..=i {
+            let k = covariance(kernel, params, &rows[i], &rows[j]);
+            K[(i, j)] = k;
+            K[(j, i)] = k;
+        }
+        K[(i, i)] += params.noise_variance / w[i] + JITTER;
+    }
+
+    let chol = K.cholesky()?;
+    let alpha = chol.solve(y);
+    let log_det: f64 = chol.l_dirty().diagonal().iter().map(|d| d.ln()).sum();
+    let nlml = 0.5 * y.dot(&alpha) + log_det + 0.5 * (n as f64) * (2.0 * std::f64::consts::PI).ln();
+
+    nlml.is_finite().then_some((chol, alpha, nlml))
+}
+
+/// A Gaussian process regression learner with hyperparameters fit by maximum marginal likelihood.
+///
+/// Real features are standardized and covary through the chosen [`Kernel`],
+/// while categorical features covary through a Hamming-style kernel.
+/// Row weights scale the noise variance of each row by `1 / weight`, so rows without positive
+/// weight have no influence and are left out of the fit.
+#[derive(Clone, Copy, Debug)]
+pub struct GaussianProcessLearner {
+    kernel: Kernel,
+    params: KernelParameters,
+    optimize: bool,
+    restarts: usize,
+    max_iter: usize,
+}
+
+impl GaussianProcessLearner {
+    pub fn new(kernel: Kernel) -> Self {
+        Self { kernel, params: Default::default(), optimize: true, restarts: 2, max_iter: 500 }
+    }
+
+    /// The kernel hyperparameters, used as the starting point when optimizing.
+    pub fn with_parameters(mut self, params: KernelParameters) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Whether to optimize the hyperparameters by marginal likelihood.
+    pub fn with_optimize(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// The number of additional random starting points for the optimizer.
+    pub fn with_restarts(mut self, restarts: usize) -> Self {
+        self.restarts = restarts;
+        self
+    }
+
+    /// The maximum number of optimizer iterations per starting point.
+    pub fn with_max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(
+        &self,
+        data: &[TrainingRow<f64>],
+        rng: &mut impl Rng,
+    ) -> Result<GaussianProcessModel> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+
+        // A weight of zero is an infinite noise variance, which the covariance cannot hold
+        let data: Vec<TrainingRow<f64>> =
+            data.iter().filter(|row| row.weight.unwrap_or(1.0) > 0.0).cloned().collect();
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without positively weighted rows.".into(),
+            ));
+        }
+
+        let encoder = FeatureEncoder::new(&data);
+        let rows: Vec<EncodedRow> = data.iter().map(|row| encoder.encode(&row.features)).collect();
+
+        // Standardize labels so the default hyperparameters are on a sensible scale
+        let ns = data.len();
+        let w = DVector::from_iterator(ns, data.iter().map(|row| row.weight.unwrap_or(1.0)));
+        let labels = DVector::from_iterator(ns, data.iter().map(|row| row.label));
+        let label_mean = labels.dot(&w) / w.sum();
+        let label_var = labels.map(|y| (y - label_mean).powi(2)).dot(&w) / w.sum();
+        let label_scale = if label_var > 0.0 { label_var.sqrt() } else { 1.0 };
+        let y = labels.map(|y| (y - label_mean) / label_scale);
+
+        let params = if self.optimize {
+            let objective = |log_params: &[f64]| {
+                let params = KernelParameters::from_log(log_params);
+                factorize(self.kernel, &params, &rows, &y, &w).map_or(f64::INFINITY, |f| f.2)
+            };
+
+            let mut starts = vec![self.params.to_log()];
+            for _ in 0..self.restarts {
+                starts.push([
+                    rng.gen_range(-2.0..2.0),
+                    rng.gen_range(-2.0..2.0),
+                    rng.gen_range(-2.0..2.0),
+                    rng.gen_range(-8.0..0.0),
+                ]);
+            }
+
+            let (best, _) = starts
+                .iter()
+                .map(|x0| nelder_mead(objective, x0, 1.0, self.max_iter, 1e-8))
+                .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                .unwrap();
+            KernelParameters::from_log(&best)
+        } else {
+            self.params
+        };
+
+        let (chol, alpha, nlml) =
+            factorize(self.kernel, &params, &rows, &y, &w).ok_or_else(|| {
+                ModelingError::FitError("Kernel matrix is not positive definite.".into())
+            })?;
+
+        Ok(GaussianProcessModel {
+            kernel: self.kernel,
+            params,
+            encoder,
+            rows,
+            chol,
+            alpha,
+            label_mean,
+            label_scale,
+            nlml,
+        })
+    }
+}
+
+impl Learner<f64> for GaussianProcessLearner {
+    fn fit(&self, data: &[TrainingRow<f64>], rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
+    }
+}
+
+/// A model produced by a Gaussian process learner.
+#[derive(Clone, Debug)]
+pub struct GaussianProcessModel {
+    kernel: Kernel,
+    params: KernelParameters,
+    encoder: FeatureEncoder,
+    rows: Vec<EncodedRow>,
+    chol: Cholesky<f64, Dynamic>,
+    alpha: DVector<f64>,
+    label_mean: f64,
+    label_scale: f64,
+    nlml: f64,
+}
+
+impl GaussianProcessModel {
+    /// The fitted kernel hyperparameters, on the standardized label scale.
+    pub fn parameters(&self) -> KernelParameters {
+        self.params
+    }
+}
+
+impl Model<f64> for GaussianProcessModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        let encoded: Vec<EncodedRow> = inputs.iter().map(|row| self.encoder.encode(row)).collect();
+
+        let Kstar = DMatrix::from_fn(self.rows.len(), encoded.len(), |i, j| {
+            covariance(self.kernel, &self.params, &self.rows[i], &encoded[j])
+        });
+        let V = self.chol.l_dirty().solve_lower_triangular(&Kstar).ok_or_else(|| {
+            ModelingError::PredictError("Failure in triangular solve of kernel matrix.".into())
+        })?;
+
+        let means = Kstar.tr_mul(&self.alpha);
+        let (result, std_dev) = encoded
+            .iter()
+            .enumerate()
+            .map(|(j, row)| {
+                let prior = covariance(self.kernel, &self.params, row, row);
+                let variance = (prior - V.column(j).norm_squared()).max(0.0);
+                (self.label_mean + self.label_scale * means[j], self.label_scale * variance.sqrt())
+            })
+            .unzip();
+
+        Ok(Box::new(GaussianProcessPrediction { result, std_dev }))
+    }
+
+    /// The negative log marginal likelihood of the standardized training labels.
+    fn loss(&self) -> Option<f64> {
+        Some(self.nlml)
+    }
+}
+
+/// A prediction result for a Gaussian process model.
+///
+/// The uncertainty is the posterior standard deviation of the latent function, excluding noise.
+#[derive(Clone, Debug)]
+pub struct GaussianProcessPrediction {
+    result: Vec<f64>,
+    std_dev: Vec<f64>,
+}
+
+impl Prediction<f64> for GaussianProcessPrediction {
+    fn expected(&self) -> Vec<f64> {
+        self.result.clone()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<f64>> {
+        Some(self.std_dev.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::core::AnyValue;
+
+    #[test]
+    fn test_interpolation_and_uncertainty() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<_> = (0..20)
+            .map(|i| {
+                let x = i as f64 / 4.0;
+                TrainingRow::new(vec![x], x.sin(), None)
+            })
+            .collect();
+
+        for kernel in [Kernel::RBF, Kernel::Matern52] {
+            let model = GaussianProcessLearner::new(kernel).fit_model(&data, &mut rng).unwrap();
+
+            let inputs: Vec<FeatureRow> = vec![vec![1.1].into(), vec![20.0].into()];
+            let prediction = model.transform(&inputs).unwrap();
+            let (expected, sigma) = (prediction.expected(), prediction.uncertainty().unwrap());
+
+            assert!((expected[0] - 1.1f64.sin()).abs() < 1e-2, "{:?}", kernel);
+            assert!(sigma[0] < 1e-2 && sigma[1] > 10.0 * sigma[0], "{:?}", kernel);
+        }
+    }
+
+    #[test]
+    fn test_categorical_kernel() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<_> = (0..30)
+            .map(|i| {
+                let category = i % 3;
+                let features = vec![AnyValue::from(category), AnyValue::from(i as f64 / 30.0)];
+                TrainingRow::new(features, 10.0 * category as f64, None)
+            })
+            .collect();
+
+        let model = GaussianProcessLearner::new(Kernel::RBF).fit_model(&data, &mut rng).unwrap();
+        let inputs: Vec<FeatureRow> =
+            (0..3).map(|c| vec![AnyValue::from(c), AnyValue::from(0.5)].into()).collect();
+        let expected = model.transform(&inputs).unwrap().expected();
+
+        for (c, e) in expected.iter().enumerate() {
+            assert!((e - 10.0 * c as f64).abs() < 0.5);
+        }
+    }
+
+    #[test]
+    fn test_zero_weights() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let row = |i: usize, weight| TrainingRow::new(vec![i as f64], (i as f64).sin(), weight);
+        let data: Vec<_> = (0..10).map(|i| row(i, if i == 4 { Some(0.0) } else { None })).collect();
+        let kept: Vec<_> = data.iter().filter(|row| row.weight.is_none()).cloned().collect();
+
+        // A row without weight is left out, as if it were never there
+        let learner = GaussianProcessLearner::new(Kernel::RBF).with_optimize(false);
+        let inputs: Vec<FeatureRow> = vec![vec![4.0].into(), vec![6.5].into()];
+        let weighted = learner.fit_model(&data, &mut rng).unwrap().transform(&inputs).unwrap();
+        let dropped = learner.fit_model(&kept, &mut rng).unwrap().transform(&inputs).unwrap();
+        for (a, b) in weighted.expected().iter().zip(dropped.expected()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        let unweighted: Vec<_> = (0..3).map(|i| row(i, Some(0.0))).collect();
+        assert!(learner.fit_model(&unweighted, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+mod regression;
+
+pub use self::regression::*;
@@ -0,0 +1,228 @@
+use rand::Rng;
+
+pub use crate::core::Monotonicity;
+use crate::core::{FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+
+/// A block of pooled observations sharing a single fitted value.
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    weighted_sum: f64,
+    weight: f64,
+    start: usize,
+    end: usize,
+}
+
+impl Block {
+    fn value(&self) -> f64 {
+        self.weighted_sum / self.weight
+    }
+}
+
+/// Weighted monotone least squares fit of a sequence with the pool-adjacent-violators algorithm.
+///
+/// The values are taken in the given order, and the fitted value of each one is returned. The
+/// direction must be increasing or decreasing.
+pub fn pool_adjacent_violators(
+    values: &[f64],
+    weights: &[f64],
+    direction: Monotonicity,
+) -> Result<Vec<f64>> {
+    let sign = match direction {
+        Monotonicity::Increasing => 1.0,
+        Monotonicity::Decreasing => -1.0,
+        Monotonicity::None => {
+            return Err(ModelingError::FitError(
+                "Isotonic regression needs an increasing or decreasing direction.".into(),
+            ))
+        }
+    };
+
+    let mut blocks: Vec<Block> = Vec::with_capacity(values.len());
+    for (i, (&y, &w)) in values.iter().zip(weights.iter()).enumerate() {
+        let mut block = Block { weighted_sum: sign * y * w, weight: w, start: i, end: i };
+
+        // Merge backwards while the previous block violates the ordering
+        while let Some(last) = blocks.last() {
+            if last.value() < block.value() {
+                break;
+            }
+            block.weighted_sum += last.weighted_sum;
+            block.weight += last.weight;
+            block.start = last.start;
+            blocks.pop();
+        }
+        blocks.push(block);
+    }
+
+    let mut fitted = vec![0.0; values.len()];
+    for block in blocks {
+        fitted[block.start..=block.end].fill(sign * block.value());
+    }
+    Ok(fitted)
+}
+
+/// A learner for a weighted monotone, piecewise-constant function of a single real feature.
+///
+/// Predictions interpolate linearly between the fitted steps and are clipped outside the training range.
+#[derive(Clone, Copy, Debug)]
+pub struct IsotonicRegressionLearner {
+    index: usize,
+    direction: Monotonicity,
+}
+
+impl IsotonicRegressionLearner {
+    pub fn new(index: usize, direction: Monotonicity) -> Self {
+        Self { index, direction }
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(&self, data: &[TrainingRow<f64>]) -> Result<IsotonicRegressionModel> {
+        let mut points: Vec<(f64, f64, f64)> = data
+            .iter()
+            .filter_map(|row| {
+                let x = row.features[self.index].as_real()?;
+                (x.is_finite() && row.label.is_finite())
+                    .then(|| (x, row.label, row.weight.unwrap_or(1.0)))
+            })
+            .filter(|(_, _, w)| *w > 0.0)
+            .collect();
+        if points.is_empty() {
+            return Err(ModelingError::FitError(
+                format!("No finite real values for feature {} in the training data.", self.index)
+                    .into(),
+            ));
+        }
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        // Pool rows that share a feature value before enforcing monotonicity
+        let mut xs: Vec<f64> = Vec::with_capacity(points.len());
+        let mut sums: Vec<(f64, f64)> = Vec::with_capacity(points.len());
+        for (x, y, w) in points {
+            match (xs.last(), sums.last_mut()) {
+                (Some(&last), Some((wy, ws))) if last == x => {
+                    *wy += w * y;
+                    *ws += w;
+                }
+                _ => {
+                    xs.push(x);
+                    sums.push((w * y, w));
+                }
+            }
+        }
+        let values: Vec<f64> = sums.iter().map(|(wy, w)| wy / w).collect();
+        let weights: Vec<f64> = sums.iter().map(|(_, w)| *w).collect();
+        let fitted = pool_adjacent_violators(&values, &weights, self.direction)?;
+
+        // Only the first and last point of each constant step are needed to interpolate
+        let mut knots: Vec<(f64, f64)> = Vec::new();
+        for (i, (&x, &y)) in xs.iter().zip(fitted.iter()).enumerate() {
+            let interior = i > 0 && i + 1 < xs.len() && fitted[i - 1] == y && fitted[i + 1] == y;
+            if !interior {
+                knots.push((x, y));
+            }
+        }
+
+        Ok(IsotonicRegressionModel { index: self.index, knots })
+    }
+}
+
+impl Learner<f64> for IsotonicRegressionLearner {
+    fn fit(&self, data: &[TrainingRow<f64>], _rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.fit_model(data)?))
+    }
+}
+
+/// A model produced by an isotonic regression learner.
+#[derive(Clone, Debug)]
+pub struct IsotonicRegressionModel {
+    index: usize,
+    knots: Vec<(f64, f64)>,
+}
+
+impl IsotonicRegressionModel {
+    /// The `(x, y)` points defining the fitted function, in increasing order of `x`.
+    pub fn knots(&self) -> &[(f64, f64)] {
+        &self.knots
+    }
+
+    /// Evaluate the fitted function at a single value.
+    pub fn evaluate(&self, x: f64) -> f64 {
+        if x.is_nan() {
+            return f64::NAN;
+        }
+
+        let (first, last) = (self.knots[0], self.knots[self.knots.len() - 1]);
+        if x <= first.0 {
+            return first.1;
+        }
+        if x >= last.0 {
+            return last.1;
+        }
+
+        // Index of the first knot strictly to the right of x
+        let right = self.knots.partition_point(|(k, _)| *k <= x);
+        let ((x0, y0), (x1, y1)) = (self.knots[right - 1], self.knots[right]);
+        y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    }
+}
+
+impl Model<f64> for IsotonicRegressionModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        let result = inputs
+            .iter()
+            .map(|row| self.evaluate(row[self.index].as_real().unwrap_or(f64::NAN)))
+            .collect();
+        Ok(Box::new(IsotonicRegressionPrediction { result }))
+    }
+}
+
+/// A prediction result for an isotonic regression model.
+#[derive(Clone, Debug)]
+pub struct IsotonicRegressionPrediction {
+    result: Vec<f64>,
+}
+
+impl Prediction<f64> for IsotonicRegressionPrediction {
+    fn expected(&self) -> Vec<f64> {
+        self.result.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_adjacent_violators() {
+        let values = [1.0, 3.0, 2.0, 4.0, 3.0, 5.0];
+        let weights = [1.0, 1.0, 1.0, 1.0, 3.0, 1.0];
+
+        let increasing =
+            pool_adjacent_violators(&values, &weights, Monotonicity::Increasing).unwrap();
+        assert_eq!(increasing, vec![1.0, 2.5, 2.5, 3.25, 3.25, 5.0]);
+
+        let decreasing =
+            pool_adjacent_violators(&values, &[1.0; 6], Monotonicity::Decreasing).unwrap();
+        assert!(decreasing.windows(2).all(|w| w[0] >= w[1]));
+        assert!((decreasing.iter().sum::<f64>() - values.iter().sum::<f64>()).abs() < 1e-12);
+
+        assert!(pool_adjacent_violators(&values, &weights, Monotonicity::None).is_err());
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let data: Vec<_> = [(0.0, 0.0), (1.0, 2.0), (2.0, 1.0), (3.0, 3.0), (3.0, 5.0)]
+            .iter()
+            .map(|&(x, y)| TrainingRow::new(vec![x], y, None))
+            .collect();
+
+        let model =
+            IsotonicRegressionLearner::new(0, Monotonicity::Increasing).fit_model(&data).unwrap();
+        assert_eq!(model.knots(), &[(0.0, 0.0), (1.0, 1.5), (2.0, 1.5), (3.0, 4.0)]);
+
+        let inputs: Vec<FeatureRow> =
+            [-1.0, 0.5, 1.5, 2.5, 10.0].iter().map(|&x| vec![x].into()).collect();
+        let expected = model.transform(&inputs).unwrap().expected();
+        assert_eq!(expected, vec![0.0, 0.75, 1.5, 2.75, 4.0]);
+    }
+}
@@ -1,9 +1,15 @@
 #![allow(non_snake_case)]
 #![allow(clippy::upper_case_acronyms)]
+#![allow(clippy::module_inception)]
 
+pub mod bayes;
 pub mod core;
 pub mod encoders;
+pub mod ensemble;
+pub mod gp;
+pub mod isotonic;
 pub mod linear;
+pub mod neighbors;
 pub mod stats;
-//pub mod trees;
-//pub mod utils;
+pub mod trees;
+pub mod utils;
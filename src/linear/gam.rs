@@ -0,0 +1,464 @@
+use std::collections::BTreeMap;
+
+use nalgebra::{DMatrix, DVector};
+use rand::Rng;
+
+use super::{included_real_features, LinearRegressionLearner};
+use crate::core::{
+    AnyValue, FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow,
+};
+
+/// A B-spline basis over equally spaced knots spanning a closed range.
+#[derive(Clone, Debug)]
+pub struct BSplineBasis {
+    lower: f64,
+    upper: f64,
+    degree: usize,
+    knots: Vec<f64>,
+}
+
+impl BSplineBasis {
+    /// Build a basis with `num_basis` functions of the given degree over `[lower, upper]`.
+    pub fn new(lower: f64, upper: f64, num_basis: usize, degree: usize) -> Self {
+        let num_segments = num_basis.saturating_sub(degree).max(1);
+        let step = (upper - lower) / num_segments as f64;
+        let knots = (0..=num_segments + 2 * degree)
+            .map(|i| lower + (i as f64 - degree as f64) * step)
+            .collect();
+        Self { lower, upper, degree, knots }
+    }
+
+    /// The number of basis functions.
+    pub fn len(&self) -> usize {
+        self.knots.len() - self.degree - 1
+    }
+
+    /// Whether the basis has no functions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The range covered by the basis.
+    pub fn range(&self) -> (f64, f64) {
+        (self.lower, self.upper)
+    }
+
+    /// Values of every basis function at `x`, which is clamped to the range of the basis.
+    pub fn evaluate(&self, x: f64) -> Vec<f64> {
+        let x = x.clamp(self.lower, self.upper);
+        let t = &self.knots;
+
+        // Degree zero indicator of the knot span holding x, with the upper bound in the last span
+        let last_span = t.len() - 2 - self.degree;
+        let span = (self.degree..=last_span).find(|&i| x < t[i + 1]).unwrap_or(last_span);
+        let mut values = vec![0.0; t.len() - 1];
+        values[span] = 1.0;
+
+        // Cox-de Boor recursion, raising the degree one step at a time
+        for d in 1..=self.degree {
+            for i in 0..t.len() - 1 - d {
+                let left = (x - t[i]) / (t[i + d] - t[i]) * values[i];
+                let right = (t[i + d + 1] - x) / (t[i + d + 1] - t[i + 1]) * values[i + 1];
+                values[i] = left + right;
+            }
+        }
+        values.truncate(self.len());
+        values
+    }
+}
+
+/// The fitted penalized spline for a real feature.
+#[derive(Clone, Debug)]
+pub struct SplineShape {
+    index: usize,
+    basis: BSplineBasis,
+    coefficients: Vec<f64>,
+}
+
+impl SplineShape {
+    /// The index of the feature.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The spline basis and its coefficients.
+    pub fn basis(&self) -> (&BSplineBasis, &[f64]) {
+        (&self.basis, &self.coefficients)
+    }
+
+    /// The contribution of a value, which is held constant outside the training range.
+    /// Missing values contribute nothing.
+    pub fn evaluate(&self, x: f64) -> f64 {
+        if !x.is_finite() {
+            return 0.0;
+        }
+        self.basis.evaluate(x).iter().zip(self.coefficients.iter()).map(|(b, c)| b * c).sum()
+    }
+
+    /// The shape function sampled at `num_points` equally spaced values across the training range.
+    pub fn curve(&self, num_points: usize) -> Vec<(f64, f64)> {
+        let (lower, upper) = self.basis.range();
+        let step = (upper - lower) / (num_points.max(2) - 1) as f64;
+        (0..num_points)
+            .map(|i| {
+                let x = lower + i as f64 * step;
+                (x, self.evaluate(x))
+            })
+            .collect()
+    }
+}
+
+/// The fitted per-level effects for a categorical feature.
+#[derive(Clone, Debug)]
+pub struct CategoricalShape {
+    index: usize,
+    effects: BTreeMap<usize, f64>,
+}
+
+impl CategoricalShape {
+    /// The index of the feature.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The effect of each level seen during training.
+    pub fn effects(&self) -> &BTreeMap<usize, f64> {
+        &self.effects
+    }
+
+    /// The contribution of a level, where unseen levels contribute nothing.
+    pub fn evaluate(&self, level: usize) -> f64 {
+        self.effects.get(&level).copied().unwrap_or(0.0)
+    }
+}
+
+/// The shape function of a single feature in an additive model.
+///
+/// Each shape is centered to have zero weighted mean over the training data.
+#[derive(Clone, Debug)]
+pub enum ShapeFunction {
+    Spline(SplineShape),
+    Categorical(CategoricalShape),
+}
+
+impl ShapeFunction {
+    /// The index of the feature.
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Spline(shape) => shape.index,
+            Self::Categorical(shape) => shape.index,
+        }
+    }
+
+    /// The contribution of a feature value to the prediction.
+    pub fn evaluate(&self, value: &AnyValue) -> f64 {
+        match (self, value) {
+            (Self::Spline(shape), AnyValue::Real(x)) => shape.evaluate(*x),
+            (Self::Categorical(shape), AnyValue::Categorical(level)) => shape.evaluate(*level),
+            _ => 0.0,
+        }
+    }
+}
+
+/// A learner for generalized additive models with an identity link.
+///
+/// Real features get penalized B-splines (P-splines) with a penalty on the squared second
+/// differences of the coefficients, and categorical features get ridge-shrunk per-level effects.
+/// The shapes are fit by backfitting on the partial residuals.
+#[derive(Clone, Copy, Debug)]
+pub struct GamLearner {
+    num_basis: usize,
+    degree: usize,
+    penalty: f64,
+    categorical_penalty: f64,
+    max_iter: usize,
+    tolerance: f64,
+}
+
+impl GamLearner {
+    pub fn new(num_basis: usize, penalty: Option<f64>) -> Self {
+        Self {
+            num_basis,
+            degree: 3,
+            penalty: penalty.unwrap_or(1.0).max(0.0),
+            categorical_penalty: 1.0,
+            max_iter: 100,
+            tolerance: 1e-6,
+        }
+    }
+
+    /// The polynomial degree of the spline basis.
+    pub fn with_degree(mut self, degree: usize) -> Self {
+        self.degree = degree;
+        self
+    }
+
+    /// The ridge penalty shrinking each categorical level effect towards zero.
+    pub fn with_categorical_penalty(mut self, penalty: f64) -> Self {
+        self.categorical_penalty = penalty.max(0.0);
+        self
+    }
+
+    /// The maximum number of backfitting sweeps.
+    pub fn with_max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// The largest change in any fitted shape value at which backfitting stops.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(&self, data: &[TrainingRow<f64>]) -> Result<GamModel> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+
+        let ns = data.len();
+        let y: Vec<f64> = data.iter().map(|row| row.label).collect();
+        let w: Vec<f64> = data.iter().map(|row| row.weight.unwrap_or(1.0)).collect();
+        let total_weight: f64 = w.iter().sum();
+        let intercept = y.iter().zip(w.iter()).map(|(y, w)| y * w).sum::<f64>() / total_weight;
+
+        // Spline bases are fixed up front, so each sweep only re-solves for coefficients
+        let num_basis = self.num_basis.max(self.degree + 1);
+        let mut shapes: Vec<ShapeFunction> = included_real_features(data)
+            .into_iter()
+            .map(|index| {
+                let (lower, upper) = data
+                    .iter()
+                    .filter_map(|row| row.features[index].as_real())
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+                let basis = BSplineBasis::new(lower, upper, num_basis, self.degree);
+                let coefficients = vec![0.0; basis.len()];
+                ShapeFunction::Spline(SplineShape { index, basis, coefficients })
+            })
+            .collect();
+        shapes.extend(data[0].features.categorical_indices().into_iter().map(|index| {
+            ShapeFunction::Categorical(CategoricalShape { index, effects: BTreeMap::new() })
+        }));
+
+        let solver = LinearRegressionLearner::new(false, Some(1e-8));
+        let penalty = self.penalty.sqrt();
+        let mut fitted = vec![vec![0.0; ns]; shapes.len()];
+
+        // Append the difference penalty to each spline design as pseudo-rows with zero response
+        let designs: Vec<Option<DMatrix<f64>>> = shapes
+            .iter()
+            .map(|shape| match shape {
+                ShapeFunction::Spline(spline) => {
+                    let nb = spline.basis.len();
+                    let nd = nb.saturating_sub(2);
+                    let mut X = DMatrix::zeros(ns + nd, nb);
+                    for (i, row) in data.iter().enumerate() {
+                        let x = row.features[spline.index].as_real().unwrap_or(f64::NAN);
+                        X.row_mut(i).copy_from_slice(&spline.basis.evaluate(x));
+                    }
+                    for k in 0..nd {
+                        X[(ns + k, k)] = penalty;
+                        X[(ns + k, k + 1)] = -2.0 * penalty;
+                        X[(ns + k, k + 2)] = penalty;
+                    }
+                    Some(X)
+                }
+                ShapeFunction::Categorical(_) => None,
+            })
+            .collect();
+
+        for _ in 0..self.max_iter {
+            let mut max_change: f64 = 0.0;
+
+            for (j, shape) in shapes.iter_mut().enumerate() {
+                let residual: Vec<f64> = (0..ns)
+                    .map(|i| {
+                        let others: f64 = fitted.iter().map(|f| f[i]).sum::<f64>() - fitted[j][i];
+                        y[i] - intercept - others
+                    })
+                    .collect();
+
+                match shape {
+                    ShapeFunction::Spline(spline) => {
+                        let X = designs[j].as_ref().unwrap();
+                        let nd = X.nrows() - ns;
+                        let mut targets = residual.clone();
+                        targets.resize(ns + nd, 0.0);
+                        let mut weights = w.clone();
+                        weights.resize(ns + nd, 1.0);
+
+                        let beta = solver.solve_normal_equation(
+                            X,
+                            &DVector::from_vec(targets),
+                            &DVector::from_vec(weights),
+                        )?;
+                        spline.coefficients = beta.iter().copied().collect();
+                    }
+                    ShapeFunction::Categorical(categorical) => {
+                        // The ridge solution on the one-hot columns is a shrunken mean per level
+                        let mut sums: BTreeMap<usize, (f64, f64)> = BTreeMap::new();
+                        for (i, row) in data.iter().enumerate() {
+                            if let Some(level) = row.features[categorical.index].as_categorical() {
+                                let (wr, ws) = sums.entry(level).or_insert((0.0, 0.0));
+                                *wr += w[i] * residual[i];
+                                *ws += w[i];
+                            }
+                        }
+                        categorical.effects = sums
+                            .into_iter()
+                            .map(|(level, (wr, ws))| (level, wr / (ws + self.categorical_penalty)))
+                            .collect();
+                    }
+                }
+
+                // Center the shape so the intercept carries the overall level
+                let index = shape.index();
+                let mut values: Vec<f64> =
+                    data.iter().map(|row| shape.evaluate(&row.features[index])).collect();
+                let mean =
+                    values.iter().zip(w.iter()).map(|(v, w)| v * w).sum::<f64>() / total_weight;
+                match shape {
+                    ShapeFunction::Spline(spline) => {
+                        // B-splines sum to one, so shifting every coefficient shifts the curve
+                        spline.coefficients.iter_mut().for_each(|c| *c -= mean);
+                    }
+                    ShapeFunction::Categorical(categorical) => {
+                        categorical.effects.values_mut().for_each(|e| *e -= mean);
+                    }
+                }
+                values.iter_mut().for_each(|v| *v -= mean);
+
+                for (old, new) in fitted[j].iter().zip(values.iter()) {
+                    max_change = max_change.max((old - new).abs());
+                }
+                fitted[j] = values;
+            }
+
+            if max_change < self.tolerance {
+                break;
+            }
+        }
+
+        Ok(GamModel { intercept, shapes })
+    }
+}
+
+impl Learner<f64> for GamLearner {
+    fn fit(&self, data: &[TrainingRow<f64>], _rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.fit_model(data)?))
+    }
+}
+
+/// A model produced by a GAM learner.
+#[derive(Clone, Debug)]
+pub struct GamModel {
+    intercept: f64,
+    shapes: Vec<ShapeFunction>,
+}
+
+impl GamModel {
+    pub fn intercept(&self) -> f64 {
+        self.intercept
+    }
+
+    /// The fitted shape function of each included feature.
+    pub fn shape_functions(&self) -> &[ShapeFunction] {
+        &self.shapes
+    }
+
+    /// The shape function of a feature, if it was included in the model.
+    pub fn shape_function(&self, index: usize) -> Option<&ShapeFunction> {
+        self.shapes.iter().find(|shape| shape.index() == index)
+    }
+}
+
+impl Model<f64> for GamModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        let result = inputs
+            .iter()
+            .map(|row| {
+                self.intercept
+                    + self
+                        .shapes
+                        .iter()
+                        .map(|shape| shape.evaluate(&row[shape.index()]))
+                        .sum::<f64>()
+            })
+            .collect();
+        Ok(Box::new(GamPrediction { result }))
+    }
+}
+
+/// A prediction result for a GAM.
+#[derive(Clone, Debug)]
+pub struct GamPrediction {
+    result: Vec<f64>,
+}
+
+impl Prediction<f64> for GamPrediction {
+    fn expected(&self) -> Vec<f64> {
+        self.result.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_basis_partition_of_unity() {
+        let basis = BSplineBasis::new(-1.0, 2.0, 8, 3);
+        assert_eq!(basis.len(), 8);
+        for x in [-5.0, -1.0, -0.3, 0.0, 0.77, 1.5, 2.0] {
+            let values = basis.evaluate(x);
+            assert!(values.iter().all(|&b| b >= 0.0));
+            assert!((values.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_additive_recovery() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let effects = [-1.0, 0.5, 0.5];
+        let data: Vec<TrainingRow<f64>> = (0..300)
+            .map(|_| {
+                let x = rng.gen_range(-3.0..3.0);
+                let level = rng.gen_range(0..3);
+                let features = vec![AnyValue::from(x), AnyValue::from(level)];
+                TrainingRow::new(features, 2.0 + x.sin() + effects[level], None)
+            })
+            .collect();
+
+        let learner = GamLearner::new(12, Some(0.1)).with_categorical_penalty(0.0);
+        let model = learner.fit_model(&data).unwrap();
+        assert_eq!(model.shape_functions().len(), 2);
+
+        let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
+        let predicted = model.transform(&features).unwrap().expected();
+        let max_error = predicted
+            .iter()
+            .zip(data.iter())
+            .map(|(p, row)| (p - row.label).abs())
+            .fold(0.0, f64::max);
+        assert!(max_error < 0.05, "Max error {}", max_error);
+
+        // Shapes are identified up to a constant, so compare differences
+        let spline = model.shape_function(0).unwrap();
+        let at = |x: f64| spline.evaluate(&AnyValue::from(x));
+        assert!((at(1.5) - at(-1.5) - 2.0 * 1.5f64.sin()).abs() < 0.05);
+
+        match model.shape_function(1).unwrap() {
+            ShapeFunction::Categorical(shape) => {
+                assert!((shape.evaluate(1) - shape.evaluate(0) - 1.5).abs() < 0.05);
+                assert!((shape.evaluate(1) - shape.evaluate(2)).abs() < 0.05);
+                assert_eq!(shape.evaluate(9), 0.0);
+            }
+            _ => panic!("Expected a categorical shape"),
+        }
+    }
+}
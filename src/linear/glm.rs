@@ -0,0 +1,447 @@
+use nalgebra::DVector;
+use rand::Rng;
+
+use super::linear::{design_matrix, included_real_features};
+use super::LinearRegressionLearner;
+use crate::core::{FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+
+/// Smallest mean allowed for families whose variance is only defined on positive means.
+const MIN_MEAN: f64 = 1e-10;
+
+/// Most times an IRLS step is halved before the previous solution is kept.
+const MAX_HALVINGS: usize = 30;
+
+/// Distribution family of the response in a generalized linear model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlmFamily {
+    Normal,
+    Poisson,
+    Gamma,
+    /// Tweedie family with variance `mu^power`, where `power` lies outside of (0, 1).
+    Tweedie(f64),
+}
+
+impl GlmFamily {
+    /// The canonical link function for the family.
+    pub fn canonical_link(&self) -> GlmLink {
+        match self {
+            Self::Normal => GlmLink::Identity,
+            Self::Poisson => GlmLink::Log,
+            Self::Gamma => GlmLink::Inverse,
+            Self::Tweedie(_) => GlmLink::Log,
+        }
+    }
+
+    /// The variance of the response as a function of its mean.
+    pub fn variance(&self, mu: f64) -> f64 {
+        match self {
+            Self::Normal => 1.0,
+            Self::Poisson => mu,
+            Self::Gamma => mu * mu,
+            Self::Tweedie(power) => mu.powf(*power),
+        }
+    }
+
+    /// The unit deviance between an observed label and a predicted mean.
+    pub fn unit_deviance(&self, y: f64, mu: f64) -> f64 {
+        match self {
+            Self::Normal => (y - mu).powi(2),
+            Self::Poisson => {
+                let ylogy = if y > 0.0 { y * (y / mu).ln() } else { 0.0 };
+                2.0 * (ylogy - (y - mu))
+            }
+            Self::Gamma => 2.0 * ((y - mu) / mu - (y / mu).ln()),
+            Self::Tweedie(power) => match *power {
+                0.0 => Self::Normal.unit_deviance(y, mu),
+                1.0 => Self::Poisson.unit_deviance(y, mu),
+                2.0 => Self::Gamma.unit_deviance(y, mu),
+                p => {
+                    let a = y.max(0.0).powf(2.0 - p) / ((1.0 - p) * (2.0 - p));
+                    let b = y * mu.powf(1.0 - p) / (1.0 - p);
+                    let c = mu.powf(2.0 - p) / (2.0 - p);
+                    2.0 * (a - b + c)
+                }
+            },
+        }
+    }
+
+    /// Does the family require strictly positive means?
+    fn positive_mean(&self) -> bool {
+        !matches!(self, Self::Normal | Self::Tweedie(0.0))
+    }
+
+    /// Is the label inside the support of the family?
+    fn in_support(&self, y: f64) -> bool {
+        match self {
+            Self::Normal => y.is_finite(),
+            Self::Poisson => y >= 0.0,
+            Self::Gamma => y > 0.0,
+            Self::Tweedie(power) if *power <= 0.0 => y.is_finite(),
+            Self::Tweedie(power) if *power < 2.0 => y >= 0.0,
+            Self::Tweedie(_) => y > 0.0,
+        }
+    }
+}
+
+/// Link function relating the linear predictor to the mean response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlmLink {
+    Identity,
+    Log,
+    Inverse,
+}
+
+impl GlmLink {
+    /// Map a mean response onto the linear predictor scale.
+    pub fn link(&self, mu: f64) -> f64 {
+        match self {
+            Self::Identity => mu,
+            Self::Log => mu.ln(),
+            Self::Inverse => 1.0 / mu,
+        }
+    }
+
+    /// Map a linear predictor back onto the mean response scale.
+    pub fn inverse(&self, eta: f64) -> f64 {
+        match self {
+            Self::Identity => eta,
+            Self::Log => eta.exp(),
+            Self::Inverse => 1.0 / eta,
+        }
+    }
+
+    /// Derivative of the link function with respect to the mean.
+    pub fn derivative(&self, mu: f64) -> f64 {
+        match self {
+            Self::Identity => 1.0,
+            Self::Log => 1.0 / mu,
+            Self::Inverse => -1.0 / (mu * mu),
+        }
+    }
+}
+
+/// A generalized linear model learner, fit by iteratively reweighted least squares.
+///
+/// Steps that increase the deviance, or make it non-finite, are halved back towards the previous
+/// solution. When no finite solution is found, the model predicts the weighted mean label.
+#[derive(Clone, Copy, Debug)]
+pub struct GeneralizedLinearLearner {
+    family: GlmFamily,
+    link: GlmLink,
+    intercept: bool,
+    alpha: f64,
+    max_iter: usize,
+    tolerance: f64,
+}
+
+impl GeneralizedLinearLearner {
+    /// Create a new learner, using the canonical link for the family when none is provided.
+    pub fn new(
+        family: GlmFamily,
+        link: Option<GlmLink>,
+        intercept: bool,
+        alpha: Option<f64>,
+    ) -> Self {
+        Self {
+            family,
+            link: link.unwrap_or_else(|| family.canonical_link()),
+            intercept,
+            alpha: alpha.unwrap_or(0.0).max(0.0),
+            max_iter: 100,
+            tolerance: 1e-8,
+        }
+    }
+
+    /// The maximum number of IRLS iterations.
+    pub fn with_max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// The relative change in deviance below which IRLS has converged.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    fn clamp_mean(&self, mu: f64) -> f64 {
+        if self.family.positive_mean() {
+            mu.max(MIN_MEAN)
+        } else {
+            mu
+        }
+    }
+
+    fn deviance(&self, y: &DVector<f64>, mu: &DVector<f64>, w: &DVector<f64>) -> f64 {
+        y.iter()
+            .zip(mu.iter())
+            .zip(w.iter())
+            .map(|((&yi, &mi), &wi)| wi * self.family.unit_deviance(yi, mi))
+            .sum()
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(&self, data: &[TrainingRow<f64>]) -> Result<GeneralizedLinearModel> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+        if let GlmFamily::Tweedie(power) = self.family {
+            if power > 0.0 && power < 1.0 {
+                return Err(ModelingError::FitError(
+                    format!("No Tweedie distribution exists for power {}.", power).into(),
+                ));
+            }
+        }
+        if let Some(row) = data.iter().find(|row| !self.family.in_support(row.label)) {
+            return Err(ModelingError::FitError(
+                format!(
+                    "Label {} is outside the support of the {:?} family.",
+                    row.label, self.family
+                )
+                .into(),
+            ));
+        }
+
+        let indices = included_real_features(data);
+        let (ns, nf) = (data.len(), indices.len());
+
+        let y = DVector::from_iterator(ns, data.iter().map(|row| row.label));
+        let w = DVector::from_iterator(ns, data.iter().map(|row| row.weight.unwrap_or(1.0)));
+        let mean = y.dot(&w) / w.sum();
+
+        // The weighted mean label, or no intercept at all, for when there is no linear fit
+        let mean_model = |indices: Vec<usize>| {
+            let intercept = if self.intercept { self.link.link(mean) } else { 0.0 };
+            let mu = DVector::from_element(ns, self.clamp_mean(self.link.inverse(intercept)));
+            GeneralizedLinearModel {
+                intercept,
+                coeffs: vec![0.0; nf],
+                indices,
+                link: self.link,
+                deviance: self.deviance(&y, &mu, &w),
+            }
+        };
+
+        // If there are not enough data rows, return early with mean
+        if ns <= nf {
+            return Ok(mean_model(indices));
+        }
+
+        let X = design_matrix(data.iter().map(|row| &row.features), &indices, self.intercept);
+        let solver = LinearRegressionLearner::new(self.intercept, Some(self.alpha));
+        let evaluate = |beta: &DVector<f64>| {
+            let eta = &X * beta;
+            let mu = eta.map(|ei| self.clamp_mean(self.link.inverse(ei)));
+            let deviance = self.deviance(&y, &mu, &w);
+            (eta, mu, deviance)
+        };
+
+        // Start halfway between each label and the mean, which keeps log/inverse links finite
+        let mut mu = y.map(|yi| self.clamp_mean(0.5 * (yi + mean)));
+        let mut eta = mu.map(|mi| self.link.link(mi));
+        let mut deviance = self.deviance(&y, &mu, &w);
+        let mut fitted: Option<DVector<f64>> = None;
+
+        for _ in 0..self.max_iter {
+            // Working response and weights of the local quadratic approximation
+            let gprime = mu.map(|mi| self.link.derivative(mi));
+            let z = DVector::from_fn(ns, |i, _| eta[i] + (y[i] - mu[i]) * gprime[i]);
+            let irls_w = DVector::from_fn(ns, |i, _| {
+                w[i] / (self.family.variance(mu[i]) * gprime[i] * gprime[i])
+            });
+
+            // Keep the last solution when the reweighted system is singular
+            let mut beta = match solver.solve_normal_equation(&X, &z, &irls_w) {
+                Ok(beta) => beta,
+                Err(_) => break,
+            };
+            let (mut next_eta, mut next_mu, mut next_deviance) = evaluate(&beta);
+
+            // Halve the step while it makes the deviance worse than at the previous solution
+            let slack = self.tolerance * (deviance.abs() + 0.1);
+            let worse = |next: f64| !next.is_finite() || next - deviance > slack;
+            if let Some(previous) = &fitted {
+                for _ in 0..MAX_HALVINGS {
+                    if !worse(next_deviance) {
+                        break;
+                    }
+                    beta = (previous + &beta) * 0.5;
+                    (next_eta, next_mu, next_deviance) = evaluate(&beta);
+                }
+                if worse(next_deviance) {
+                    break;
+                }
+            } else if !next_deviance.is_finite() {
+                break;
+            }
+
+            let previous = deviance;
+            (eta, mu, deviance) = (next_eta, next_mu, next_deviance);
+            fitted = Some(beta);
+            if (deviance - previous).abs() / (deviance.abs() + 0.1) < self.tolerance {
+                break;
+            }
+        }
+
+        let beta = match fitted {
+            Some(beta) => beta,
+            None => return Ok(mean_model(indices)),
+        };
+        let (intercept, coeffs) = if self.intercept {
+            (beta[0], beta.iter().skip(1).copied().collect())
+        } else {
+            (0.0, beta.iter().copied().collect())
+        };
+
+        Ok(GeneralizedLinearModel { intercept, coeffs, indices, link: self.link, deviance })
+    }
+}
+
+impl Learner<f64> for GeneralizedLinearLearner {
+    fn fit(&self, data: &[TrainingRow<f64>], _rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.fit_model(data)?))
+    }
+}
+
+/// A model produced by a GLM learner.
+#[derive(Clone, Debug)]
+pub struct GeneralizedLinearModel {
+    intercept: f64,
+    coeffs: Vec<f64>,
+    indices: Vec<usize>,
+    link: GlmLink,
+    deviance: f64,
+}
+
+impl GeneralizedLinearModel {
+    /// The intercept on the linear predictor scale.
+    pub fn intercept(&self) -> f64 {
+        self.intercept
+    }
+
+    /// The coefficients on the linear predictor scale, paired with their feature indices.
+    pub fn coefficients(&self) -> Vec<(usize, f64)> {
+        self.indices.iter().copied().zip(self.coeffs.iter().copied()).collect()
+    }
+}
+
+impl Model<f64> for GeneralizedLinearModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        let result = inputs
+            .iter()
+            .map(|row| {
+                let eta = self.intercept
+                    + self.indices.iter().zip(self.coeffs.iter()).fold(0.0, |state, (&idx, c)| {
+                        state + c * row[idx].as_real().unwrap_or(f64::NAN)
+                    });
+                self.link.inverse(eta)
+            })
+            .collect();
+
+        Ok(Box::new(GeneralizedLinearPrediction { result }))
+    }
+
+    /// The weighted deviance of the model on its training data.
+    fn loss(&self) -> Option<f64> {
+        Some(self.deviance)
+    }
+}
+
+/// A prediction result for a GLM, on the mean response scale.
+#[derive(Clone, Debug)]
+pub struct GeneralizedLinearPrediction {
+    result: Vec<f64>,
+}
+
+impl Prediction<f64> for GeneralizedLinearPrediction {
+    fn expected(&self) -> Vec<f64> {
+        self.result.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn log_linear_data(ns: usize, rng: &mut impl Rng) -> Vec<TrainingRow<f64>> {
+        (0..ns)
+            .map(|_| {
+                let x: Vec<f64> = vec![rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)];
+                let y = (0.5 + 1.5 * x[0] - 0.5 * x[1]).exp();
+                TrainingRow::new(x, y, Some(rng.gen_range(0.5..1.5)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_poisson_recovers_coefficients() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data = log_linear_data(50, &mut rng);
+
+        let learner = GeneralizedLinearLearner::new(GlmFamily::Poisson, None, true, None);
+        let model = learner.fit_model(&data).unwrap();
+
+        let coeffs = model.coefficients();
+        assert!((model.intercept() - 0.5).abs() < 1e-6);
+        assert!((coeffs[0].1 - 1.5).abs() < 1e-6 && (coeffs[1].1 + 0.5).abs() < 1e-6);
+
+        // Noiseless labels are fit exactly, with zero deviance
+        let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
+        let predicted = model.transform(&features).unwrap().expected();
+        for (p, row) in predicted.iter().zip(data.iter()) {
+            assert!((p - row.label).abs() < 1e-6);
+        }
+        assert!(model.loss().unwrap() < 1e-8);
+    }
+
+    #[test]
+    fn test_tweedie_deviance_limits() {
+        let (y, mu) = (3.0, 2.0);
+        let poisson = GlmFamily::Poisson.unit_deviance(y, mu);
+        let gamma = GlmFamily::Gamma.unit_deviance(y, mu);
+
+        assert!((GlmFamily::Tweedie(1.0 + 1e-7).unit_deviance(y, mu) - poisson).abs() < 1e-5);
+        assert!((GlmFamily::Tweedie(2.0 - 1e-7).unit_deviance(y, mu) - gamma).abs() < 1e-5);
+        assert_eq!(GlmFamily::Tweedie(1.5).unit_deviance(y, y), 0.0);
+    }
+
+    #[test]
+    fn test_labels_outside_support() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data =
+            vec![TrainingRow::new(vec![1.0], -1.0, None), TrainingRow::new(vec![2.0], 1.0, None)];
+
+        let learner =
+            GeneralizedLinearLearner::new(GlmFamily::Gamma, Some(GlmLink::Log), true, None);
+        assert!(learner.fit(&data, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_degenerate_fits() {
+        let poisson =
+            |intercept| GeneralizedLinearLearner::new(GlmFamily::Poisson, None, intercept, None);
+
+        // Exactly collinear features leave a singular system, so the model predicts the mean
+        let data: Vec<TrainingRow<f64>> = (0..10)
+            .map(|i| TrainingRow::new(vec![i as f64, 2.0 * i as f64], 1.0 + i as f64, None))
+            .collect();
+        let model = poisson(true).fit_model(&data).unwrap();
+        assert!((model.intercept() - 5.5f64.ln()).abs() < 1e-12);
+        assert!(model.coefficients().iter().all(|(_, c)| *c == 0.0));
+
+        // Without an intercept, too few rows leave no term at all
+        let model = poisson(false).fit_model(&data[..2]).unwrap();
+        assert_eq!(model.intercept(), 0.0);
+
+        // A huge label overflows the log link on the first full step, which is halved instead
+        let data: Vec<TrainingRow<f64>> = (0..20)
+            .map(|i| TrainingRow::new(vec![i as f64], if i == 19 { 1e300 } else { 1.0 }, None))
+            .collect();
+        let model = poisson(true).fit_model(&data).unwrap();
+        assert!(model.loss().unwrap().is_finite());
+    }
+}
@@ -2,7 +2,58 @@ use itertools::{Itertools, MinMaxResult};
 use nalgebra::{DMatrix, DVector};
 use rand::Rng;
 
-use crate::core::{FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+use crate::core::{
+    fit_per_output, FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow,
+};
+
+/// Indices of the real features that are (1) non-constant and (2) finite for every row.
+pub(crate) fn included_real_features<T>(data: &[TrainingRow<T>]) -> Vec<usize> {
+    match data.first() {
+        None => vec![],
+        Some(head) => head
+            .features
+            .real_indices()
+            .into_iter()
+            .filter(|&idx| {
+                let feature_values: Vec<f64> = data
+                    .iter()
+                    .map(|row| row.features[idx].as_real().unwrap_or(f64::NAN))
+                    .collect();
+
+                let finite_values: Vec<f64> =
+                    feature_values.iter().filter(|x| x.is_finite()).copied().collect();
+                let non_finite = finite_values.len() != feature_values.len();
+
+                let is_constant = match finite_values.iter().minmax() {
+                    MinMaxResult::NoElements => true,
+                    MinMaxResult::OneElement(_) => true,
+                    MinMaxResult::MinMax(x, y) => x == y,
+                };
+
+                !(non_finite || is_constant)
+            })
+            .collect(),
+    }
+}
+
+/// Assemble the design matrix for the given feature indices, with an optional leading column of ones.
+pub(crate) fn design_matrix<'a>(
+    rows: impl ExactSizeIterator<Item = &'a FeatureRow>,
+    indices: &[usize],
+    intercept: bool,
+) -> DMatrix<f64> {
+    let (ns, offset) = (rows.len(), intercept as usize);
+    let mut X = DMatrix::zeros(ns, indices.len() + offset);
+    for (i, row) in rows.enumerate() {
+        if intercept {
+            X[(i, 0)] = 1.0;
+        }
+        for (j, &idx) in indices.iter().enumerate() {
+            X[(i, j + offset)] = row[idx].as_real().unwrap_or(f64::NAN);
+        }
+    }
+    X
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct LinearRegressionLearner {
@@ -15,20 +66,25 @@ impl LinearRegressionLearner {
         LinearRegressionLearner { intercept, alpha: alpha.unwrap_or(0.0).max(0.0) }
     }
 
-    fn solve_normal_equation(
+    /// Does the learner fit an intercept term?
+    pub fn fit_intercept(&self) -> bool {
+        self.intercept
+    }
+
+    pub(crate) fn solve_normal_equation(
         &self,
         X: &DMatrix<f64>,
         y: &DVector<f64>,
         w: &DVector<f64>,
     ) -> Result<DVector<f64>> {
         // Multiply by weight matrix by scaling X columns (after transpose)
-        let mut Xw = X.transpose().to_owned();
-        for (i, mut col) in Xw.columns_mut().into_iter().enumerate() {
+        let mut Xw = X.transpose();
+        for (i, mut col) in Xw.column_iter_mut().enumerate() {
             col *= w[i];
         }
 
-        let rhs = Xw.dot(y);
-        let mut lhs = Xw.dot(X);
+        let rhs = &Xw * y;
+        let mut lhs = &Xw * X;
 
         // Add reg param if present
         if self.alpha != 0.0 {
@@ -37,9 +93,9 @@ impl LinearRegressionLearner {
             }
         }
 
-        match lhs.solve_into(rhs) {
-            Ok(beta) => Ok(beta),
-            Err(_) => Err(ModelingError::FitError(
+        match lhs.lu().solve(&rhs) {
+            Some(beta) if beta.iter().all(|b| b.is_finite()) => Ok(beta),
+            _ => Err(ModelingError::SolutionError(
                 "Failure while inverting linear operator in normal equations.".into(),
             )),
         }
@@ -47,81 +103,51 @@ impl LinearRegressionLearner {
 }
 
 impl Learner<f64> for LinearRegressionLearner {
-    fn fit(&self, data: &[TrainingRow<f64>], rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
-        // Get real indices that are (1) non-constant and (2) non-NaN
-        let included_features: Vec<usize> = data[0]
-            .features
-            .real_indices()
-            .filter(|&idx| {
-                let feature_values: Vec<f64> = data
-                    .iter()
-                    .map(|row| row.features[idx].as_real().unwrap_or(f64::NAN))
-                    .collect();
-
-                let finite_values =
-                    feature_values.iter().filter(|x| x.is_finite()).copied().collect();
-                let non_finite = finite_values.len() != feature_values.len();
-
-                let is_constant = match finite_values.iter().minmax() {
-                    MinMaxResult::NoElements => true,
-                    MinMaxResult::OneElement(_) => true,
-                    MinMaxResult::MinMax(x, y) => x != y,
-                };
-
-                !(non_finite || is_constant)
-            })
-            .collect();
+    fn fit(&self, data: &[TrainingRow<f64>], _rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
 
-        // Collect data into matrices for fitting
-        // First collect data in vectors, then assemble into nalgebra matrices
+        let indices = included_real_features(data);
         let (ns, nf) = (data.len(), indices.len());
 
-        let mut x_data = Vec::with_capacity(ns * nf);
-        let mut y_data = Vec::with_capacity(ns);
-        let mut w_data = Vec::with_capacity(ns);
-        for row in data {
-            y_data.push(row.label);
-            w_data.push(row.weight);
-            for &idx in indices.iter() {
-                x_data.push(row.features.real_at(idx));
-            }
-        }
+        let y = DVector::from_iterator(ns, data.iter().map(|row| row.label));
+        let w = DVector::from_iterator(ns, data.iter().map(|row| row.weight.unwrap_or(1.0)));
 
         // If there are not enough data rows, return early with mean
         if ns <= nf {
-            let intercept = y_data.iter().sum::<f64>() / (ns as f64);
+            let intercept = y.mean();
             let coeffs = vec![0.0; nf];
-            return Box::new(LinearRegessionModel { intercept, coeffs, indices });
+            return Ok(Box::new(LinearRegessionModel { intercept, coeffs, indices }));
         }
 
-        let yvec = arr1(&y_data);
-        let wvec = arr1(&w_data);
-
-        let Xmat = Array2::from_shape_vec((ns, nf), x_data).expect("X has correct dimensions");
-        let Xmat = match self.intercept {
-            true => {
-                let intercept_col: Array<f64, _> = Array::ones((ns, 1));
-                concatenate![Axis(1), intercept_col, Xmat]
-            }
-            false => Xmat,
-        };
+        let X = design_matrix(data.iter().map(|row| &row.features), &indices, self.intercept);
 
-        let (intercept, coeffs) = match self.solve_normal_equation(&Xmat, &yvec, &wvec) {
+        let (intercept, coeffs) = match self.solve_normal_equation(&X, &y, &w) {
             Ok(beta) => {
-                if self.fit_intercept {
-                    (beta[0], beta.slice(s![1..]).to_vec())
+                if self.intercept {
+                    (beta[0], beta.iter().skip(1).copied().collect())
                 } else {
-                    (0.0, beta.to_vec())
+                    (0.0, beta.iter().copied().collect())
                 }
             }
-            Err(_) => {
-                let mean = yvec.mean().expect("Label vector has a valid mean.");
-                let zeros = vec![0.0; nf];
-                (mean, zeros)
-            }
+            Err(_) => (y.mean(), vec![0.0; nf]),
         };
 
-        Box::new(LinearRegessionModel { intercept, coeffs, indices })
+        Ok(Box::new(LinearRegessionModel { intercept, coeffs, indices }))
+    }
+}
+
+/// Fits an independent linear model to each output.
+impl Learner<Vec<f64>> for LinearRegressionLearner {
+    fn fit(
+        &self,
+        data: &[TrainingRow<Vec<f64>>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<Vec<f64>>>> {
+        fit_per_output(self, data, rng)
     }
 }
 
@@ -133,19 +159,18 @@ pub struct LinearRegessionModel {
 }
 
 impl Model<f64> for LinearRegessionModel {
-    fn transform(&self, inputs: &[FeatureRow]) -> Box<dyn Prediction<f64>> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
         let result = inputs
             .iter()
             .map(|row| {
                 self.intercept
-                    + self
-                        .indices
-                        .iter()
-                        .fold(0.0, |state, &idx| state + self.coeffs[idx] * row.real_at(idx))
+                    + self.indices.iter().zip(self.coeffs.iter()).fold(0.0, |state, (&idx, c)| {
+                        state + c * row[idx].as_real().unwrap_or(f64::NAN)
+                    })
             })
             .collect();
 
-        Box::new(LinearRegressionPrediction { result })
+        Ok(Box::new(LinearRegressionPrediction { result }))
     }
 }
 
@@ -162,22 +187,25 @@ impl Prediction<f64> for LinearRegressionPrediction {
 
 #[cfg(test)]
 mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
     use crate::utils::linear_training_data;
 
     use super::*;
 
     #[test]
     fn test_regression() {
+        let mut rng = StdRng::seed_from_u64(0);
         let ns = 10;
         let coeffs = &[1.0, 2.0, 3.0, 4.0];
-        let data = linear_training_data(ns, coeffs, 5.0);
+        let data = linear_training_data(ns, coeffs, 5.0, &mut rng);
 
         let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
         let labels: Vec<_> = data.iter().map(|row| row.label).collect();
 
-        let mut learner = LinearRegressionLearner::new(true, None);
-        let model = learner.fit(&data);
-        let output = model.transform(&features);
+        let learner = LinearRegressionLearner::new(true, None);
+        let model = learner.fit(&data, &mut rng).unwrap();
+        let output = model.transform(&features).unwrap();
         let predicted = output.expected();
 
         let error: f64 = predicted.iter().zip(labels.iter()).map(|(p, y)| (p - y).abs()).sum();
@@ -187,18 +215,40 @@ mod tests {
 
     #[test]
     fn test_underconstrained() {
+        let mut rng = StdRng::seed_from_u64(0);
         let ns = 3; // Only 3 samples
         let coeffs = &[1.0, 2.0, 3.0, 4.0]; // But 4 features + intercept
-        let data = linear_training_data(ns, coeffs, 0.0);
+        let data = linear_training_data(ns, coeffs, 0.0, &mut rng);
 
         let features: Vec<_> = data.iter().map(|row| row.features.clone()).collect();
         let mean = data.iter().map(|row| row.label).sum::<f64>() / (ns as f64);
 
-        let mut learner = LinearRegressionLearner::new(true, None);
-        let model = learner.fit(&data);
-        let output = model.transform(&features);
+        let learner = LinearRegressionLearner::new(true, None);
+        let model = learner.fit(&data, &mut rng).unwrap();
+        let output = model.transform(&features).unwrap();
         let predicted = output.expected();
 
         predicted.iter().for_each(|p| assert!((p - mean).abs() < 1e-9))
     }
+
+    #[test]
+    fn test_multiple_outputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let first = linear_training_data(20, &[1.0, 2.0], 5.0, &mut rng);
+        let data: Vec<TrainingRow<Vec<f64>>> = first
+            .iter()
+            .map(|row| {
+                let x = row.features[0].as_real().unwrap();
+                TrainingRow::new(row.features.clone(), vec![row.label, -3.0 * x], row.weight)
+            })
+            .collect();
+        let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
+
+        let learner = LinearRegressionLearner::new(true, None);
+        let predicted = learner.fit(&data, &mut rng).unwrap().transform(&features).unwrap();
+        for (p, row) in predicted.expected().iter().zip(data.iter()) {
+            assert_eq!(p.len(), 2);
+            assert!((p[0] - row.label[0]).abs() < 1e-9 && (p[1] - row.label[1]).abs() < 1e-9);
+        }
+    }
 }
@@ -1,13 +1,61 @@
-use rand::prelude::SliceRandom;
-use rand::Rng;
-use std::collections::HashMap;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::prelude::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
 
-use crate::core::Result;
-use crate::core::{FeatureRow, Learner, Model, Prediction, TrainingRow};
+use crate::core::{
+    fit_per_output, EmpiricalDistribution, FeatureRow, Learner, Model, Prediction, TrainingRow,
+};
+use crate::core::{ModelingError, Result};
 
-/// A learner that calculates the mean of the labels
+/// The statistic of the labels predicted by a regression baseline.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RegressionBaseline {
+    /// The weighted mean of the labels.
+    #[default]
+    Mean,
+    /// The weighted median of the labels.
+    Median,
+    /// The weighted quantile of the labels, with the quantile in [0, 1].
+    Quantile(f64),
+}
+
+/// The rule used by a classification baseline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClassificationBaseline {
+    /// Predict the class with the largest weight, with a one-hot probability.
+    #[default]
+    Mode,
+    /// Predict the class with the largest weight, with the class priors as probabilities.
+    Prior,
+    /// Predict classes drawn at random from the class priors.
+    Stratified,
+}
+
+/// A learner that calculates a summary statistic of the labels
 #[derive(Clone, Copy, Debug, Default)]
-pub struct GuessTheMeanLearner {}
+pub struct GuessTheMeanLearner {
+    regression: RegressionBaseline,
+    classification: ClassificationBaseline,
+}
+
+impl GuessTheMeanLearner {
+    pub fn new(regression: RegressionBaseline, classification: ClassificationBaseline) -> Self {
+        Self { regression, classification }
+    }
+
+    /// The statistic predicted for real-valued labels.
+    pub fn with_regression(mut self, regression: RegressionBaseline) -> Self {
+        self.regression = regression;
+        self
+    }
+
+    /// The rule used for categorical labels.
+    pub fn with_classification(mut self, classification: ClassificationBaseline) -> Self {
+        self.classification = classification;
+        self
+    }
+}
 
 impl Learner<f64> for GuessTheMeanLearner {
     fn fit(&self, data: &[TrainingRow<f64>], _rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
@@ -15,7 +63,34 @@ impl Learner<f64> for GuessTheMeanLearner {
             let row_weight = row.weight.unwrap_or(1.0);
             (sum + row_weight * row.label, weight + row_weight)
         });
-        Ok(Box::new(GuessTheMeanModel { mean: sums.0 / sums.1 }))
+        let mean = sums.0 / sums.1;
+        let variance = data
+            .iter()
+            .map(|row| row.weight.unwrap_or(1.0) * (row.label - mean).powi(2))
+            .sum::<f64>()
+            / sums.1;
+
+        let labels = EmpiricalDistribution::new(
+            data.iter().map(|row| (row.label, row.weight.unwrap_or(1.0))),
+        );
+        let guess = match self.regression {
+            RegressionBaseline::Mean => mean,
+            RegressionBaseline::Median => labels.quantile(0.5),
+            RegressionBaseline::Quantile(q) => labels.quantile(q),
+        };
+
+        Ok(Box::new(GuessTheMeanModel { mean: guess, std_dev: variance.sqrt() }))
+    }
+}
+
+/// Fits the regression baseline independently to each output.
+impl Learner<Vec<f64>> for GuessTheMeanLearner {
+    fn fit(
+        &self,
+        data: &[TrainingRow<Vec<f64>>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<Vec<f64>>>> {
+        fit_per_output(self, data, rng)
     }
 }
 
@@ -25,94 +100,244 @@ impl Learner<usize> for GuessTheMeanLearner {
         data: &[TrainingRow<usize>],
         rng: &mut impl Rng,
     ) -> Result<Box<dyn Model<usize>>> {
-        let mut labels: Vec<(usize, f64)> =
-            data.iter().map(|row| (row.label, row.weight.unwrap_or(1.0))).collect();
-        labels.shuffle(rng);
-
-        let mut weight_sums = HashMap::new();
-        for (label, weight) in labels {
-            *weight_sums.entry(label).or_insert(0.0) += weight;
+        let mut weight_sums = BTreeMap::new();
+        for row in data.iter() {
+            *weight_sums.entry(row.label).or_insert(0.0) += row.weight.unwrap_or(1.0);
         }
 
+        // Ties go to the smallest label, the first of the heaviest in label order
         let mean_label = weight_sums
-            .into_iter()
-            .max_by(|(_, wa), (_, wb)| wa.partial_cmp(wb).unwrap())
-            .map(|(k, _)| k)
-            .unwrap();
+            .iter()
+            .min_by(|(_, wa), (_, wb)| wb.total_cmp(wa))
+            .map(|(k, _)| *k)
+            .ok_or_else(|| {
+                ModelingError::FitError("Cannot fit a model without training data.".into())
+            })?;
+
+        let total: f64 = weight_sums.values().sum();
+        let mut priors = vec![0.0; weight_sums.keys().max().map_or(0, |&k| k + 1)];
+        weight_sums.into_iter().for_each(|(label, weight)| priors[label] = weight / total);
 
-        Ok(Box::new(GuessTheMeanModel { mean: mean_label }))
+        Ok(Box::new(GuessTheClassModel {
+            mode: mean_label,
+            priors,
+            classification: self.classification,
+            seed: rng.gen(),
+        }))
     }
 }
 
-/// A model produced by a GuessTheMean learner
+/// A regression model produced by a GuessTheMean learner
 #[derive(Clone, Copy, Debug)]
-pub struct GuessTheMeanModel<T> {
-    mean: T,
+pub struct GuessTheMeanModel {
+    mean: f64,
+    std_dev: f64,
 }
 
-impl Model<f64> for GuessTheMeanModel<f64> {
+impl Model<f64> for GuessTheMeanModel {
     fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
-        Ok(Box::new(GuessTheMeanPrediction { result: vec![self.mean; inputs.len()] }))
+        Ok(Box::new(GuessTheMeanPrediction {
+            result: vec![self.mean; inputs.len()],
+            uncertainty: Some(vec![self.std_dev; inputs.len()]),
+            probabilities: None,
+        }))
     }
 
     fn loss(&self) -> Option<f64> {
         None
     }
+
+    fn constant_outputs(&self) -> Option<Vec<f64>> {
+        Some(vec![self.mean])
+    }
 }
 
-impl Model<usize> for GuessTheMeanModel<usize> {
+/// A classification model produced by a GuessTheMean learner
+#[derive(Clone, Debug)]
+pub struct GuessTheClassModel {
+    mode: usize,
+    priors: Vec<f64>,
+    classification: ClassificationBaseline,
+    seed: u64,
+}
+
+impl Model<usize> for GuessTheClassModel {
     fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<usize>>> {
-        Ok(Box::new(GuessTheMeanPrediction { result: vec![self.mean; inputs.len()] }))
+        let one_hot = |label: usize| {
+            let mut p = vec![0.0; self.priors.len()];
+            p[label] = 1.0;
+            p
+        };
+
+        let (result, probabilities) = match self.classification {
+            ClassificationBaseline::Mode => {
+                (vec![self.mode; inputs.len()], vec![one_hot(self.mode); inputs.len()])
+            }
+            ClassificationBaseline::Prior => {
+                (vec![self.mode; inputs.len()], vec![self.priors.clone(); inputs.len()])
+            }
+            ClassificationBaseline::Stratified => {
+                // Draws are reseeded per call, so repeated transforms agree
+                let mut rng = StdRng::seed_from_u64(self.seed);
+                let dist = WeightedIndex::new(&self.priors).map_err(|e| {
+                    ModelingError::TransformError(format!("Invalid class priors: {}", e).into())
+                })?;
+                let result: Vec<usize> = (0..inputs.len()).map(|_| dist.sample(&mut rng)).collect();
+                let probabilities = result.iter().map(|&label| one_hot(label)).collect();
+                (result, probabilities)
+            }
+        };
+
+        Ok(Box::new(GuessTheMeanPrediction {
+            result,
+            uncertainty: None,
+            probabilities: Some(probabilities),
+        }))
     }
 
     fn loss(&self) -> Option<f64> {
         None
     }
+
+    /// The class probabilities, except for stratified draws which vary between rows.
+    fn constant_outputs(&self) -> Option<Vec<f64>> {
+        match self.classification {
+            ClassificationBaseline::Mode => {
+                let mut p = vec![0.0; self.priors.len()];
+                p[self.mode] = 1.0;
+                Some(p)
+            }
+            ClassificationBaseline::Prior => Some(self.priors.clone()),
+            ClassificationBaseline::Stratified => None,
+        }
+    }
 }
 
 /// A prediction result for a GuessTheMean learner
 #[derive(Clone, Debug)]
 pub struct GuessTheMeanPrediction<T> {
     result: Vec<T>,
+    uncertainty: Option<Vec<T>>,
+    probabilities: Option<Vec<Vec<f64>>>,
 }
 
 impl Prediction<f64> for GuessTheMeanPrediction<f64> {
     fn expected(&self) -> Vec<f64> {
         self.result.clone()
     }
+
+    /// The weighted standard deviation of the training labels.
+    fn uncertainty(&self) -> Option<Vec<f64>> {
+        self.uncertainty.clone()
+    }
 }
 
 impl Prediction<usize> for GuessTheMeanPrediction<usize> {
     fn expected(&self) -> Vec<usize> {
         self.result.clone()
     }
+
+    fn probabilities(&self) -> Option<Vec<Vec<f64>>> {
+        self.probabilities.clone()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    // use crate::utils::random_training_data;
+    use rand::rngs::StdRng;
 
-    // use super::*;
+    use crate::utils::random_training_data;
 
-    // #[test]
-    // fn test_categorical() {
-    //     let ns = 5;
-    //     let mut data = random_training_data::<usize>(ns, 1, 1);
-    //     let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
+    use super::*;
 
-    //     // Modify data so class of first element is largest weight
-    //     let weight_sum: f64 = data.iter().map(|row| row.weight).sum();
+    #[test]
+    fn test_categorical() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let ns = 5;
+        let mut data = random_training_data::<usize>(ns, 1, 1, &mut rng);
+        let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
 
-    //     let head = &data[0];
-    //     let head_label = head.label;
-    //     let adjusted_weight = head.weight + weight_sum;
-    //     data[0] = TrainingRow::from_features(head.features.clone(), head.label, adjusted_weight);
+        // Modify data so class of first element is largest weight
+        let weight_sum: f64 = data.iter().map(|row| row.weight.unwrap()).sum();
 
-    //     let mut learner = GuessTheMeanLearner::new(None);
-    //     let model = learner.fit(&data);
-    //     let output = model.transform(&features);
-    //     let predicted = output.expected();
+        let head = &data[0];
+        let head_label = head.label;
+        let adjusted_weight = head.weight.unwrap() + weight_sum;
+        data[0] = TrainingRow::new(head.features.clone(), head.label, Some(adjusted_weight));
+
+        let learner = GuessTheMeanLearner::default();
+        let model = learner.fit(&data, &mut rng).unwrap();
+        let output = model.transform(&features).unwrap();
+        let predicted = output.expected();
+
+        predicted.into_iter().for_each(|p| assert!(p == head_label));
+    }
 
-    //     predicted.into_iter().for_each(|p| assert!(p == head_label));
-    // }
+    #[test]
+    fn test_regression_baselines() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<_> = [1.0, 2.0, 3.0, 10.0]
+            .iter()
+            .map(|&y| TrainingRow::new(vec![0.0], y, Some(1.0)))
+            .collect();
+        let features = vec![data[0].features.clone()];
+
+        let expect = |baseline: RegressionBaseline, rng: &mut StdRng| {
+            let learner = GuessTheMeanLearner::default().with_regression(baseline);
+            learner.fit(&data, rng).unwrap().transform(&features).unwrap().expected()[0]
+        };
+        assert_eq!(expect(RegressionBaseline::Mean, &mut rng), 4.0);
+        assert_eq!(expect(RegressionBaseline::Median, &mut rng), 2.0);
+        assert_eq!(expect(RegressionBaseline::Quantile(0.9), &mut rng), 10.0);
+
+        let model = GuessTheMeanLearner::default().fit(&data, &mut rng).unwrap();
+        let uncertainty = model.transform(&features).unwrap().uncertainty().unwrap();
+        assert_eq!(uncertainty, vec![(12.5f64).sqrt()]);
+    }
+
+    #[test]
+    fn test_classification_baselines() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<_> =
+            [1, 1, 1, 2].iter().map(|&y| TrainingRow::new(vec![0.0], y, None)).collect();
+        let features: Vec<FeatureRow> = (0..1000).map(|_| data[0].features.clone()).collect();
+
+        let prior =
+            GuessTheMeanLearner::default().with_classification(ClassificationBaseline::Prior);
+        let output = prior.fit(&data, &mut rng).unwrap().transform(&features).unwrap();
+        assert!(output.expected().iter().all(|&p| p == 1));
+        assert_eq!(output.probabilities().unwrap()[0], vec![0.0, 0.75, 0.25]);
+
+        let stratified =
+            GuessTheMeanLearner::default().with_classification(ClassificationBaseline::Stratified);
+        let model = stratified.fit(&data, &mut rng).unwrap();
+        let predicted = model.transform(&features).unwrap().expected();
+        let ones = predicted.iter().filter(|&&p| p == 1).count();
+        assert!(ones > 700 && ones < 800);
+        assert_eq!(predicted, model.transform(&features).unwrap().expected());
+
+        // Tied classes resolve to the smallest label, whatever the seed
+        let tied: Vec<_> =
+            [3, 2, 3, 2, 1].iter().map(|&y| TrainingRow::new(vec![0.0], y, None)).collect();
+        for seed in 0..5 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let model = GuessTheMeanLearner::default().fit(&tied, &mut rng).unwrap();
+            assert_eq!(model.transform(&features[..1]).unwrap().expected(), vec![2]);
+        }
+    }
+
+    #[test]
+    fn test_multiple_outputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<_> = [(1.0, 10.0), (3.0, 20.0)]
+            .iter()
+            .map(|&(a, b)| TrainingRow::new(vec![0.0], vec![a, b], None))
+            .collect();
+        let features = vec![data[0].features.clone()];
+
+        let output = GuessTheMeanLearner::default().fit(&data, &mut rng).unwrap();
+        let prediction = output.transform(&features).unwrap();
+        assert_eq!(prediction.expected(), vec![vec![2.0, 15.0]]);
+        assert_eq!(prediction.uncertainty().unwrap(), vec![vec![1.0, 5.0]]);
+    }
 }
@@ -1,5 +1,13 @@
+mod gam;
+mod glm;
 mod linear;
 mod mean;
+mod pls;
+mod robust;
 
+pub use self::gam::*;
+pub use self::glm::*;
 pub use self::linear::*;
 pub use self::mean::*;
+pub use self::pls::*;
+pub use self::robust::*;
@@ -0,0 +1,426 @@
+use nalgebra::{DMatrix, DVector};
+use rand::Rng;
+
+use super::{design_matrix, included_real_features};
+use crate::core::{FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+
+/// Weighted column means and standard deviations, with unit scales for constant columns.
+fn column_moments(M: &DMatrix<f64>, w: &DVector<f64>, scale: bool) -> (DVector<f64>, DVector<f64>) {
+    let total = w.sum();
+    let means = DVector::from_iterator(M.ncols(), M.column_iter().map(|c| c.dot(w) / total));
+    let scales = DVector::from_iterator(
+        M.ncols(),
+        M.column_iter().zip(means.iter()).map(|(c, m)| {
+            let var = c.iter().zip(w.iter()).map(|(x, w)| w * (x - m).powi(2)).sum::<f64>() / total;
+            if scale && var > 0.0 {
+                var.sqrt()
+            } else {
+                1.0
+            }
+        }),
+    );
+    (means, scales)
+}
+
+/// Center and scale the columns of a matrix, then multiply each row by the root of its weight.
+fn standardize(
+    mut M: DMatrix<f64>,
+    means: &DVector<f64>,
+    scales: &DVector<f64>,
+    w: &DVector<f64>,
+) -> DMatrix<f64> {
+    for (j, mut col) in M.column_iter_mut().enumerate() {
+        for (i, x) in col.iter_mut().enumerate() {
+            *x = (*x - means[j]) / scales[j] * w[i].sqrt();
+        }
+    }
+    M
+}
+
+/// A partial least squares regression learner using the NIPALS algorithm.
+///
+/// Supports both single targets (`f64` labels) and multiple targets (`Vec<f64>` labels).
+#[derive(Clone, Copy, Debug)]
+pub struct PlsRegressionLearner {
+    num_components: usize,
+    scale: bool,
+    max_iter: usize,
+    tolerance: f64,
+}
+
+impl PlsRegressionLearner {
+    pub fn new(num_components: usize) -> Self {
+        Self { num_components, scale: true, max_iter: 500, tolerance: 1e-10 }
+    }
+
+    /// Whether to scale features and targets to unit variance before fitting.
+    pub fn with_scale(mut self, scale: bool) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// The maximum number of NIPALS iterations per component.
+    pub fn with_max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// The change in the weight vector at which NIPALS iterations stop.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(&self, data: &[TrainingRow<Vec<f64>>]) -> Result<PlsRegressionModel> {
+        let nt = data.first().map_or(0, |row| row.label.len());
+        if data.iter().any(|row| row.label.len() != nt) {
+            return Err(ModelingError::FitError(
+                "All training rows must have the same number of targets.".into(),
+            ));
+        }
+
+        let Y = DMatrix::from_fn(data.len(), nt, |i, j| data[i].label[j]);
+        self.build_model(data, Y)
+    }
+
+    fn build_model<T>(
+        &self,
+        data: &[TrainingRow<T>],
+        Y: DMatrix<f64>,
+    ) -> Result<PlsRegressionModel> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+        if self.num_components == 0 {
+            return Err(ModelingError::FitError(
+                "The number of components must be positive.".into(),
+            ));
+        }
+
+        if Y.ncols() == 0 {
+            return Err(ModelingError::FitError(
+                "The labels must have at least one target.".into(),
+            ));
+        }
+
+        let indices = included_real_features(data);
+        let (ns, nf, nt) = (data.len(), indices.len(), Y.ncols());
+        let nc = self.num_components.min(nf).min(ns.saturating_sub(1));
+
+        let w = DVector::from_iterator(ns, data.iter().map(|row| row.weight.unwrap_or(1.0)));
+        let X = design_matrix(data.iter().map(|row| &row.features), &indices, false);
+        let (x_means, x_scales) = column_moments(&X, &w, self.scale);
+        let (y_means, y_scales) = column_moments(&Y, &w, self.scale);
+        let mut Xk = standardize(X.clone(), &x_means, &x_scales, &w);
+        let mut Yk = standardize(Y, &y_means, &y_scales, &w);
+
+        let (mut weight_cols, mut loading_cols, mut target_cols) = (vec![], vec![], vec![]);
+        let mut explained = vec![];
+
+        for _ in 0..nc {
+            // Start from the target column with the largest remaining variance
+            let start = (0..nt)
+                .max_by(|&i, &j| {
+                    Yk.column(i).norm_squared().total_cmp(&Yk.column(j).norm_squared())
+                })
+                .unwrap_or(0);
+            let mut u: DVector<f64> = Yk.column(start).into_owned();
+            let mut x_weight = DVector::zeros(nf);
+            let mut t = DVector::zeros(ns);
+            let mut c = DVector::zeros(nt);
+
+            for _ in 0..self.max_iter {
+                let mut next = Xk.transpose() * &u;
+                let norm = next.norm();
+                if norm == 0.0 {
+                    break;
+                }
+                next /= norm;
+                t = &Xk * &next;
+                c = Yk.transpose() * &t / t.norm_squared();
+                u = &Yk * &c / c.norm_squared().max(f64::MIN_POSITIVE);
+
+                let change = (&next - &x_weight).norm();
+                x_weight = next;
+                if change < self.tolerance || nt == 1 {
+                    break;
+                }
+            }
+
+            // Stop early once the features are exhausted
+            let tt = t.norm_squared();
+            if tt == 0.0 {
+                break;
+            }
+            let p = Xk.transpose() * &t / tt;
+            Xk -= &t * p.transpose();
+            Yk -= &t * c.transpose();
+
+            explained.push(c.norm_squared() * tt);
+            weight_cols.push(x_weight);
+            loading_cols.push(p);
+            target_cols.push(c);
+        }
+
+        if explained.is_empty() {
+            return Err(ModelingError::FitError(
+                "No components can be extracted, which needs two rows and a varying feature."
+                    .into(),
+            ));
+        }
+        let x_weights = DMatrix::from_columns(&weight_cols);
+        let x_loadings = DMatrix::from_columns(&loading_cols);
+        let y_loadings = DMatrix::from_columns(&target_cols);
+
+        // Rotations map standardized features directly onto the component scores
+        let rotations = match (x_loadings.transpose() * &x_weights).try_inverse() {
+            Some(inverse) => &x_weights * inverse,
+            None => {
+                return Err(ModelingError::SolutionError(
+                    "Failure while inverting the PLS loading products.".into(),
+                ))
+            }
+        };
+        let ones = DVector::from_element(ns, 1.0);
+        let x_scores = standardize(X, &x_means, &x_scales, &ones) * &rotations;
+        let coefficients = &rotations * y_loadings.transpose();
+
+        // Variable importance in projection, from the target variance explained per component
+        let total_explained: f64 = explained.iter().sum();
+        let vip = (0..nf)
+            .map(|j| {
+                let importance: f64 = explained
+                    .iter()
+                    .enumerate()
+                    .map(|(a, e)| e * (x_weights[(j, a)] / x_weights.column(a).norm()).powi(2))
+                    .sum();
+                // Constant targets leave nothing explained, and no feature is important
+                if total_explained > 0.0 {
+                    (nf as f64 * importance / total_explained).sqrt()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        Ok(PlsRegressionModel {
+            indices,
+            x_means,
+            x_scales,
+            y_means,
+            y_scales,
+            coefficients,
+            rotations,
+            x_weights,
+            x_loadings,
+            y_loadings,
+            x_scores,
+            vip,
+        })
+    }
+}
+
+impl Learner<f64> for PlsRegressionLearner {
+    fn fit(&self, data: &[TrainingRow<f64>], _rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        let Y = DMatrix::from_iterator(data.len(), 1, data.iter().map(|row| row.label));
+        Ok(Box::new(self.build_model(data, Y)?))
+    }
+}
+
+impl Learner<Vec<f64>> for PlsRegressionLearner {
+    fn fit(
+        &self,
+        data: &[TrainingRow<Vec<f64>>],
+        _rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<Vec<f64>>>> {
+        Ok(Box::new(self.fit_model(data)?))
+    }
+}
+
+/// A model produced by a partial least squares learner.
+///
+/// Matrices are expressed in the standardized feature and target space, with one column per component.
+#[derive(Clone, Debug)]
+pub struct PlsRegressionModel {
+    indices: Vec<usize>,
+    x_means: DVector<f64>,
+    x_scales: DVector<f64>,
+    y_means: DVector<f64>,
+    y_scales: DVector<f64>,
+    coefficients: DMatrix<f64>,
+    rotations: DMatrix<f64>,
+    x_weights: DMatrix<f64>,
+    x_loadings: DMatrix<f64>,
+    y_loadings: DMatrix<f64>,
+    x_scores: DMatrix<f64>,
+    vip: Vec<f64>,
+}
+
+impl PlsRegressionModel {
+    /// The indices of the real features used by the model, in the row order of the feature matrices.
+    pub fn feature_indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// The number of fitted components.
+    pub fn num_components(&self) -> usize {
+        self.x_weights.ncols()
+    }
+
+    /// The feature weights of each component.
+    pub fn x_weights(&self) -> &DMatrix<f64> {
+        &self.x_weights
+    }
+
+    /// The feature loadings of each component.
+    pub fn x_loadings(&self) -> &DMatrix<f64> {
+        &self.x_loadings
+    }
+
+    /// The target loadings of each component.
+    pub fn y_loadings(&self) -> &DMatrix<f64> {
+        &self.y_loadings
+    }
+
+    /// The component scores of the training rows.
+    pub fn x_scores(&self) -> &DMatrix<f64> {
+        &self.x_scores
+    }
+
+    /// The variable importance in projection of each feature, in the order of `feature_indices`.
+    pub fn vip(&self) -> &[f64] {
+        &self.vip
+    }
+
+    /// The component scores of new rows.
+    pub fn scores(&self, inputs: &[FeatureRow]) -> DMatrix<f64> {
+        self.standardized(inputs) * &self.rotations
+    }
+
+    fn standardized(&self, inputs: &[FeatureRow]) -> DMatrix<f64> {
+        let X = design_matrix(inputs.iter(), &self.indices, false);
+        let ones = DVector::from_element(inputs.len(), 1.0);
+        standardize(X, &self.x_means, &self.x_scales, &ones)
+    }
+
+    fn predict(&self, inputs: &[FeatureRow]) -> DMatrix<f64> {
+        let mut Y = self.standardized(inputs) * &self.coefficients;
+        for (j, mut col) in Y.column_iter_mut().enumerate() {
+            col.iter_mut().for_each(|y| *y = *y * self.y_scales[j] + self.y_means[j]);
+        }
+        Y
+    }
+}
+
+impl Model<f64> for PlsRegressionModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        let result = self.predict(inputs).column(0).iter().copied().collect();
+        Ok(Box::new(PlsRegressionPrediction { result }))
+    }
+}
+
+impl Model<Vec<f64>> for PlsRegressionModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<Vec<f64>>>> {
+        let result =
+            self.predict(inputs).row_iter().map(|row| row.iter().copied().collect()).collect();
+        Ok(Box::new(PlsRegressionPrediction { result }))
+    }
+}
+
+/// A prediction result for a partial least squares model.
+#[derive(Clone, Debug)]
+pub struct PlsRegressionPrediction<T> {
+    result: Vec<T>,
+}
+
+impl<T: Clone> Prediction<T> for PlsRegressionPrediction<T> {
+    fn expected(&self) -> Vec<T> {
+        self.result.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_distr::{Distribution, Normal};
+
+    use super::*;
+
+    /// Rows with three latent factors spread over twelve collinear features and two targets.
+    fn collinear_data(ns: usize, rng: &mut StdRng) -> Vec<TrainingRow<Vec<f64>>> {
+        let noise = Normal::new(0.0, 0.01).unwrap();
+        (0..ns)
+            .map(|_| {
+                let z: Vec<f64> = (0..3).map(|_| rng.gen_range(-1.0..1.0)).collect();
+                let x: Vec<f64> =
+                    (0..12).map(|j| z[j % 3] * (1.0 + j as f64) + noise.sample(rng)).collect();
+                let y = vec![2.0 * z[0] - z[1] + 1.0, z[1] + 3.0 * z[2]];
+                TrainingRow::new(x, y, Some(rng.gen_range(0.5..2.0)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_multiple_targets() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data = collinear_data(200, &mut rng);
+        let test = collinear_data(20, &mut rng);
+        let inputs: Vec<FeatureRow> = test.iter().map(|row| row.features.clone()).collect();
+
+        let model = PlsRegressionLearner::new(3).fit_model(&data).unwrap();
+        assert_eq!(model.num_components(), 3);
+        assert_eq!(model.x_scores().shape(), (200, 3));
+        assert_eq!(model.y_loadings().shape(), (2, 3));
+
+        let predicted = Model::<Vec<f64>>::transform(&model, &inputs).unwrap().expected();
+        for (p, row) in predicted.iter().zip(test.iter()) {
+            assert!((p[0] - row.label[0]).abs() < 0.05 && (p[1] - row.label[1]).abs() < 0.05);
+        }
+
+        // Training scores are uncorrelated across components
+        let scores = model.x_scores();
+        let gram = scores.transpose() * scores;
+        assert!(gram[(0, 1)].abs() / gram[(0, 0)] < 0.1);
+
+        let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
+        assert!((model.scores(&features) - scores).norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_vip_single_target() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<TrainingRow<f64>> = (0..100)
+            .map(|_| {
+                let x: Vec<f64> = (0..4).map(|_| rng.gen_range(-1.0..1.0)).collect();
+                TrainingRow::new(x.clone(), 3.0 * x[0] + 0.1 * x[1], None)
+            })
+            .collect();
+
+        let multi: Vec<TrainingRow<Vec<f64>>> = data
+            .iter()
+            .map(|row| TrainingRow::new(row.features.clone(), vec![row.label], None))
+            .collect();
+        let model = PlsRegressionLearner::new(2).fit_model(&multi).unwrap();
+
+        // The mean squared VIP score is one
+        let vip = model.vip();
+        assert!((vip.iter().map(|v| v * v).sum::<f64>() / 4.0 - 1.0).abs() < 1e-9);
+        assert!(vip[0] > 1.0 && vip[2] < 1.0 && vip[3] < 1.0);
+
+        let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
+        let single = PlsRegressionLearner::new(2).fit(&data, &mut rng).unwrap();
+        let expected = single.transform(&features).unwrap().expected();
+        let error = expected.iter().zip(data.iter()).map(|(p, row)| (p - row.label).abs());
+        assert!(error.fold(0.0, f64::max) < 0.05);
+
+        // Without targets or enough rows for a component there is no model to fit
+        let learner = PlsRegressionLearner::new(2);
+        let no_targets: Vec<TrainingRow<Vec<f64>>> =
+            multi.iter().map(|row| TrainingRow::new(row.features.clone(), vec![], None)).collect();
+        assert!(learner.fit_model(&no_targets).is_err());
+        assert!(learner.fit_model(&multi[..1]).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+use itertools::Itertools;
+use nalgebra::DVector;
+use rand::Rng;
+
+use super::linear::{design_matrix, included_real_features};
+use super::LinearRegressionLearner;
+use crate::core::{FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+
+/// Scale factor making the MAD a consistent estimator of the standard deviation for normal errors.
+const MAD_NORMAL_SCALE: f64 = 0.6744897501960817;
+
+/// Median of a non-empty slice of finite values.
+fn median(values: &[f64]) -> f64 {
+    let sorted: Vec<f64> =
+        values.iter().copied().sorted_by(|a, b| a.partial_cmp(b).unwrap()).collect();
+    let n = sorted.len();
+    if n % 2 == 1 {
+        sorted[n / 2]
+    } else {
+        0.5 * (sorted[n / 2 - 1] + sorted[n / 2])
+    }
+}
+
+/// Robust estimate of the residual scale from the median absolute deviation about zero.
+fn mad_scale(residuals: &[f64]) -> f64 {
+    let deviations: Vec<f64> = residuals.iter().map(|r| r.abs()).collect();
+    median(&deviations) / MAD_NORMAL_SCALE
+}
+
+/// Weight function used to downweight observations with large residuals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RobustLoss {
+    /// Huber loss with the given tuning constant, in units of the residual scale.
+    Huber(f64),
+    /// Tukey bisquare loss with the given tuning constant, in units of the residual scale.
+    Tukey(f64),
+}
+
+impl RobustLoss {
+    /// Huber loss with 95% efficiency for normal errors.
+    pub fn huber() -> Self {
+        Self::Huber(1.345)
+    }
+
+    /// Tukey bisquare loss with 95% efficiency for normal errors.
+    pub fn tukey() -> Self {
+        Self::Tukey(4.685)
+    }
+
+    /// The IRLS weight for a residual standardized by the scale estimate.
+    pub fn weight(&self, u: f64) -> f64 {
+        match self {
+            Self::Huber(c) => {
+                if u.abs() <= *c {
+                    1.0
+                } else {
+                    c / u.abs()
+                }
+            }
+            Self::Tukey(c) => {
+                if u.abs() < *c {
+                    (1.0 - (u / c).powi(2)).powi(2)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// A linear regression learner that is robust to outliers, fit by iteratively reweighted least squares.
+#[derive(Clone, Copy, Debug)]
+pub struct RobustLinearLearner {
+    learner: LinearRegressionLearner,
+    loss: RobustLoss,
+    max_iter: usize,
+    tolerance: f64,
+}
+
+impl RobustLinearLearner {
+    pub fn new(learner: LinearRegressionLearner, loss: RobustLoss) -> Self {
+        Self { learner, loss, max_iter: 50, tolerance: 1e-8 }
+    }
+
+    /// The maximum number of IRLS iterations.
+    pub fn with_max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// The relative change in coefficients below which IRLS has converged.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(&self, data: &[TrainingRow<f64>]) -> Result<RobustLinearModel> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+
+        let intercept = self.learner.fit_intercept();
+        let indices = included_real_features(data);
+        let (ns, nf) = (data.len(), indices.len());
+
+        let y = DVector::from_iterator(ns, data.iter().map(|row| row.label));
+        let w = DVector::from_iterator(ns, data.iter().map(|row| row.weight.unwrap_or(1.0)));
+
+        // The median of the labels, for when there is no linear fit
+        let median_model = |indices: Vec<usize>| {
+            let labels: Vec<f64> = y.iter().copied().collect();
+            let center = median(&labels);
+            let residuals: Vec<f64> = labels.iter().map(|yi| yi - center).collect();
+            RobustLinearModel {
+                intercept: center,
+                coeffs: vec![0.0; nf],
+                indices,
+                scale: mad_scale(&residuals),
+                robustness_weights: vec![1.0; ns],
+            }
+        };
+
+        // If there are not enough data rows, return early with the median
+        if ns <= nf {
+            return Ok(median_model(indices));
+        }
+
+        // Start from the ordinary least squares solution, or the median if it is singular
+        let X = design_matrix(data.iter().map(|row| &row.features), &indices, intercept);
+        let mut beta = match self.learner.solve_normal_equation(&X, &y, &w) {
+            Ok(beta) => beta,
+            Err(_) => return Ok(median_model(indices)),
+        };
+        let mut robustness_weights = vec![1.0; ns];
+        let mut scale = 0.0;
+
+        for _ in 0..self.max_iter {
+            let residuals: Vec<f64> = (&y - &X * &beta).iter().copied().collect();
+            scale = mad_scale(&residuals);
+            if scale <= f64::EPSILON {
+                // The majority of rows are fit exactly, so only they carry weight
+                robustness_weights = residuals
+                    .iter()
+                    .map(|r| if r.abs() <= f64::EPSILON { 1.0 } else { 0.0 })
+                    .collect();
+                break;
+            }
+
+            let weights: Vec<f64> = residuals.iter().map(|r| self.loss.weight(r / scale)).collect();
+            let combined = DVector::from_fn(ns, |i, _| w[i] * weights[i]);
+
+            // Keep the last solution when the reweighted system is singular
+            let previous = match self.learner.solve_normal_equation(&X, &y, &combined) {
+                Ok(next) => std::mem::replace(&mut beta, next),
+                Err(_) => break,
+            };
+            robustness_weights = weights;
+
+            let change = (&beta - &previous).amax();
+            if change <= self.tolerance * (previous.amax() + self.tolerance) {
+                break;
+            }
+        }
+
+        let (intercept, coeffs) = if intercept {
+            (beta[0], beta.iter().skip(1).copied().collect())
+        } else {
+            (0.0, beta.iter().copied().collect())
+        };
+
+        Ok(RobustLinearModel { intercept, coeffs, indices, scale, robustness_weights })
+    }
+}
+
+impl Learner<f64> for RobustLinearLearner {
+    fn fit(&self, data: &[TrainingRow<f64>], _rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.fit_model(data)?))
+    }
+}
+
+/// A model produced by a robust linear learner.
+#[derive(Clone, Debug)]
+pub struct RobustLinearModel {
+    intercept: f64,
+    coeffs: Vec<f64>,
+    indices: Vec<usize>,
+    scale: f64,
+    robustness_weights: Vec<f64>,
+}
+
+impl RobustLinearModel {
+    pub fn intercept(&self) -> f64 {
+        self.intercept
+    }
+
+    /// The coefficients paired with their feature indices.
+    pub fn coefficients(&self) -> Vec<(usize, f64)> {
+        self.indices.iter().copied().zip(self.coeffs.iter().copied()).collect()
+    }
+
+    /// The MAD-based estimate of the residual scale.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// The final robustness weight of each training row, in training order.
+    ///
+    /// These multiply the row weights and lie in [0, 1], with small values marking outliers.
+    pub fn robustness_weights(&self) -> &[f64] {
+        &self.robustness_weights
+    }
+}
+
+impl Model<f64> for RobustLinearModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        let result = inputs
+            .iter()
+            .map(|row| {
+                self.intercept
+                    + self.indices.iter().zip(self.coeffs.iter()).fold(0.0, |state, (&idx, c)| {
+                        state + c * row[idx].as_real().unwrap_or(f64::NAN)
+                    })
+            })
+            .collect();
+
+        Ok(Box::new(RobustLinearPrediction { result }))
+    }
+}
+
+/// A prediction result for a robust linear model.
+#[derive(Clone, Debug)]
+pub struct RobustLinearPrediction {
+    result: Vec<f64>,
+}
+
+impl Prediction<f64> for RobustLinearPrediction {
+    fn expected(&self) -> Vec<f64> {
+        self.result.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_distr::{Distribution, Normal};
+
+    use super::*;
+
+    /// Noisy linear data where every tenth label is a gross outlier.
+    fn contaminated_data(ns: usize, rng: &mut impl Rng) -> Vec<TrainingRow<f64>> {
+        let noise = Normal::new(0.0, 0.1).unwrap();
+        (0..ns)
+            .map(|i| {
+                let x: f64 = rng.gen_range(-5.0..5.0);
+                let outlier = if i % 10 == 0 { 50.0 } else { 0.0 };
+                TrainingRow::new(vec![x], 1.0 + 2.0 * x + noise.sample(rng) + outlier, None)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_robust_to_outliers() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data = contaminated_data(100, &mut rng);
+        let base = LinearRegressionLearner::new(true, None);
+
+        for loss in [RobustLoss::huber(), RobustLoss::tukey()] {
+            let model = RobustLinearLearner::new(base, loss).fit_model(&data).unwrap();
+            assert!((model.intercept() - 1.0).abs() < 0.1, "{:?}", loss);
+            assert!((model.coefficients()[0].1 - 2.0).abs() < 0.1, "{:?}", loss);
+        }
+    }
+
+    #[test]
+    fn test_robustness_weights() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data = contaminated_data(100, &mut rng);
+        let base = LinearRegressionLearner::new(true, None);
+
+        let model = RobustLinearLearner::new(base, RobustLoss::tukey()).fit_model(&data).unwrap();
+        let weights = model.robustness_weights();
+        assert_eq!(weights.len(), data.len());
+        for (i, w) in weights.iter().enumerate() {
+            if i % 10 == 0 {
+                assert_eq!(*w, 0.0);
+            } else {
+                assert!(*w > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_collinear_features() {
+        // The second feature is exactly twice the first, so the normal equations are singular
+        let data: Vec<TrainingRow<f64>> = (0..20)
+            .map(|i| {
+                let x = i as f64;
+                let outlier = if i == 7 { 100.0 } else { 0.0 };
+                TrainingRow::new(vec![x, 2.0 * x], 1.0 + 3.0 * x + outlier, None)
+            })
+            .collect();
+        let base = LinearRegressionLearner::new(true, None);
+
+        for loss in [RobustLoss::huber(), RobustLoss::tukey()] {
+            let model = RobustLinearLearner::new(base, loss).fit_model(&data).unwrap();
+            assert!(model.coefficients().iter().all(|(_, c)| *c == 0.0), "{:?}", loss);
+            assert_eq!(model.intercept(), 32.5, "{:?}", loss);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::core::{FeatureRow, TrainingRow};
+
+/// Gower distance over mixed real and categorical features.
+///
+/// Each real feature contributes `|x - y| / range` and each categorical feature contributes
+/// zero or one for a match or mismatch. The distance is the mean of the contributions,
+/// so it lies in [0, 1] between training rows. Missing or non-finite real values contribute one.
+#[derive(Clone, Debug)]
+pub struct GowerDistance {
+    real_indices: Vec<usize>,
+    categorical_indices: Vec<usize>,
+    ranges: Vec<f64>,
+}
+
+impl GowerDistance {
+    pub fn from_training_data<T>(data: &[TrainingRow<T>]) -> Self {
+        let (real_indices, categorical_indices) = match data.first() {
+            Some(head) => (head.features.real_indices(), head.features.categorical_indices()),
+            None => (vec![], vec![]),
+        };
+
+        let ranges = real_indices
+            .iter()
+            .map(|&idx| {
+                let (min, max) = data
+                    .iter()
+                    .filter_map(|row| row.features[idx].as_real())
+                    .filter(|x| x.is_finite())
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+                if max > min {
+                    max - min
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        Self { real_indices, categorical_indices, ranges }
+    }
+
+    pub fn num_features(&self) -> usize {
+        self.real_indices.len() + self.categorical_indices.len()
+    }
+
+    /// Are all features real, so that the distance is a scaled L1 metric?
+    pub fn is_real_only(&self) -> bool {
+        self.categorical_indices.is_empty()
+    }
+
+    /// The real features of a row, each divided by its range.
+    ///
+    /// For real-only data, the Gower distance is the L1 distance between scaled rows
+    /// divided by the number of features.
+    pub fn scaled_reals(&self, row: &FeatureRow) -> Vec<f64> {
+        self.real_indices
+            .iter()
+            .zip(self.ranges.iter())
+            .map(|(&idx, &range)| {
+                let x = row[idx].as_real().unwrap_or(f64::NAN);
+                if range > 0.0 {
+                    x / range
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    pub fn distance(&self, a: &FeatureRow, b: &FeatureRow) -> f64 {
+        let nf = self.num_features();
+        if nf == 0 {
+            return 0.0;
+        }
+
+        let real_sum: f64 = self
+            .real_indices
+            .iter()
+            .zip(self.ranges.iter())
+            .map(|(&idx, &range)| match (a[idx].as_real(), b[idx].as_real()) {
+                (Some(x), Some(y)) if x.is_finite() && y.is_finite() => {
+                    if range > 0.0 {
+                        (x - y).abs() / range
+                    } else {
+                        0.0
+                    }
+                }
+                _ => 1.0,
+            })
+            .sum();
+
+        let categorical_sum = self
+            .categorical_indices
+            .iter()
+            .filter(|&&idx| match (a[idx].as_categorical(), b[idx].as_categorical()) {
+                (Some(x), Some(y)) => x != y,
+                _ => true,
+            })
+            .count() as f64;
+
+        (real_sum + categorical_sum) / (nf as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::AnyValue;
+
+    #[test]
+    fn test_gower_distance() {
+        let rows: Vec<TrainingRow<f64>> = vec![
+            TrainingRow::new(vec![AnyValue::from(0.0), AnyValue::from(1)], 0.0, None),
+            TrainingRow::new(vec![AnyValue::from(4.0), AnyValue::from(2)], 0.0, None),
+        ];
+        let gower = GowerDistance::from_training_data(&rows);
+
+        let query: FeatureRow = vec![AnyValue::from(1.0), AnyValue::from(1)].into();
+        assert_eq!(gower.distance(&query, &rows[0].features), 0.125);
+        assert_eq!(gower.distance(&query, &rows[1].features), 0.875);
+        assert_eq!(gower.distance(&rows[0].features, &rows[1].features), 1.0);
+    }
+}
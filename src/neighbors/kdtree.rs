@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// A candidate neighbor, ordered by distance so the heap keeps the farthest on top.
+#[derive(Clone, Copy, Debug)]
+struct Candidate {
+    distance: f64,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.index.cmp(&other.index))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum KdNode {
+    Leaf { points: Vec<usize> },
+    Internal { axis: usize, pivot: f64, left: Box<KdNode>, right: Box<KdNode> },
+}
+
+/// A k-d tree for nearest neighbor queries under the L1 metric.
+#[derive(Clone, Debug)]
+pub struct KdTree {
+    points: Vec<Vec<f64>>,
+    root: KdNode,
+}
+
+impl KdTree {
+    /// Maximum number of points held in a leaf.
+    const LEAF_SIZE: usize = 16;
+
+    /// Build a tree over points of equal dimension, which must all be finite.
+    pub fn new(points: Vec<Vec<f64>>) -> Self {
+        let indices: Vec<usize> = (0..points.len()).collect();
+        let root = Self::build(&points, indices, 0);
+        Self { points, root }
+    }
+
+    fn build(points: &[Vec<f64>], mut indices: Vec<usize>, depth: usize) -> KdNode {
+        let dim = points.first().map_or(0, |p| p.len());
+        if indices.len() <= Self::LEAF_SIZE || dim == 0 {
+            return KdNode::Leaf { points: indices };
+        }
+
+        // Split at the median of the axis with the largest spread
+        let axis = (0..dim)
+            .map(|a| {
+                let (lo, hi) =
+                    indices.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &i| {
+                        (lo.min(points[i][a]), hi.max(points[i][a]))
+                    });
+                (a, hi - lo)
+            })
+            .max_by(|(_, x), (_, y)| x.total_cmp(y))
+            .map_or(depth % dim, |(a, _)| a);
+
+        let mid = indices.len() / 2;
+        indices.select_nth_unstable_by(mid, |&i, &j| points[i][axis].total_cmp(&points[j][axis]));
+        let pivot = points[indices[mid]][axis];
+        let right = indices.split_off(mid);
+
+        KdNode::Internal {
+            axis,
+            pivot,
+            left: Box::new(Self::build(points, indices, depth + 1)),
+            right: Box::new(Self::build(points, right, depth + 1)),
+        }
+    }
+
+    /// The `k` nearest points to the query as `(index, distance)`, ordered by increasing distance.
+    pub fn nearest(&self, query: &[f64], k: usize) -> Vec<(usize, f64)> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.search(&self.root, query, k, &mut heap);
+        }
+        heap.into_sorted_vec().into_iter().map(|c| (c.index, c.distance)).collect()
+    }
+
+    fn search(&self, node: &KdNode, query: &[f64], k: usize, heap: &mut BinaryHeap<Candidate>) {
+        match node {
+            KdNode::Leaf { points } => {
+                for &index in points {
+                    let distance: f64 = self.points[index]
+                        .iter()
+                        .zip(query.iter())
+                        .map(|(p, q)| (p - q).abs())
+                        .sum();
+                    heap.push(Candidate { distance, index });
+                    if heap.len() > k {
+                        heap.pop();
+                    }
+                }
+            }
+            KdNode::Internal { axis, pivot, left, right } => {
+                let offset = query[*axis] - pivot;
+                let (near, far) = if offset < 0.0 { (left, right) } else { (right, left) };
+                self.search(near, query, k, heap);
+
+                // The L1 distance to any point across the plane is at least the offset
+                let worst = heap.peek().map_or(f64::INFINITY, |c| c.distance);
+                if heap.len() < k || offset.abs() <= worst {
+                    self.search(far, query, k, heap);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let points: Vec<Vec<f64>> =
+            (0..500).map(|_| (0..3).map(|_| rng.gen_range(-1.0..1.0)).collect()).collect();
+        let tree = KdTree::new(points.clone());
+
+        for _ in 0..20 {
+            let query: Vec<f64> = (0..3).map(|_| rng.gen_range(-1.5..1.5)).collect();
+            let mut brute: Vec<(usize, f64)> = points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (i, p.iter().zip(query.iter()).map(|(a, b)| (a - b).abs()).sum()))
+                .collect();
+            brute.sort_by(|(i, a), (j, b)| a.total_cmp(b).then(i.cmp(j)));
+            brute.truncate(7);
+
+            assert_eq!(tree.nearest(&query, 7), brute);
+        }
+    }
+}
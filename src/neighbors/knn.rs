@@ -0,0 +1,302 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+
+use super::{GowerDistance, KdTree};
+use crate::core::{FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+
+/// How the labels of the neighbors are weighted when forming a prediction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeighborWeighting {
+    /// Each neighbor contributes its row weight.
+    Uniform,
+    /// Each neighbor contributes its row weight divided by its distance.
+    /// Neighbors at zero distance take all of the weight when present.
+    Distance,
+}
+
+/// A k-nearest-neighbors learner using the Gower distance over mixed features.
+///
+/// When every feature is real, neighbors are found with a k-d tree over the range-scaled features.
+#[derive(Clone, Copy, Debug)]
+pub struct KNearestNeighborsLearner {
+    k: usize,
+    weighting: NeighborWeighting,
+    use_index: bool,
+}
+
+impl KNearestNeighborsLearner {
+    pub fn new(k: usize, weighting: NeighborWeighting) -> Self {
+        Self { k, weighting, use_index: true }
+    }
+
+    /// Whether to build a k-d tree index when all features are real.
+    pub fn with_index(mut self, use_index: bool) -> Self {
+        self.use_index = use_index;
+        self
+    }
+
+    fn build_model<T: Copy>(&self, data: &[TrainingRow<T>]) -> Result<KNearestNeighborsModel<T>> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+        if self.k == 0 {
+            return Err(ModelingError::FitError(
+                "The number of neighbors must be positive.".into(),
+            ));
+        }
+
+        let distance = GowerDistance::from_training_data(data);
+
+        let index = if self.use_index && distance.is_real_only() {
+            let points: Vec<Vec<f64>> =
+                data.iter().map(|row| distance.scaled_reals(&row.features)).collect();
+            let finite = points.iter().all(|p| p.iter().all(|x| x.is_finite()));
+            finite.then(|| KdTree::new(points))
+        } else {
+            None
+        };
+
+        Ok(KNearestNeighborsModel {
+            k: self.k.min(data.len()),
+            weighting: self.weighting,
+            distance,
+            index,
+            features: data.iter().map(|row| row.features.clone()).collect(),
+            labels: data.iter().map(|row| row.label).collect(),
+            weights: data.iter().map(|row| row.weight.unwrap_or(1.0)).collect(),
+        })
+    }
+}
+
+impl Learner<f64> for KNearestNeighborsLearner {
+    fn fit(&self, data: &[TrainingRow<f64>], _rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.build_model(data)?))
+    }
+}
+
+impl Learner<usize> for KNearestNeighborsLearner {
+    fn fit(
+        &self,
+        data: &[TrainingRow<usize>],
+        _rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<usize>>> {
+        Ok(Box::new(self.build_model(data)?))
+    }
+}
+
+/// A model produced by a k-nearest-neighbors learner.
+#[derive(Clone, Debug)]
+pub struct KNearestNeighborsModel<T> {
+    k: usize,
+    weighting: NeighborWeighting,
+    distance: GowerDistance,
+    index: Option<KdTree>,
+    features: Vec<FeatureRow>,
+    labels: Vec<T>,
+    weights: Vec<f64>,
+}
+
+impl<T> KNearestNeighborsModel<T> {
+    /// The `k` nearest training rows as `(index, Gower distance)`, ordered by increasing distance.
+    pub fn neighbors(&self, row: &FeatureRow) -> Vec<(usize, f64)> {
+        if let Some(tree) = &self.index {
+            let query = self.distance.scaled_reals(row);
+            if query.iter().all(|x| x.is_finite()) {
+                let nf = self.distance.num_features().max(1) as f64;
+                return tree
+                    .nearest(&query, self.k)
+                    .into_iter()
+                    .map(|(i, d)| (i, d / nf))
+                    .collect();
+            }
+        }
+
+        let mut distances: Vec<(usize, f64)> = self
+            .features
+            .iter()
+            .enumerate()
+            .map(|(i, other)| (i, self.distance.distance(row, other)))
+            .collect();
+        let by_distance =
+            |(i, a): &(usize, f64), (j, b): &(usize, f64)| a.total_cmp(b).then(i.cmp(j));
+        if self.k < distances.len() {
+            distances.select_nth_unstable_by(self.k, by_distance);
+            distances.truncate(self.k);
+        }
+        distances.sort_by(by_distance);
+        distances
+    }
+
+    /// The neighbors of a row paired with their weight in the prediction.
+    fn weighted_neighbors(&self, row: &FeatureRow) -> Vec<(usize, f64)> {
+        let neighbors = self.neighbors(row);
+        match self.weighting {
+            NeighborWeighting::Uniform => {
+                neighbors.into_iter().map(|(i, _)| (i, self.weights[i])).collect()
+            }
+            NeighborWeighting::Distance => {
+                if neighbors.iter().any(|(_, d)| *d == 0.0) {
+                    neighbors
+                        .into_iter()
+                        .filter(|(_, d)| *d == 0.0)
+                        .map(|(i, _)| (i, self.weights[i]))
+                        .collect()
+                } else {
+                    neighbors.into_iter().map(|(i, d)| (i, self.weights[i] / d)).collect()
+                }
+            }
+        }
+    }
+}
+
+impl Model<f64> for KNearestNeighborsModel<f64> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        let (result, spread) = inputs
+            .iter()
+            .map(|row| {
+                let neighbors = self.weighted_neighbors(row);
+                let total: f64 = neighbors.iter().map(|(_, w)| w).sum();
+                let mean = neighbors.iter().map(|&(i, w)| w * self.labels[i]).sum::<f64>() / total;
+                let var = neighbors
+                    .iter()
+                    .map(|&(i, w)| w * (self.labels[i] - mean).powi(2))
+                    .sum::<f64>()
+                    / total;
+                (mean, var.sqrt())
+            })
+            .unzip();
+
+        Ok(Box::new(KNearestNeighborsPrediction { result, spread }))
+    }
+}
+
+impl Model<usize> for KNearestNeighborsModel<usize> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<usize>>> {
+        let (result, spread) = inputs
+            .iter()
+            .map(|row| {
+                let neighbors = self.weighted_neighbors(row);
+
+                let mut votes: BTreeMap<usize, f64> = BTreeMap::new();
+                for &(i, w) in neighbors.iter() {
+                    *votes.entry(self.labels[i]).or_insert(0.0) += w;
+                }
+
+                // Ties go to the smallest label
+                let winner = votes
+                    .into_iter()
+                    .fold(None, |best: Option<(usize, f64)>, (label, w)| match best {
+                        Some((_, bw)) if bw >= w => best,
+                        _ => Some((label, w)),
+                    })
+                    .map(|(label, _)| label)
+                    .unwrap();
+                let dissent = neighbors.iter().filter(|&&(i, _)| self.labels[i] != winner).count();
+                (winner, dissent)
+            })
+            .unzip();
+
+        Ok(Box::new(KNearestNeighborsPrediction { result, spread }))
+    }
+}
+
+/// A prediction result for a k-nearest-neighbors model.
+///
+/// For regression, the uncertainty is the weighted standard deviation of the neighbor labels.
+/// For classification, it is the number of neighbors that disagree with the predicted class.
+#[derive(Clone, Debug)]
+pub struct KNearestNeighborsPrediction<T> {
+    result: Vec<T>,
+    spread: Vec<T>,
+}
+
+impl Prediction<f64> for KNearestNeighborsPrediction<f64> {
+    fn expected(&self) -> Vec<f64> {
+        self.result.clone()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<f64>> {
+        Some(self.spread.clone())
+    }
+}
+
+impl Prediction<usize> for KNearestNeighborsPrediction<usize> {
+    fn expected(&self) -> Vec<usize> {
+        self.result.clone()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<usize>> {
+        Some(self.spread.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::core::AnyValue;
+    use crate::utils::linear_training_data;
+
+    #[test]
+    fn test_index_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data = linear_training_data(200, &[1.0, -2.0, 0.5], 0.0, &mut rng);
+        let inputs: Vec<FeatureRow> = linear_training_data(20, &[0.0; 3], 0.0, &mut rng)
+            .into_iter()
+            .map(|row| row.features)
+            .collect();
+
+        let learner = KNearestNeighborsLearner::new(5, NeighborWeighting::Distance);
+        let indexed = learner.build_model(&data).unwrap();
+        let brute = learner.with_index(false).build_model(&data).unwrap();
+        assert!(indexed.index.is_some() && brute.index.is_none());
+
+        for row in inputs.iter() {
+            let (a, b) = (indexed.neighbors(row), brute.neighbors(row));
+            assert_eq!(
+                a.iter().map(|n| n.0).collect::<Vec<_>>(),
+                b.iter().map(|n| n.0).collect::<Vec<_>>()
+            );
+            a.iter().zip(b.iter()).for_each(|(x, y)| assert!((x.1 - y.1).abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_regression_uncertainty() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<_> =
+            (0..10).map(|i| TrainingRow::new(vec![i as f64], i as f64, None)).collect();
+        let inputs: Vec<FeatureRow> = vec![vec![4.0].into(), vec![4.5].into()];
+
+        let learner = KNearestNeighborsLearner::new(2, NeighborWeighting::Uniform);
+        let prediction = learner.fit(&data, &mut rng).unwrap().transform(&inputs).unwrap();
+
+        assert_eq!(prediction.expected(), vec![3.5, 4.5]);
+        assert_eq!(prediction.uncertainty().unwrap(), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_mixed_classification() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<TrainingRow<usize>> = (0..30)
+            .map(|i| {
+                let category = i % 3;
+                let features = vec![AnyValue::from(i as f64), AnyValue::from(category)];
+                TrainingRow::new(features, category, None)
+            })
+            .collect();
+
+        let learner = KNearestNeighborsLearner::new(3, NeighborWeighting::Distance);
+        let model = learner.fit(&data, &mut rng).unwrap();
+
+        let inputs: Vec<FeatureRow> =
+            (0..3).map(|c| vec![AnyValue::from(15.0), AnyValue::from(c)].into()).collect();
+        let prediction = model.transform(&inputs).unwrap();
+        assert_eq!(prediction.expected(), vec![0, 1, 2]);
+        assert_eq!(prediction.uncertainty().unwrap(), vec![0, 0, 0]);
+    }
+}
@@ -0,0 +1,7 @@
+mod distance;
+mod kdtree;
+mod knn;
+
+pub use self::distance::GowerDistance;
+pub use self::kdtree::KdTree;
+pub use self::knn::*;
@@ -1,3 +1,5 @@
+use crate::core::TrainingRow;
+
 /// Interface for calculating the impurity during a split
 pub trait ImpurityCalculator<T> {
     fn add(&mut self, value: T, weight: f64);
@@ -8,3 +10,8 @@ pub trait ImpurityCalculator<T> {
 
     fn impurity(&self) -> f64;
 }
+
+/// Labels with an impurity over the rows of a node, on the same scale as the split deltas.
+pub trait NodeImpurity: Sized {
+    fn node_impurity(data: &[TrainingRow<Self>]) -> f64;
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use super::ImpurityCalculator;
+use super::{ImpurityCalculator, NodeImpurity};
 use crate::core::TrainingRow;
 
 #[derive(Clone, Debug)]
@@ -30,7 +30,7 @@ impl GiniCalculator {
     }
 
     pub fn from_labels(labels: &[usize], weights: &[f64]) -> Self {
-        if !(labels.len() == weights.len()) {
+        if labels.len() != weights.len() {
             panic!("Labels and weights are not the same size.")
         }
 
@@ -45,7 +45,7 @@ impl GiniCalculator {
 
         let max_category = *category_weights.keys().max().unwrap();
 
-        let mut weight_vec = Vec::with_capacity(max_category);
+        let mut weight_vec = vec![0.0; max_category + 1];
         let mut total_weight = 0.0;
         let mut total_sq_sum = 0.0;
         for (cat, weight) in category_weights {
@@ -57,9 +57,16 @@ impl GiniCalculator {
         GiniCalculator::new(weight_vec, total_sq_sum, total_weight)
     }
 
-    pub fn from_training_data(data: &Vec<TrainingRow<usize>>) -> GiniCalculator {
+    pub fn from_training_data(data: &[TrainingRow<usize>]) -> GiniCalculator {
         let (labels, weights): (Vec<_>, Vec<_>) =
-            data.iter().map(|row| (row.label, row.weight)).unzip();
+            data.iter().map(|row| (row.label, row.weight.unwrap_or(1.0))).unzip();
+        GiniCalculator::from_labels(&labels, &weights)
+    }
+
+    /// A calculator over the rows of `data` at `indices`.
+    pub fn from_indexed_data(data: &[TrainingRow<usize>], indices: &[usize]) -> GiniCalculator {
+        let (labels, weights): (Vec<_>, Vec<_>) =
+            indices.iter().map(|&i| (data[i].label, data[i].weight.unwrap_or(1.0))).unzip();
         GiniCalculator::from_labels(&labels, &weights)
     }
 }
@@ -97,18 +104,25 @@ impl ImpurityCalculator<usize> for GiniCalculator {
     }
 
     fn impurity(&self) -> f64 {
+        let right_weight = self.total_weight - self.left_weight;
         if self.total_weight == 0.0 {
             0.0
-        } else if self.left_sq_sum == 0.0 && self.right_sq_sum == 0.0 {
+        } else if self.left_weight == 0.0 || right_weight == 0.0 {
             self.total_weight - self.total_sq_sum / self.total_weight
         } else {
             self.total_weight
                 - self.left_sq_sum / self.left_weight
-                - self.right_sq_sum / (self.total_weight - self.left_weight)
+                - self.right_sq_sum / right_weight
         }
     }
 }
 
+impl NodeImpurity for usize {
+    fn node_impurity(data: &[TrainingRow<Self>]) -> f64 {
+        GiniCalculator::from_training_data(data).impurity()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,4 +132,17 @@ mod tests {
         let calc = GiniCalculator::from_labels(&[], &[]);
         assert!(calc.impurity() == 0.0);
     }
+
+    #[test]
+    fn test_split_impurity() {
+        let mut calc = GiniCalculator::from_labels(&[1, 1, 2, 2], &[1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(calc.impurity(), 2.0);
+
+        calc.add(1, 1.0);
+        calc.add(1, 1.0);
+        assert_eq!(calc.impurity(), 0.0);
+
+        calc.remove(1, 1.0);
+        assert!((calc.impurity() - (4.0 - 1.0 - 5.0 / 3.0)).abs() < 1e-12);
+    }
 }
@@ -1,7 +1,9 @@
 pub mod calculator;
 pub mod gini;
+pub mod multitask;
 pub mod variance;
 
-pub use self::calculator::ImpurityCalculator;
+pub use self::calculator::{ImpurityCalculator, NodeImpurity};
 pub use self::gini::GiniCalculator;
+pub use self::multitask::MultiTaskCalculator;
 pub use self::variance::VarianceCalculator;
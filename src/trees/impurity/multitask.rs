@@ -0,0 +1,162 @@
+use super::{GiniCalculator, ImpurityCalculator, VarianceCalculator};
+use crate::core::{AnyValue, MultiTaskLabel, TrainingRow};
+
+/// The impurity calculator of a single task, chosen from the type of its labels.
+#[derive(Clone, Debug)]
+enum TaskCalculator {
+    Real(VarianceCalculator),
+    Categorical(GiniCalculator),
+}
+
+impl TaskCalculator {
+    fn add(&mut self, value: &AnyValue, weight: f64) {
+        match (self, value) {
+            (Self::Real(calc), AnyValue::Real(y)) => calc.add(*y, weight),
+            (Self::Categorical(calc), AnyValue::Categorical(c)) => calc.add(*c, weight),
+            _ => {}
+        }
+    }
+
+    fn remove(&mut self, value: &AnyValue, weight: f64) {
+        match (self, value) {
+            (Self::Real(calc), AnyValue::Real(y)) => calc.remove(*y, weight),
+            (Self::Categorical(calc), AnyValue::Categorical(c)) => calc.remove(*c, weight),
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Self::Real(calc) => ImpurityCalculator::<f64>::reset(calc),
+            Self::Categorical(calc) => calc.reset(),
+        }
+    }
+
+    fn impurity(&self) -> f64 {
+        match self {
+            Self::Real(calc) => ImpurityCalculator::<f64>::impurity(calc),
+            Self::Categorical(calc) => calc.impurity(),
+        }
+    }
+}
+
+/// Combined impurity over several tasks with possibly missing labels.
+///
+/// Real tasks use the variance and categorical tasks the Gini impurity, each divided by its
+/// impurity over all of the data so that every task contributes on the same scale.
+/// Tasks without impurity in the data do not contribute.
+#[derive(Clone, Debug)]
+pub struct MultiTaskCalculator {
+    tasks: Vec<TaskCalculator>,
+    scales: Vec<f64>,
+}
+
+impl MultiTaskCalculator {
+    pub fn from_training_data(data: &[TrainingRow<MultiTaskLabel>]) -> Self {
+        Self::from_rows(data.iter())
+    }
+
+    /// A calculator over the rows of `data` at `indices`.
+    pub fn from_indexed_data(data: &[TrainingRow<MultiTaskLabel>], indices: &[usize]) -> Self {
+        Self::from_rows(indices.iter().map(|&i| &data[i]))
+    }
+
+    fn from_rows<'a>(rows: impl Iterator<Item = &'a TrainingRow<MultiTaskLabel>> + Clone) -> Self {
+        let nt = rows.clone().map(|row| row.label.len()).max().unwrap_or(0);
+
+        let tasks: Vec<TaskCalculator> = (0..nt)
+            .map(|k| {
+                let present = rows.clone().filter_map(|row| {
+                    row.label.get(k).copied().flatten().map(|y| (y, row.weight.unwrap_or(1.0)))
+                });
+                let categorical = present.clone().next().is_some_and(|(y, _)| y.is_categorcial());
+                if categorical {
+                    let (labels, weights): (Vec<usize>, Vec<f64>) =
+                        present.filter_map(|(y, w)| Some((y.as_categorical()?, w))).unzip();
+                    TaskCalculator::Categorical(GiniCalculator::from_labels(&labels, &weights))
+                } else {
+                    let (labels, weights): (Vec<f64>, Vec<f64>) =
+                        present.filter_map(|(y, w)| Some((y.as_real()?, w))).unzip();
+                    TaskCalculator::Real(VarianceCalculator::from_labels(&labels, &weights))
+                }
+            })
+            .collect();
+
+        let scales = tasks
+            .iter()
+            .map(|calc| {
+                let impurity = calc.impurity();
+                if impurity > 0.0 {
+                    1.0 / impurity
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        Self { tasks, scales }
+    }
+
+    /// The number of tasks that contribute to the impurity.
+    pub fn num_active_tasks(&self) -> usize {
+        self.scales.iter().filter(|&&s| s > 0.0).count()
+    }
+}
+
+impl ImpurityCalculator<&[Option<AnyValue>]> for MultiTaskCalculator {
+    fn add(&mut self, value: &[Option<AnyValue>], weight: f64) {
+        for (calc, y) in self.tasks.iter_mut().zip(value) {
+            if let Some(y) = y {
+                calc.add(y, weight);
+            }
+        }
+    }
+
+    fn remove(&mut self, value: &[Option<AnyValue>], weight: f64) {
+        for (calc, y) in self.tasks.iter_mut().zip(value) {
+            if let Some(y) = y {
+                calc.remove(y, weight);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.tasks.iter_mut().for_each(|calc| calc.reset());
+    }
+
+    fn impurity(&self) -> f64 {
+        self.tasks
+            .iter()
+            .zip(self.scales.iter())
+            .filter(|(_, &scale)| scale > 0.0)
+            .map(|(calc, scale)| scale * calc.impurity())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_tasks() {
+        let labels: Vec<MultiTaskLabel> = vec![
+            vec![Some(AnyValue::from(0.0)), Some(AnyValue::from(1))],
+            vec![Some(AnyValue::from(0.0)), None],
+            vec![Some(AnyValue::from(100.0)), Some(AnyValue::from(2))],
+            vec![None, Some(AnyValue::from(2))],
+        ];
+        let data: Vec<_> =
+            labels.into_iter().map(|y| TrainingRow::new(vec![0.0], y, None)).collect();
+
+        // Each task is normalized to unit impurity, regardless of its scale
+        let mut calc = MultiTaskCalculator::from_training_data(&data);
+        assert_eq!(calc.num_active_tasks(), 2);
+        assert!((calc.impurity() - 2.0).abs() < 1e-12);
+
+        // Separating the first two rows purifies both tasks
+        calc.add(&data[0].label, 1.0);
+        calc.add(&data[1].label, 1.0);
+        assert!(calc.impurity().abs() < 1e-12);
+    }
+}
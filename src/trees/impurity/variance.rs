@@ -1,86 +1,171 @@
-use super::ImpurityCalculator;
-use crate::core::TrainingRow;
+use super::{ImpurityCalculator, NodeImpurity};
+use crate::core::{RegressionLabel, TrainingRow};
 
+/// Weighted sum of squared deviations, summed across the outputs of the label.
 #[derive(Clone, Debug)]
 pub struct VarianceCalculator {
-    total_sum: f64,
+    total_sum: Vec<f64>,
     total_sq_sum: f64,
     total_weight: f64,
 
-    left_sum: f64,
+    left_sum: Vec<f64>,
     left_weight: f64,
 }
 
 impl VarianceCalculator {
-    pub fn new(ts: f64, tsq: f64, tw: f64) -> Self {
-        Self { total_sum: ts, total_sq_sum: tsq, total_weight: tw, left_sum: 0.0, left_weight: 0.0 }
+    pub fn new(ts: Vec<f64>, tsq: f64, tw: f64) -> Self {
+        let ls = vec![0.0; ts.len()];
+        Self { total_sum: ts, total_sq_sum: tsq, total_weight: tw, left_sum: ls, left_weight: 0.0 }
     }
 
-    pub fn from_labels(labels: &[f64], weights: &[f64]) -> Self {
-        if !(labels.len() == weights.len()) {
+    pub fn from_labels<L: RegressionLabel>(labels: &[L], weights: &[f64]) -> Self {
+        if labels.len() != weights.len() {
             panic!("Labels and weights are not the same size.")
         }
 
-        let mut ts = 0.0;
+        let no = labels.first().map_or(0, |y| y.outputs().len());
+        let mut ts = vec![0.0; no];
         let mut tsq = 0.0;
         let mut tw = 0.0;
         for (y, w) in labels.iter().zip(weights.iter()) {
-            ts += w * y;
-            tsq += w * y * y;
+            for (s, y) in ts.iter_mut().zip(y.outputs()) {
+                *s += w * y;
+                tsq += w * y * y;
+            }
             tw += w;
         }
 
         Self::new(ts, tsq, tw)
     }
 
-    pub fn from_training_data(data: &[TrainingRow<f64>]) -> Self {
+    pub fn from_training_data<L: RegressionLabel>(data: &[TrainingRow<L>]) -> Self {
         let (labels, weights): (Vec<_>, Vec<_>) =
-            data.iter().map(|row| (row.label, row.weight)).unzip();
+            data.iter().map(|row| (row.label.clone(), row.weight.unwrap_or(1.0))).unzip();
         Self::from_labels(labels.as_slice(), weights.as_slice())
     }
+
+    /// A calculator over the rows of `data` at `indices`.
+    pub fn from_indexed_data<L: RegressionLabel>(
+        data: &[TrainingRow<L>],
+        indices: &[usize],
+    ) -> Self {
+        let (labels, weights): (Vec<_>, Vec<_>) =
+            indices.iter().map(|&i| (data[i].label.clone(), data[i].weight.unwrap_or(1.0))).unzip();
+        Self::from_labels(labels.as_slice(), weights.as_slice())
+    }
+
+    /// The weighted mean of each output on the left and on the right of the split.
+    pub fn means(&self) -> (Vec<f64>, Vec<f64>) {
+        let rw = self.total_weight - self.left_weight;
+        let left = self.left_sum.iter().map(|l| l / self.left_weight).collect();
+        let right = self.total_sum.iter().zip(self.left_sum.iter()).map(|(t, l)| (t - l) / rw);
+        (left, right.collect())
+    }
+
+    /// Move rows to the left given the weighted sum of each of their outputs and their weight.
+    pub fn add_sums(&mut self, sums: &[f64], weight: f64) {
+        self.left_sum.iter_mut().zip(sums).for_each(|(s, y)| *s += y);
+        self.left_weight += weight;
+    }
+
+    fn squared_sum(sums: impl Iterator<Item = f64>) -> f64 {
+        sums.map(|s| s * s).sum()
+    }
 }
 
-impl ImpurityCalculator<f64> for VarianceCalculator {
-    fn add(&mut self, value: f64, weight: f64) {
-        if !value.is_nan() && !weight.is_nan() {
-            self.left_sum += weight * value;
+impl ImpurityCalculator<&[f64]> for VarianceCalculator {
+    fn add(&mut self, value: &[f64], weight: f64) {
+        if !value.iter().any(|y| y.is_nan()) && !weight.is_nan() {
+            self.left_sum.iter_mut().zip(value).for_each(|(s, y)| *s += weight * y);
             self.left_weight += weight;
         }
     }
 
-    fn remove(&mut self, value: f64, weight: f64) {
-        if !value.is_nan() && !weight.is_nan() {
-            self.left_sum -= weight * value;
+    fn remove(&mut self, value: &[f64], weight: f64) {
+        if !value.iter().any(|y| y.is_nan()) && !weight.is_nan() {
+            self.left_sum.iter_mut().zip(value).for_each(|(s, y)| *s -= weight * y);
             self.left_weight -= weight;
         }
     }
 
     fn reset(&mut self) {
-        self.left_sum = 0.0;
+        self.left_sum.fill(0.0);
         self.left_weight = 0.0;
     }
 
     fn impurity(&self) -> f64 {
-        let rs = self.total_sum - self.left_sum;
         let rw = self.total_weight - self.left_weight;
         if self.total_weight == 0.0 {
             0.0
         } else if rw == 0.0 || self.left_weight == 0.0 {
-            self.total_sq_sum - self.total_sum * self.total_sum / self.total_weight
+            let ts = Self::squared_sum(self.total_sum.iter().copied());
+            self.total_sq_sum - ts / self.total_weight
         } else {
-            let (ls, lw) = (self.left_sum, self.left_weight);
-            self.total_sq_sum - ls * ls / lw - rs * rs / rw
+            let ls = Self::squared_sum(self.left_sum.iter().copied());
+            let rs = Self::squared_sum(
+                self.total_sum.iter().zip(self.left_sum.iter()).map(|(t, l)| t - l),
+            );
+            self.total_sq_sum - ls / self.left_weight - rs / rw
         }
     }
 }
 
+impl ImpurityCalculator<f64> for VarianceCalculator {
+    fn add(&mut self, value: f64, weight: f64) {
+        ImpurityCalculator::<&[f64]>::add(self, &[value], weight)
+    }
+
+    fn remove(&mut self, value: f64, weight: f64) {
+        ImpurityCalculator::<&[f64]>::remove(self, &[value], weight)
+    }
+
+    fn reset(&mut self) {
+        ImpurityCalculator::<&[f64]>::reset(self)
+    }
+
+    fn impurity(&self) -> f64 {
+        ImpurityCalculator::<&[f64]>::impurity(self)
+    }
+}
+
+impl NodeImpurity for f64 {
+    fn node_impurity(data: &[TrainingRow<Self>]) -> f64 {
+        ImpurityCalculator::<f64>::impurity(&VarianceCalculator::from_training_data(data))
+    }
+}
+
+impl NodeImpurity for Vec<f64> {
+    fn node_impurity(data: &[TrainingRow<Self>]) -> f64 {
+        ImpurityCalculator::<f64>::impurity(&VarianceCalculator::from_training_data(data))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_empty() {
-        let calc = VarianceCalculator::from_labels(&[], &[]);
-        assert!(calc.impurity() == 0.0);
+        let calc = VarianceCalculator::from_labels::<f64>(&[], &[]);
+        assert!(ImpurityCalculator::<f64>::impurity(&calc) == 0.0);
+    }
+
+    #[test]
+    fn test_sum_over_outputs() {
+        let labels = vec![vec![1.0, 10.0], vec![3.0, 20.0], vec![5.0, 60.0]];
+        let weights = vec![1.0, 1.0, 2.0];
+        let mut calc = VarianceCalculator::from_labels(&labels, &weights);
+
+        // Each output contributes its own weighted sum of squared deviations
+        let single = |k: usize| {
+            let values: Vec<f64> = labels.iter().map(|y| y[k]).collect();
+            ImpurityCalculator::<f64>::impurity(&VarianceCalculator::from_labels(&values, &weights))
+        };
+        let total = ImpurityCalculator::<&[f64]>::impurity(&calc);
+        assert!((total - single(0) - single(1)).abs() < 1e-9);
+
+        // Splitting off the last row leaves only the spread within the first two
+        ImpurityCalculator::<&[f64]>::add(&mut calc, &labels[2], weights[2]);
+        assert!((ImpurityCalculator::<&[f64]>::impurity(&calc) - 2.0 - 50.0).abs() < 1e-9);
     }
 }
@@ -1,7 +1,7 @@
-use crate::{
-    core::{Learner, Model, TrainingRow},
-    learners::GuessTheMeanLearner,
-};
+use rand::Rng;
+
+use crate::core::{Learner, Model, Result, TrainingRow};
+use crate::linear::GuessTheMeanLearner;
 
 #[derive(Clone, Debug)]
 pub enum ClassificationLeafLearner {
@@ -15,9 +15,13 @@ impl ClassificationLeafLearner {
 }
 
 impl Learner<usize> for ClassificationLeafLearner {
-    fn fit(&mut self, data: &[TrainingRow<usize>]) -> Box<dyn Model<usize>> {
+    fn fit(
+        &self,
+        data: &[TrainingRow<usize>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<usize>>> {
         match self {
-            Self::GuessTheMean { learner } => learner.fit(data),
+            Self::GuessTheMean { learner } => learner.fit(data, rng),
         }
     }
 }
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use nalgebra::DVector;
+
+use crate::core::{
+    FeatureRow, Model, ModelingError, Prediction, RegressionLabel, Result, TrainingRow,
+};
+use crate::linear::{design_matrix, included_real_features, LinearRegressionLearner};
+
+/// Options for trees with linear models at the leaves, following the M5 model tree.
+#[derive(Debug, Copy, Clone)]
+pub struct LinearLeafParameters {
+    pub feature_selection: bool,
+    pub pruning: bool,
+    pub smoothing: f64,
+}
+
+impl LinearLeafParameters {
+    /// Greedily drop features from each linear model while its adjusted error does not increase.
+    pub fn with_feature_selection(mut self, feature_selection: bool) -> Self {
+        self.feature_selection = feature_selection;
+        self
+    }
+
+    /// Replace subtrees by a leaf when the linear model of their root has a lower adjusted error.
+    pub fn with_pruning(mut self, pruning: bool) -> Self {
+        self.pruning = pruning;
+        self
+    }
+
+    /// The smoothing constant blending leaf predictions with the models along the root-to-leaf
+    /// path, where zero disables smoothing.
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.max(0.0);
+        self
+    }
+}
+
+impl Default for LinearLeafParameters {
+    fn default() -> Self {
+        Self { feature_selection: true, pruning: true, smoothing: 15.0 }
+    }
+}
+
+/// A linear model with one intercept and set of coefficients per output, sharing the features.
+#[derive(Clone, Debug)]
+pub struct LinearLeafModel<T> {
+    indices: Vec<usize>,
+    intercepts: Vec<f64>,
+    coeffs: Vec<Vec<f64>>,
+    label: PhantomData<T>,
+}
+
+impl<T: RegressionLabel> LinearLeafModel<T> {
+    /// Fit a linear model to the rows, returning it with its adjusted training error.
+    ///
+    /// The mean absolute error is inflated by `(n + v) / (n - v)` for `n` rows and `v` parameters,
+    /// as in M5, so that models with fewer features are preferred on small samples.
+    pub(crate) fn fit(
+        learner: &LinearRegressionLearner,
+        feature_selection: bool,
+        data: &[TrainingRow<T>],
+    ) -> Result<(Self, f64)> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+
+        let mut indices = included_real_features(data);
+        let mut best = Self::fit_indices(learner, data, &indices);
+        let mut best_error = best.adjusted_error(learner, data);
+
+        // Backward elimination, dropping the feature whose removal reduces the error the most
+        while feature_selection && !indices.is_empty() {
+            let (trial, trial_error, trial_indices) = (0..indices.len())
+                .map(|j| {
+                    let mut trial_indices = indices.clone();
+                    trial_indices.remove(j);
+                    let trial = Self::fit_indices(learner, data, &trial_indices);
+                    let error = trial.adjusted_error(learner, data);
+                    (trial, error, trial_indices)
+                })
+                .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b))
+                .unwrap();
+
+            if trial_error > best_error {
+                break;
+            }
+            best = trial;
+            best_error = trial_error;
+            indices = trial_indices;
+        }
+
+        Ok((best, best_error))
+    }
+
+    /// Fit every output on the given features, falling back to the weighted mean when the
+    /// problem is underdetermined or singular.
+    fn fit_indices(
+        learner: &LinearRegressionLearner,
+        data: &[TrainingRow<T>],
+        indices: &[usize],
+    ) -> Self {
+        let ns = data.len();
+        let no = data[0].label.outputs().len();
+        let nf = indices.len();
+        let offset = learner.fit_intercept() as usize;
+
+        let w = DVector::from_iterator(ns, data.iter().map(|row| row.weight.unwrap_or(1.0)));
+        let X =
+            design_matrix(data.iter().map(|row| &row.features), indices, learner.fit_intercept());
+
+        let (intercepts, coeffs) = (0..no)
+            .map(|k| {
+                let y = DVector::from_iterator(ns, data.iter().map(|row| row.label.outputs()[k]));
+                let mean = y.dot(&w) / w.sum();
+                match learner.solve_normal_equation(&X, &y, &w) {
+                    Ok(beta) if ns > nf + offset => {
+                        let intercept = if offset == 1 { beta[0] } else { 0.0 };
+                        (intercept, beta.iter().skip(offset).copied().collect())
+                    }
+                    _ => (mean, vec![0.0; nf]),
+                }
+            })
+            .unzip();
+
+        Self { indices: indices.to_vec(), intercepts, coeffs, label: PhantomData }
+    }
+
+    fn adjusted_error(&self, learner: &LinearRegressionLearner, data: &[TrainingRow<T>]) -> f64 {
+        let (mut error, mut weight) = (0.0, 0.0);
+        for row in data.iter() {
+            let w = row.weight.unwrap_or(1.0);
+            let predicted = self.predict_outputs(&row.features);
+            error += w * predicted
+                .iter()
+                .zip(row.label.outputs())
+                .map(|(p, y)| (p - y).abs())
+                .sum::<f64>();
+            weight += w;
+        }
+
+        let n = data.len() as f64;
+        let v = (self.indices.len() + learner.fit_intercept() as usize) as f64;
+        let factor = if n > v { (n + v) / (n - v) } else { 10.0 };
+        factor * error / weight
+    }
+}
+
+impl<T> LinearLeafModel<T> {
+    /// The indices of the features used by the model.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// The intercept of each output.
+    pub fn intercepts(&self) -> &[f64] {
+        &self.intercepts
+    }
+
+    /// The coefficients of each output, in the order of the feature indices.
+    pub fn coefficients(&self) -> &[Vec<f64>] {
+        &self.coeffs
+    }
+
+    pub(crate) fn predict_outputs(&self, row: &FeatureRow) -> Vec<f64> {
+        self.intercepts
+            .iter()
+            .zip(self.coeffs.iter())
+            .map(|(intercept, coeffs)| {
+                intercept
+                    + self.indices.iter().zip(coeffs.iter()).fold(0.0, |state, (&idx, c)| {
+                        state + c * row[idx].as_real().unwrap_or(f64::NAN)
+                    })
+            })
+            .collect()
+    }
+
+    /// The linear model predicting `a * self + b * other`, over the union of their features.
+    pub(crate) fn blend(&self, a: f64, other: &Self, b: f64) -> Self {
+        let no = self.intercepts.len();
+        let mut merged: BTreeMap<usize, Vec<f64>> = BTreeMap::new();
+        for (model, scale) in [(self, a), (other, b)] {
+            for (j, &idx) in model.indices.iter().enumerate() {
+                let entry = merged.entry(idx).or_insert_with(|| vec![0.0; no]);
+                for (value, coeffs) in entry.iter_mut().zip(model.coeffs.iter()) {
+                    *value += scale * coeffs[j];
+                }
+            }
+        }
+
+        let indices: Vec<usize> = merged.keys().copied().collect();
+        let coeffs = (0..no).map(|k| merged.values().map(|c| c[k]).collect()).collect();
+        let intercepts = self
+            .intercepts
+            .iter()
+            .zip(other.intercepts.iter())
+            .map(|(x, y)| a * x + b * y)
+            .collect();
+
+        Self { indices, intercepts, coeffs, label: PhantomData }
+    }
+}
+
+impl<T: RegressionLabel + 'static> Model<T> for LinearLeafModel<T> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<T>>> {
+        let result = inputs.iter().map(|row| T::from_outputs(self.predict_outputs(row))).collect();
+        Ok(Box::new(LinearLeafPrediction { result }))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LinearLeafPrediction<T> {
+    result: Vec<T>,
+}
+
+impl<T: Clone> Prediction<T> for LinearLeafPrediction<T> {
+    fn expected(&self) -> Vec<T> {
+        self.result.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_feature_selection() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // The label only depends on the first of three features, up to a little noise
+        let data: Vec<TrainingRow<f64>> = (0..30)
+            .map(|_| {
+                let x: Vec<f64> = (0..3).map(|_| rng.gen::<f64>()).collect();
+                let y = 1.0 + 4.0 * x[0] + 1e-3 * rng.gen::<f64>();
+                TrainingRow::new(x, y, None)
+            })
+            .collect();
+
+        let learner = LinearRegressionLearner::new(true, None);
+        let (full, _) = LinearLeafModel::fit(&learner, false, &data).unwrap();
+        let (selected, _) = LinearLeafModel::fit(&learner, true, &data).unwrap();
+
+        assert_eq!(full.indices(), &[0, 1, 2]);
+        assert_eq!(selected.indices(), &[0]);
+        assert!((selected.coefficients()[0][0] - 4.0).abs() < 1e-2);
+        assert!((selected.intercepts()[0] - 1.0).abs() < 1e-2);
+    }
+}
@@ -1,5 +1,9 @@
 mod classification;
+mod linear;
+mod multitask;
 mod regression;
 
 pub use self::classification::ClassificationLeafLearner;
-pub use self::regression::RegressionLeafLearner;
+pub use self::linear::{LinearLeafModel, LinearLeafParameters, LinearLeafPrediction};
+pub use self::multitask::{MultiTaskLeafLearner, MultiTaskLeafModel, MultiTaskPrediction};
+pub use self::regression::{EmpiricalLeafModel, RegressionLeafLearner};
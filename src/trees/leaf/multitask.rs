@@ -0,0 +1,153 @@
+use rand::Rng;
+
+use crate::core::{
+    AnyValue, FeatureRow, Learner, Model, MultiTaskLabel, Prediction, Result, TrainingRow,
+};
+use crate::linear::GuessTheMeanLearner;
+
+#[derive(Clone, Debug)]
+pub enum MultiTaskLeafLearner {
+    GuessTheMean { learner: GuessTheMeanLearner },
+}
+
+impl MultiTaskLeafLearner {
+    pub fn mean(learner: GuessTheMeanLearner) -> Self {
+        Self::GuessTheMean { learner }
+    }
+}
+
+impl Default for MultiTaskLeafLearner {
+    fn default() -> Self {
+        Self::mean(GuessTheMeanLearner::default())
+    }
+}
+
+/// Fits each task on the rows where its label is present, using the type of the first label.
+impl Learner<MultiTaskLabel> for MultiTaskLeafLearner {
+    fn fit(
+        &self,
+        data: &[TrainingRow<MultiTaskLabel>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<MultiTaskLabel>>> {
+        let Self::GuessTheMean { learner } = self;
+        let nt = data.iter().map(|row| row.label.len()).max().unwrap_or(0);
+
+        let tasks = (0..nt)
+            .map(|k| {
+                let present: Vec<(&TrainingRow<MultiTaskLabel>, AnyValue)> = data
+                    .iter()
+                    .filter_map(|row| row.label.get(k).copied().flatten().map(|y| (row, y)))
+                    .collect();
+
+                match present.first() {
+                    None => Ok(None),
+                    Some((_, AnyValue::Real(_))) => {
+                        let rows: Vec<TrainingRow<f64>> = present
+                            .iter()
+                            .filter_map(|(row, y)| {
+                                Some(TrainingRow::new(
+                                    row.features.clone(),
+                                    y.as_real()?,
+                                    row.weight,
+                                ))
+                            })
+                            .collect();
+                        Ok(Some(TaskModel::Real(learner.fit(&rows, rng)?)))
+                    }
+                    Some((_, AnyValue::Categorical(_))) => {
+                        let rows: Vec<TrainingRow<usize>> = present
+                            .iter()
+                            .filter_map(|(row, y)| {
+                                let y = y.as_categorical()?;
+                                Some(TrainingRow::new(row.features.clone(), y, row.weight))
+                            })
+                            .collect();
+                        Ok(Some(TaskModel::Categorical(learner.fit(&rows, rng)?)))
+                    }
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Box::new(MultiTaskLeafModel { tasks }))
+    }
+}
+
+enum TaskModel {
+    Real(Box<dyn Model<f64>>),
+    Categorical(Box<dyn Model<usize>>),
+}
+
+/// A leaf model with one model per task, or none when the task had no labels in the leaf.
+pub struct MultiTaskLeafModel {
+    tasks: Vec<Option<TaskModel>>,
+}
+
+impl Model<MultiTaskLabel> for MultiTaskLeafModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<MultiTaskLabel>>> {
+        let mut result: Vec<MultiTaskLabel> = vec![vec![None; self.tasks.len()]; inputs.len()];
+        let mut uncertainty = result.clone();
+
+        for (k, task) in self.tasks.iter().enumerate() {
+            match task {
+                Some(TaskModel::Real(model)) => {
+                    let prediction = model.transform(inputs)?;
+                    for (i, y) in prediction.expected().into_iter().enumerate() {
+                        result[i][k] = Some(AnyValue::Real(y));
+                    }
+                    for (i, u) in prediction.uncertainty().into_iter().flatten().enumerate() {
+                        uncertainty[i][k] = Some(AnyValue::Real(u));
+                    }
+                }
+                Some(TaskModel::Categorical(model)) => {
+                    let prediction = model.transform(inputs)?;
+                    for (i, y) in prediction.expected().into_iter().enumerate() {
+                        result[i][k] = Some(AnyValue::Categorical(y));
+                    }
+                }
+                None => {}
+            }
+        }
+
+        Ok(Box::new(MultiTaskPrediction { result, uncertainty }))
+    }
+}
+
+/// A prediction result with one optional value per task for each row.
+///
+/// The uncertainty holds the standard deviation of real tasks, and is missing for categorical tasks.
+#[derive(Clone, Debug)]
+pub struct MultiTaskPrediction {
+    result: Vec<MultiTaskLabel>,
+    uncertainty: Vec<MultiTaskLabel>,
+}
+
+impl MultiTaskPrediction {
+    pub(crate) fn new(result: Vec<MultiTaskLabel>, uncertainty: Vec<MultiTaskLabel>) -> Self {
+        Self { result, uncertainty }
+    }
+
+    /// The predictions of a single task, which are missing where no training label reached the row.
+    pub fn task(&self, k: usize) -> Vec<Option<AnyValue>> {
+        self.result.iter().map(|row| row.get(k).copied().flatten()).collect()
+    }
+
+    /// The predictions of a real task.
+    pub fn real_task(&self, k: usize) -> Vec<Option<f64>> {
+        self.task(k).into_iter().map(|y| y.and_then(|y| y.as_real())).collect()
+    }
+
+    /// The predictions of a categorical task.
+    pub fn categorical_task(&self, k: usize) -> Vec<Option<usize>> {
+        self.task(k).into_iter().map(|y| y.and_then(|y| y.as_categorical())).collect()
+    }
+}
+
+impl Prediction<MultiTaskLabel> for MultiTaskPrediction {
+    fn expected(&self) -> Vec<MultiTaskLabel> {
+        self.result.clone()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<MultiTaskLabel>> {
+        Some(self.uncertainty.clone())
+    }
+}
@@ -1,12 +1,24 @@
-use crate::{
-    core::{Learner, Model, TrainingRow},
-    learners::{GuessTheMeanLearner, LinearRegressionLearner},
+use rand::Rng;
+
+use crate::core::{
+    fit_per_output, EmpiricalDistribution, FeatureRow, Learner, Model, ModelingError, Prediction,
+    QuantilePrediction, Result, TrainingRow,
 };
+use crate::linear::{GuessTheMeanLearner, LinearRegressionLearner};
+
+use super::{LinearLeafModel, LinearLeafParameters};
 
 #[derive(Clone, Debug)]
 pub enum RegressionLeafLearner {
-    GuessTheMean { learner: GuessTheMeanLearner },
-    LinearRegression { learner: LinearRegressionLearner },
+    GuessTheMean {
+        learner: GuessTheMeanLearner,
+    },
+    LinearRegression {
+        learner: LinearRegressionLearner,
+        params: LinearLeafParameters,
+    },
+    /// Keeps the training labels and weights, predicting their empirical distribution.
+    Empirical,
 }
 
 impl RegressionLeafLearner {
@@ -15,15 +27,86 @@ impl RegressionLeafLearner {
     }
 
     pub fn linreg(learner: LinearRegressionLearner) -> Self {
-        Self::LinearRegression { learner }
+        Self::linreg_with_params(learner, LinearLeafParameters::default())
+    }
+
+    pub fn linreg_with_params(
+        learner: LinearRegressionLearner,
+        params: LinearLeafParameters,
+    ) -> Self {
+        Self::LinearRegression { learner, params }
+    }
+
+    pub fn empirical() -> Self {
+        Self::Empirical
+    }
+}
+
+impl Default for RegressionLeafLearner {
+    fn default() -> Self {
+        Self::mean(GuessTheMeanLearner::default())
     }
 }
 
 impl Learner<f64> for RegressionLeafLearner {
-    fn fit(&mut self, data: &[TrainingRow<f64>]) -> Box<dyn Model<f64>> {
+    fn fit(&self, data: &[TrainingRow<f64>], rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        match self {
+            Self::GuessTheMean { learner } => learner.fit(data, rng),
+            Self::LinearRegression { learner, params } => {
+                Ok(Box::new(LinearLeafModel::fit(learner, params.feature_selection, data)?.0))
+            }
+            Self::Empirical => Ok(Box::new(EmpiricalLeafModel::new(data)?)),
+        }
+    }
+}
+
+impl Learner<Vec<f64>> for RegressionLeafLearner {
+    fn fit(
+        &self,
+        data: &[TrainingRow<Vec<f64>>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<Vec<f64>>>> {
         match self {
-            Self::GuessTheMean { learner } => learner.fit(data),
-            Self::LinearRegression { learner } => learner.fit(data),
+            Self::GuessTheMean { learner } => learner.fit(data, rng),
+            Self::LinearRegression { learner, params } => {
+                Ok(Box::new(LinearLeafModel::fit(learner, params.feature_selection, data)?.0))
+            }
+            Self::Empirical => fit_per_output(self, data, rng),
         }
     }
 }
+
+/// A leaf model predicting the weighted empirical distribution of its training labels.
+#[derive(Clone, Debug)]
+pub struct EmpiricalLeafModel {
+    distribution: EmpiricalDistribution,
+}
+
+impl EmpiricalLeafModel {
+    fn new(data: &[TrainingRow<f64>]) -> Result<Self> {
+        let distribution = EmpiricalDistribution::new(
+            data.iter().map(|row| (row.label, row.weight.unwrap_or(1.0))),
+        );
+        if distribution.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+        Ok(Self { distribution })
+    }
+
+    pub fn distribution(&self) -> &EmpiricalDistribution {
+        &self.distribution
+    }
+}
+
+impl Model<f64> for EmpiricalLeafModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<f64>>> {
+        Ok(Box::new(QuantilePrediction::new(vec![self.distribution.clone(); inputs.len()])))
+    }
+
+    /// The mean of the distribution, which is all that a compiled tree keeps.
+    fn constant_outputs(&self) -> Option<Vec<f64>> {
+        Some(vec![self.distribution.mean()])
+    }
+}
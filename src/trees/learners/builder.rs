@@ -0,0 +1,339 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use rand::Rng;
+
+use crate::core::TrainingRow;
+use crate::trees::splits::{Split, Splitter};
+
+use super::{DecisionTreeParameters, TrainingNode};
+
+/// Grows a training tree by recursively applying the best split.
+///
+/// The training rows are shared by the whole tree, and each node refers to its rows by their
+/// indices, so rows are only cloned once into the leaves. Trees grow depth-first, or best-first by
+/// impurity decrease when the number of leaves is limited.
+#[derive(Debug)]
+pub(crate) struct TreeBuilder<S> {
+    splitter: S,
+    params: DecisionTreeParameters,
+    min_leaf_weight: f64,
+}
+
+impl<S> TreeBuilder<S> {
+    pub fn new(splitter: S, params: DecisionTreeParameters) -> Self {
+        Self { splitter, params, min_leaf_weight: 0.0 }
+    }
+
+    /// Grow the tree, then apply cost-complexity pruning when `ccp_alpha` is positive.
+    pub fn build<T: Clone>(
+        &mut self,
+        data: &[TrainingRow<T>],
+        rng: &mut impl Rng,
+    ) -> TrainingNode<T>
+    where
+        S: Splitter<T>,
+    {
+        let params = self.params;
+        if params.max_depth == 0 || data.is_empty() {
+            return TrainingNode::leaf(data.to_vec(), 0);
+        }
+
+        let total_weight: f64 = data.iter().map(|row| row.weight.unwrap_or(1.0)).sum();
+        self.min_leaf_weight = params.min_weight_fraction_leaf * total_weight;
+
+        let data_nf = data[0].features.data.len();
+        let actual_nf = data_nf.min(params.num_features);
+        let indices: Vec<usize> = (0..data.len()).collect();
+        let stats = self.splitter.node_stats(data, &indices);
+        let root = if params.max_leaves < usize::MAX {
+            self.build_best_first(data, indices, stats, actual_nf, rng)
+        } else {
+            self.build_child(data, indices, stats, actual_nf, params.max_depth, rng)
+        };
+
+        if params.ccp_alpha > 0.0 {
+            root.prune(params.ccp_alpha)
+        } else {
+            root
+        }
+    }
+
+    /// Find the split of a node at `depth`, if it may be split.
+    fn find_split<T>(
+        &mut self,
+        data: &[TrainingRow<T>],
+        indices: &[usize],
+        stats: &S::NodeStats,
+        num_features: usize,
+        depth: usize,
+        rng: &mut impl Rng,
+    ) -> Option<(Split, f64)>
+    where
+        S: Splitter<T>,
+    {
+        let min_instances = self.params.min_leaf_instances;
+        if indices.len() < 2 * min_instances || depth >= self.params.max_depth {
+            return None;
+        }
+
+        // The splitter skips splits leaving too little of the training weight on either side
+        let (split, delta) = self.splitter.find_best_split_with_stats(
+            data,
+            indices,
+            stats,
+            num_features,
+            min_instances,
+            self.min_leaf_weight,
+            rng,
+        );
+        if split == Split::None || delta <= self.params.min_impurity_decrease {
+            return None;
+        }
+
+        Some((split, delta))
+    }
+
+    /// Partition the rows of a node between its children, keeping their relative order.
+    fn partition<T>(
+        &mut self,
+        data: &[TrainingRow<T>],
+        indices: Vec<usize>,
+        stats: S::NodeStats,
+        split: &Split,
+    ) -> (NodeRows<S::NodeStats>, NodeRows<S::NodeStats>)
+    where
+        S: Splitter<T>,
+    {
+        let (left, right): (Vec<usize>, Vec<usize>) =
+            indices.into_iter().partition(|&i| split.turn_left(&data[i].features));
+        let (left_stats, right_stats) = self.splitter.partition_stats(data, stats, &left, &right);
+        ((left, left_stats), (right, right_stats))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn split_internal<T: Clone>(
+        &mut self,
+        data: &[TrainingRow<T>],
+        (indices, stats): NodeRows<S::NodeStats>,
+        split: Split,
+        delta_impurity: f64,
+        num_features: usize,
+        remaining_depth: usize,
+        rng: &mut impl Rng,
+    ) -> TrainingNode<T>
+    where
+        S: Splitter<T>,
+    {
+        let ((left, left_stats), (right, right_stats)) =
+            self.partition(data, indices, stats, &split);
+
+        let left_child =
+            self.build_child(data, left, left_stats, num_features, remaining_depth, rng);
+        let right_child =
+            self.build_child(data, right, right_stats, num_features, remaining_depth, rng);
+
+        TrainingNode::internal(
+            split,
+            Box::new(left_child),
+            Box::new(right_child),
+            delta_impurity,
+            self.params.max_depth - remaining_depth - 1,
+        )
+    }
+
+    fn build_child<T: Clone>(
+        &mut self,
+        data: &[TrainingRow<T>],
+        indices: Vec<usize>,
+        stats: S::NodeStats,
+        num_features: usize,
+        remaining_depth: usize,
+        rng: &mut impl Rng,
+    ) -> TrainingNode<T>
+    where
+        S: Splitter<T>,
+    {
+        let current_depth = self.params.max_depth - remaining_depth;
+        match self.find_split(data, &indices, &stats, num_features, current_depth, rng) {
+            Some((split, delta)) => self.split_internal(
+                data,
+                (indices, stats),
+                split,
+                delta,
+                num_features,
+                remaining_depth - 1,
+                rng,
+            ),
+            None => leaf(data, &indices, current_depth),
+        }
+    }
+
+    /// Grow the tree by always splitting the leaf with the largest impurity decrease,
+    /// until there are `max_leaves` leaves or no leaf can be split.
+    fn build_best_first<T: Clone>(
+        &mut self,
+        data: &[TrainingRow<T>],
+        indices: Vec<usize>,
+        stats: S::NodeStats,
+        num_features: usize,
+        rng: &mut impl Rng,
+    ) -> TrainingNode<T>
+    where
+        S: Splitter<T>,
+    {
+        let mut nodes: Vec<GrowingNode<S::NodeStats>> = vec![];
+        let mut queue: BinaryHeap<Candidate> = BinaryHeap::new();
+        self.push_leaf(data, &mut nodes, &mut queue, (indices, stats), 0, num_features, rng);
+
+        let mut num_leaves = 1;
+        while num_leaves < self.params.max_leaves {
+            let Some(Candidate { id, .. }) = queue.pop() else {
+                break;
+            };
+            let GrowingNode::Leaf { indices, stats, depth, split: Some((split, delta)) } =
+                std::mem::replace(&mut nodes[id], GrowingNode::Taken)
+            else {
+                unreachable!("Only leaves with a split are queued.");
+            };
+
+            let (left, right) = self.partition(data, indices, stats, &split);
+            let left =
+                self.push_leaf(data, &mut nodes, &mut queue, left, depth + 1, num_features, rng);
+            let right =
+                self.push_leaf(data, &mut nodes, &mut queue, right, depth + 1, num_features, rng);
+            nodes[id] = GrowingNode::Internal { split, left, right, delta, depth };
+            num_leaves += 1;
+        }
+
+        GrowingNode::assemble(data, &mut nodes, 0)
+    }
+
+    /// Add a leaf to the growing tree, queueing it when it can be split.
+    #[allow(clippy::too_many_arguments)]
+    fn push_leaf<T>(
+        &mut self,
+        data: &[TrainingRow<T>],
+        nodes: &mut Vec<GrowingNode<S::NodeStats>>,
+        queue: &mut BinaryHeap<Candidate>,
+        (indices, stats): NodeRows<S::NodeStats>,
+        depth: usize,
+        num_features: usize,
+        rng: &mut impl Rng,
+    ) -> usize
+    where
+        S: Splitter<T>,
+    {
+        let id = nodes.len();
+        let split = self.find_split(data, &indices, &stats, num_features, depth, rng);
+        if let Some((_, delta)) = split {
+            queue.push(Candidate { delta, id });
+        }
+        nodes.push(GrowingNode::Leaf { indices, stats, depth, split });
+        id
+    }
+}
+
+/// A leaf holding copies of the rows of `data` at `indices`.
+fn leaf<T: Clone>(data: &[TrainingRow<T>], indices: &[usize], depth: usize) -> TrainingNode<T> {
+    TrainingNode::leaf(indices.iter().map(|&i| data[i].clone()).collect(), depth)
+}
+
+/// The indices of the rows of a node with the statistics kept by the splitter.
+type NodeRows<N> = (Vec<usize>, N);
+
+/// A node of a tree grown best-first, referring to its children by index.
+enum GrowingNode<N> {
+    Leaf { indices: Vec<usize>, stats: N, depth: usize, split: Option<(Split, f64)> },
+    Internal { split: Split, left: usize, right: usize, delta: f64, depth: usize },
+    Taken,
+}
+
+impl<N> GrowingNode<N> {
+    fn assemble<T: Clone>(
+        data: &[TrainingRow<T>],
+        nodes: &mut [GrowingNode<N>],
+        id: usize,
+    ) -> TrainingNode<T> {
+        match std::mem::replace(&mut nodes[id], GrowingNode::Taken) {
+            GrowingNode::Leaf { indices, depth, .. } => leaf(data, &indices, depth),
+            GrowingNode::Internal { split, left, right, delta, depth } => {
+                let left = Box::new(Self::assemble(data, nodes, left));
+                let right = Box::new(Self::assemble(data, nodes, right));
+                TrainingNode::internal(split, left, right, delta, depth)
+            }
+            GrowingNode::Taken => unreachable!("Every node is assembled once."),
+        }
+    }
+}
+
+/// A leaf waiting to be split, ordered by impurity decrease and then by creation.
+struct Candidate {
+    delta: f64,
+    id: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.delta.total_cmp(&other.delta).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::trees::splits::RegressionSplitter;
+
+    #[test]
+    fn test_best_first() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // A large step at x = 50 and a small step at x = 25, over smaller steps
+        let data: Vec<TrainingRow<f64>> = (0..100)
+            .map(|i| {
+                let x = i as f64;
+                let y = 100.0 * (x >= 50.0) as u8 as f64
+                    + 10.0 * (x >= 25.0) as u8 as f64
+                    + (i % 5) as f64;
+                TrainingRow::new(vec![x], y, None)
+            })
+            .collect();
+
+        let params =
+            DecisionTreeParameters::default().with_min_leaf_instances(1).with_max_leaves(3);
+        let root = TreeBuilder::new(RegressionSplitter::new(false), params).build(&data, &mut rng);
+        assert_eq!(root.num_leaves(), 3);
+        match &root {
+            TrainingNode::Internal { split, left, .. } => {
+                assert_eq!(*split, Split::Real(0, 49.5));
+                assert!(matches!(
+                    **left,
+                    TrainingNode::Internal { split: Split::Real(0, 24.5), .. }
+                ));
+            }
+            TrainingNode::Leaf { .. } => panic!("The root should be split"),
+        }
+
+        // Leaves must hold at least 30% of the weight, which rules out the smaller steps
+        let params = DecisionTreeParameters::default()
+            .with_min_leaf_instances(1)
+            .with_min_weight_fraction_leaf(0.3);
+        let root = TreeBuilder::new(RegressionSplitter::new(false), params).build(&data, &mut rng);
+        assert_eq!(root.num_leaves(), 2);
+    }
+}
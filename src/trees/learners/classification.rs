@@ -1 +1,156 @@
+use rand::Rng;
 
+use crate::core::{FeatureRow, Learner, Model, ModelingError, Prediction, Result, TrainingRow};
+use crate::trees::leaf::ClassificationLeafLearner;
+use crate::trees::splits::Splitter;
+
+use super::{
+    CompileTree, CompiledTree, DecisionTreeParameters, ModelNode, TrainingNode, TreeBuilder,
+};
+
+/// A classification tree learner, where class labels start at 1 and 0 marks an unknown label.
+#[derive(Clone, Debug)]
+pub struct ClassificationTreeLearner<S> {
+    splitter: S,
+    learner: ClassificationLeafLearner,
+    params: DecisionTreeParameters,
+}
+
+impl<S: Splitter<usize> + Clone> ClassificationTreeLearner<S> {
+    pub fn new(
+        splitter: S,
+        learner: ClassificationLeafLearner,
+        params: DecisionTreeParameters,
+    ) -> Self {
+        Self { splitter, learner, params }
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(
+        &self,
+        data: &[TrainingRow<usize>],
+        rng: &mut impl Rng,
+    ) -> Result<ClassificationTreeModel> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+
+        let mut builder = TreeBuilder::new(self.splitter.clone(), self.params);
+        let training_node = builder.build(data, rng);
+        let model_node = training_node.build_model(&self.learner, rng)?;
+
+        Ok(ClassificationTreeModel { training_node, model_node })
+    }
+}
+
+impl<S: Splitter<usize> + Clone> Learner<usize> for ClassificationTreeLearner<S> {
+    fn fit(
+        &self,
+        data: &[TrainingRow<usize>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<usize>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
+    }
+}
+
+/// A model produced by a classification tree learner.
+pub struct ClassificationTreeModel {
+    training_node: TrainingNode<usize>,
+    model_node: ModelNode<usize>,
+}
+
+impl ClassificationTreeModel {
+    /// The root of the tree grown on the training data.
+    pub fn training_root(&self) -> &TrainingNode<usize> {
+        &self.training_node
+    }
+
+    /// The root of the tree of fitted leaf models.
+    pub fn root(&self) -> &ModelNode<usize> {
+        &self.model_node
+    }
+
+    /// Predict the inputs, returning the concrete prediction type.
+    pub fn predict(&self, inputs: &[FeatureRow]) -> Result<ClassificationTreePrediction> {
+        let prediction = self.model_node.transform(inputs)?;
+        Ok(ClassificationTreePrediction {
+            result: prediction.result,
+            probabilities: prediction.probabilities,
+        })
+    }
+}
+
+impl Model<usize> for ClassificationTreeModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<usize>>> {
+        Ok(Box::new(self.predict(inputs)?))
+    }
+}
+
+impl CompileTree for ClassificationTreeModel {
+    fn compile(&self) -> Result<CompiledTree> {
+        CompiledTree::new(&self.model_node)
+    }
+}
+
+/// A prediction result for a classification tree, with the class probabilities reported by the
+/// leaf models.
+#[derive(Clone, Debug)]
+pub struct ClassificationTreePrediction {
+    result: Vec<usize>,
+    probabilities: Option<Vec<Vec<f64>>>,
+}
+
+impl Prediction<usize> for ClassificationTreePrediction {
+    fn expected(&self) -> Vec<usize> {
+        self.result.clone()
+    }
+
+    fn probabilities(&self) -> Option<Vec<Vec<f64>>> {
+        self.probabilities.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::linear::{ClassificationBaseline, GuessTheMeanLearner};
+    use crate::trees::splits::ExtraTreesSplitter;
+
+    #[test]
+    fn test_classification_tree() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Three classes in bands of the first feature
+        let data: Vec<TrainingRow<usize>> = (0..60)
+            .map(|i| {
+                let x = i as f64;
+                TrainingRow::new(vec![x, (i % 7) as f64], 1 + i / 20, None)
+            })
+            .collect();
+
+        let splitter = ExtraTreesSplitter::new();
+        let baseline =
+            GuessTheMeanLearner::default().with_classification(ClassificationBaseline::Prior);
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(1);
+        let tree = ClassificationTreeLearner::new(
+            splitter,
+            ClassificationLeafLearner::mean(baseline),
+            params,
+        );
+        let model = tree.fit_model(&data, &mut rng).unwrap();
+
+        let inputs: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
+        let prediction = model.predict(&inputs).unwrap();
+        let labels: Vec<usize> = data.iter().map(|row| row.label).collect();
+        assert_eq!(prediction.expected(), labels);
+
+        // Pure leaves put all of the probability on the predicted class
+        for (p, label) in prediction.probabilities().unwrap().iter().zip(labels) {
+            assert_eq!(p[label], 1.0);
+        }
+    }
+}
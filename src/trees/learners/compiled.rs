@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+
+use crate::core::{FeatureRow, ModelingError, Result};
+use crate::trees::splits::{CategorySet, Split};
+
+use super::ModelNode;
+
+/// Marks the leaves in the feature array of a compiled tree.
+const LEAF: usize = usize::MAX;
+
+/// Marks the oblique splits in the feature array of a compiled tree.
+const OBLIQUE: usize = usize::MAX - 1;
+
+/// Marks the real splits in the category set array of a compiled tree.
+const REAL: usize = usize::MAX;
+
+/// A tree model that can be flattened into a [`CompiledTree`] for fast inference.
+pub trait CompileTree {
+    /// A flattened copy of the tree, which requires leaves with constant outputs.
+    fn compile(&self) -> Result<CompiledTree>;
+}
+
+/// A tree flattened into arrays for fast inference.
+///
+/// Internal node `i` tests feature `features[i]`, against `thresholds[i]` for real splits or the
+/// category set `sets[i]` for categorical splits. Oblique splits are marked in `features` and
+/// test the hyperplane `sets[i]` against `thresholds[i]`, where the `(feature, weight)` terms of
+/// hyperplane `k` are `terms[offsets[k]..offsets[k + 1]]`. Rows turning left move to node
+/// `children[i]` and the others to the node after it. The nodes are laid out level by level, so
+/// the top of the tree shares a few cache lines. At a leaf, `children[i]` is the index of the
+/// leaf, whose outputs are stored contiguously in `values`.
+///
+/// Only trees with leaf models reporting
+/// [`Model::constant_outputs`](crate::core::Model::constant_outputs) can be compiled.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompiledTree {
+    features: Vec<usize>,
+    thresholds: Vec<f64>,
+    sets: Vec<usize>,
+    children: Vec<usize>,
+    category_sets: Vec<CategorySet>,
+    terms: Vec<(usize, f64)>,
+    offsets: Vec<usize>,
+    values: Vec<f64>,
+    width: usize,
+    depth: usize,
+}
+
+impl CompiledTree {
+    /// Flatten a tree of fitted leaf models.
+    pub fn new<T>(root: &ModelNode<T>) -> Result<Self> {
+        let mut tree = Self {
+            features: vec![LEAF],
+            thresholds: vec![f64::NAN],
+            sets: vec![REAL],
+            children: vec![0],
+            category_sets: vec![],
+            terms: vec![],
+            offsets: vec![0],
+            values: vec![],
+            width: 0,
+            depth: 0,
+        };
+
+        let mut leaves: Vec<Vec<f64>> = vec![];
+        let mut queue = VecDeque::from([(root, 0, 0)]);
+        while let Some((node, index, level)) = queue.pop_front() {
+            tree.depth = tree.depth.max(level);
+            match node {
+                ModelNode::Leaf { model, .. } => {
+                    let outputs = model.constant_outputs().ok_or_else(|| {
+                        ModelingError::CompileError(
+                            "Only trees with constant leaves can be compiled.".into(),
+                        )
+                    })?;
+                    tree.children[index] = leaves.len();
+                    leaves.push(outputs);
+                }
+                ModelNode::Internal { split, left, right, .. } => {
+                    match split {
+                        Split::Real(idx, pivot) => {
+                            tree.features[index] = *idx;
+                            tree.thresholds[index] = *pivot;
+                        }
+                        Split::Categorical(idx, included) => {
+                            tree.features[index] = *idx;
+                            tree.sets[index] = tree.category_sets.len();
+                            tree.category_sets.push(included.clone());
+                        }
+                        Split::Oblique(weights, pivot) => {
+                            tree.features[index] = OBLIQUE;
+                            tree.thresholds[index] = *pivot;
+                            tree.sets[index] = tree.offsets.len() - 1;
+                            tree.terms.extend(weights.iter().copied());
+                            tree.offsets.push(tree.terms.len());
+                        }
+                        Split::None => {
+                            return Err(ModelingError::CompileError(
+                                "Internal nodes must have a split to be compiled.".into(),
+                            ))
+                        }
+                    }
+
+                    // The children are placeholders until they are taken off the queue
+                    let left_index = tree.features.len();
+                    tree.children[index] = left_index;
+                    tree.features.extend([LEAF, LEAF]);
+                    tree.thresholds.extend([f64::NAN, f64::NAN]);
+                    tree.sets.extend([REAL, REAL]);
+                    tree.children.extend([0, 0]);
+                    queue.push_back((left, left_index, level + 1));
+                    queue.push_back((right, left_index + 1, level + 1));
+                }
+            }
+        }
+
+        // Class probabilities may stop at the largest class of each leaf
+        tree.width = leaves.iter().map(|outputs| outputs.len()).max().unwrap_or(0);
+        for mut outputs in leaves {
+            outputs.resize(tree.width, 0.0);
+            tree.values.extend(outputs);
+        }
+        Ok(tree)
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.features.iter().filter(|&&feature| feature == LEAF).count()
+    }
+
+    /// The number of splits on the longest path from the root to a leaf.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The number of outputs of each leaf.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The index of the leaf reached by each row.
+    ///
+    /// The rows are stepped down the tree together, one level at a time, so the lookups for
+    /// different rows are independent of each other.
+    pub fn leaves(&self, inputs: &[FeatureRow]) -> Vec<usize> {
+        let mut nodes = vec![0; inputs.len()];
+        for _ in 0..self.depth {
+            for (node, input) in nodes.iter_mut().zip(inputs) {
+                if self.features[*node] != LEAF {
+                    *node = self.children[*node] + usize::from(!self.turn_left(*node, input));
+                }
+            }
+        }
+        nodes.into_iter().map(|node| self.children[node]).collect()
+    }
+
+    /// The outputs of the leaf reached by each row.
+    pub fn predict(&self, inputs: &[FeatureRow]) -> Vec<&[f64]> {
+        let leaves = self.leaves(inputs);
+        leaves.into_iter().map(|leaf| &self.values[leaf * self.width..][..self.width]).collect()
+    }
+
+    /// Should a row turn left at an internal node, as in [`Split::turn_left`]?
+    fn turn_left(&self, node: usize, input: &FeatureRow) -> bool {
+        if self.features[node] == OBLIQUE {
+            // Rows missing any of the features go right
+            let k = self.sets[node];
+            let projection: f64 = self.terms[self.offsets[k]..self.offsets[k + 1]]
+                .iter()
+                .map(|(index, w)| w * input[*index].as_real().unwrap_or(f64::NAN))
+                .sum();
+            return projection <= self.thresholds[node];
+        }
+
+        let value = &input[self.features[node]];
+        match self.sets[node] {
+            REAL => {
+                let (x, pivot) = (value.as_real().unwrap_or(f64::NAN), self.thresholds[node]);
+                if pivot.is_nan() {
+                    !x.is_nan()
+                } else {
+                    x <= pivot
+                }
+            }
+            set => value.as_categorical().is_some_and(|c| self.category_sets[set].contains(c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::core::{AnyValue, Model, TrainingRow};
+    use crate::linear::{GuessTheMeanLearner, LinearRegressionLearner};
+    use crate::trees::leaf::RegressionLeafLearner;
+    use crate::trees::learners::{DecisionTreeParameters, RegressionTreeLearner};
+    use crate::trees::splits::{ObliqueDirection, ObliqueSplitter, RegressionSplitter};
+
+    use super::*;
+
+    #[test]
+    fn compile_regression_tree() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // A real feature with missing values and a categorical feature
+        let row = |rng: &mut StdRng| {
+            let x: f64 = rng.gen_range(0.0..10.0);
+            let x = if rng.gen::<f64>() < 0.1 { AnyValue::Real(f64::NAN) } else { x.into() };
+            FeatureRow::new(vec![x, AnyValue::Categorical(rng.gen_range(1..6))])
+        };
+        let data: Vec<TrainingRow<f64>> = (0..300)
+            .map(|_| {
+                let features = row(&mut rng);
+                let y = features[0].as_real().filter(|x| !x.is_nan()).unwrap_or(5.0).sin()
+                    + features[1].as_categorical().unwrap() as f64;
+                TrainingRow::new(features, y, None)
+            })
+            .collect();
+
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(1);
+        let leaf = RegressionLeafLearner::mean(GuessTheMeanLearner::default());
+        let learner = RegressionTreeLearner::new(RegressionSplitter::new(false), leaf, params);
+        let model = learner.fit_model(&data, &mut rng).unwrap();
+        let compiled = model.compile().unwrap();
+        assert_eq!(compiled.num_leaves(), model.training_root().num_leaves());
+        assert_eq!(compiled.num_nodes(), 2 * compiled.num_leaves() - 1);
+        assert_eq!(compiled.width(), 1);
+
+        let inputs: Vec<FeatureRow> = (0..500).map(|_| row(&mut rng)).collect();
+        let expected = model.transform(&inputs).unwrap().expected();
+        let predicted: Vec<f64> = compiled.predict(&inputs).iter().map(|v| v[0]).collect();
+        assert_eq!(predicted, expected);
+
+        // Oblique splits evaluate their hyperplane, sending rows with missing values right
+        let splitter = ObliqueSplitter::new(ObliqueDirection::Ridge(1.0));
+        let leaf = RegressionLeafLearner::mean(GuessTheMeanLearner::default());
+        let learner = RegressionTreeLearner::new(splitter, leaf, params);
+        let model = learner.fit_model(&data, &mut rng).unwrap();
+        let compiled = model.compile().unwrap();
+        assert!(compiled.features.contains(&OBLIQUE));
+        let expected = model.transform(&inputs).unwrap().expected();
+        let predicted: Vec<f64> = compiled.predict(&inputs).iter().map(|v| v[0]).collect();
+        assert_eq!(predicted, expected);
+
+        // Leaves that depend on the inputs cannot be compiled
+        let leaf = RegressionLeafLearner::linreg(LinearRegressionLearner::new(true, None));
+        let learner = RegressionTreeLearner::new(RegressionSplitter::new(false), leaf, params);
+        let linear: Vec<TrainingRow<f64>> =
+            (0..50).map(|i| TrainingRow::new(vec![i as f64], 2.0 * i as f64, None)).collect();
+        let result = learner.fit_model(&linear, &mut rng).unwrap().compile();
+        assert!(matches!(result, Err(ModelingError::CompileError(_))));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::core::{RegressionLabel, Result, TrainingRow};
+use crate::linear::LinearRegressionLearner;
+use crate::trees::leaf::{LinearLeafModel, LinearLeafParameters};
+use crate::trees::splits::Split;
+
+use super::{ModelNode, TrainingNode};
+
+/// An intermediate tree of linear models, with the unsmoothed model of every internal node.
+enum LinearNode<T> {
+    Leaf {
+        model: LinearLeafModel<T>,
+        training_weight: f64,
+        depth: usize,
+    },
+    Internal {
+        split: Split,
+        left: Box<LinearNode<T>>,
+        right: Box<LinearNode<T>>,
+        model: Option<LinearLeafModel<T>>,
+        training_weight: f64,
+        depth: usize,
+    },
+}
+
+impl<T> LinearNode<T> {
+    fn training_weight(&self) -> f64 {
+        match self {
+            Self::Leaf { training_weight, .. } | Self::Internal { training_weight, .. } => {
+                *training_weight
+            }
+        }
+    }
+}
+
+impl<T: RegressionLabel + 'static> TrainingNode<T> {
+    /// Fit linear models to the tree as in M5: a model is fit at every node, subtrees are pruned
+    /// where the model of their root has a lower adjusted error, and the leaf models are smoothed
+    /// with the models along the path from the root.
+    ///
+    /// Smoothing is folded into the coefficients, so that each leaf holds a single linear model.
+    pub fn build_linear_model(
+        &self,
+        learner: &LinearRegressionLearner,
+        params: LinearLeafParameters,
+    ) -> Result<ModelNode<T>> {
+        let (root, _) = self.fit_linear(learner, &params)?;
+        Ok(root.smooth(&mut vec![], params.smoothing))
+    }
+
+    fn rows(&self) -> Vec<TrainingRow<T>> {
+        match self {
+            Self::Leaf { data, .. } => data.clone(),
+            Self::Internal { left, right, .. } => {
+                let mut rows = left.rows();
+                rows.extend(right.rows());
+                rows
+            }
+        }
+    }
+
+    /// Fit the linear models bottom-up, returning the tree with its adjusted error.
+    fn fit_linear(
+        &self,
+        learner: &LinearRegressionLearner,
+        params: &LinearLeafParameters,
+    ) -> Result<(LinearNode<T>, f64)> {
+        let training_weight = self.training_weight();
+        match self {
+            Self::Leaf { data, depth } => {
+                let (model, error) = LinearLeafModel::fit(learner, params.feature_selection, data)?;
+                Ok((LinearNode::Leaf { model, training_weight, depth: *depth }, error))
+            }
+            Self::Internal { split, left, right, depth, .. } => {
+                let (left, left_error) = left.fit_linear(learner, params)?;
+                let (right, right_error) = right.fit_linear(learner, params)?;
+                let (wl, wr) = (left.training_weight(), right.training_weight());
+                let subtree_error = (wl * left_error + wr * right_error) / (wl + wr);
+
+                if !params.pruning && params.smoothing == 0.0 {
+                    let node = LinearNode::Internal {
+                        split: split.clone(),
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        model: None,
+                        training_weight,
+                        depth: *depth,
+                    };
+                    return Ok((node, subtree_error));
+                }
+
+                let rows = self.rows();
+                let (model, error) =
+                    LinearLeafModel::fit(learner, params.feature_selection, &rows)?;
+                if params.pruning && error <= subtree_error {
+                    Ok((LinearNode::Leaf { model, training_weight, depth: *depth }, error))
+                } else {
+                    let node = LinearNode::Internal {
+                        split: split.clone(),
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        model: Some(model),
+                        training_weight,
+                        depth: *depth,
+                    };
+                    Ok((node, subtree_error))
+                }
+            }
+        }
+    }
+}
+
+impl<T: RegressionLabel + 'static> LinearNode<T> {
+    /// Build the model tree, smoothing each leaf with the `(model, weight)` pairs of its
+    /// ancestors, where the weight is that of the child on the path to the leaf.
+    ///
+    /// Moving up from the leaf, each prediction `p` becomes `(n * p + k * q) / (n + k)` for the
+    /// prediction `q` of the parent model and the weight `n` below it.
+    fn smooth<'a>(&'a self, path: &mut Vec<(&'a LinearLeafModel<T>, f64)>, k: f64) -> ModelNode<T> {
+        match self {
+            Self::Leaf { model, training_weight, depth } => {
+                let smoothed = path.iter().rev().fold(model.clone(), |current, (parent, n)| {
+                    current.blend(n / (n + k), parent, k / (n + k))
+                });
+                ModelNode::leaf(Box::new(smoothed), *training_weight, *depth)
+            }
+            Self::Internal { split, left, right, model, training_weight, depth } => {
+                let mut child = |node: &'a LinearNode<T>| match model {
+                    Some(model) if k > 0.0 => {
+                        path.push((model, node.training_weight()));
+                        let child = node.smooth(path, k);
+                        path.pop();
+                        Box::new(child)
+                    }
+                    _ => Box::new(node.smooth(path, k)),
+                };
+                let (left, right) = (child(left), child(right));
+                ModelNode::internal(split.clone(), left, right, *training_weight, *depth)
+            }
+        }
+    }
+}
@@ -1,8 +1,19 @@
+mod builder;
 mod classification;
+mod compiled;
+mod linear;
+mod monotonic;
+mod multitask;
 mod nodes;
 mod parameters;
+mod pruning;
 mod regression;
 
+pub(crate) use self::builder::TreeBuilder;
+pub use self::classification::*;
+pub use self::compiled::*;
+pub use self::multitask::*;
 pub use self::nodes::*;
 pub use self::parameters::*;
+pub use self::pruning::*;
 pub use self::regression::*;
@@ -0,0 +1,210 @@
+use rand::Rng;
+
+use crate::core::{FeatureRow, Learner, Model, Prediction, RegressionLabel, Result};
+use crate::trees::splits::{MonotonicConstraints, Monotonicity, Split};
+
+use super::{ModelNode, TrainingNode};
+
+/// Lower and upper bounds on each output of the leaves below a node.
+#[derive(Clone, Debug)]
+struct Bounds {
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+}
+
+impl Bounds {
+    fn clamp(&self, outputs: &[f64]) -> Vec<f64> {
+        outputs
+            .iter()
+            .zip(self.lower.iter().zip(self.upper.iter()))
+            .map(|(y, (lo, hi))| y.max(*lo).min(*hi))
+            .collect()
+    }
+}
+
+impl<T: RegressionLabel + 'static> TrainingNode<T> {
+    /// Fit the leaf learner to the data in every leaf, bounding the predictions of each leaf so
+    /// that the tree is monotonic in the constrained features.
+    ///
+    /// Below a split on a constrained feature, the leaves on either side are bounded by the
+    /// midpoint of the mean labels of the two sides, within the bounds of the split itself.
+    pub fn build_monotonic_model(
+        &self,
+        learner: &impl Learner<T>,
+        constraints: &MonotonicConstraints,
+        rng: &mut impl Rng,
+    ) -> Result<ModelNode<T>> {
+        let no = self.mean_outputs().len();
+        let bounds = Bounds { lower: vec![f64::NEG_INFINITY; no], upper: vec![f64::INFINITY; no] };
+        self.build_bounded(learner, constraints, bounds, rng)
+    }
+
+    fn build_bounded(
+        &self,
+        learner: &impl Learner<T>,
+        constraints: &MonotonicConstraints,
+        bounds: Bounds,
+        rng: &mut impl Rng,
+    ) -> Result<ModelNode<T>> {
+        let weight = self.training_weight();
+        match self {
+            Self::Leaf { data, depth } => {
+                let model = BoundedModel { model: learner.fit(data, rng)?, bounds };
+                Ok(ModelNode::leaf(Box::new(model), weight, *depth))
+            }
+            Self::Internal { split, left, right, depth, .. } => {
+                let (mut left_bounds, mut right_bounds) = (bounds.clone(), bounds.clone());
+                let direction = match split {
+                    Split::Real(idx, _) => constraints.direction(*idx),
+                    _ => Monotonicity::None,
+                };
+                if direction != Monotonicity::None {
+                    let left_means = bounds.clamp(&left.mean_outputs());
+                    let right_means = bounds.clamp(&right.mean_outputs());
+                    let mid = left_means.iter().zip(right_means.iter()).map(|(l, r)| 0.5 * (l + r));
+                    for (k, mid) in mid.enumerate() {
+                        match direction {
+                            Monotonicity::Increasing => {
+                                left_bounds.upper[k] = mid;
+                                right_bounds.lower[k] = mid;
+                            }
+                            _ => {
+                                left_bounds.lower[k] = mid;
+                                right_bounds.upper[k] = mid;
+                            }
+                        }
+                    }
+                }
+
+                let left_model = left.build_bounded(learner, constraints, left_bounds, rng)?;
+                let right_model = right.build_bounded(learner, constraints, right_bounds, rng)?;
+                Ok(ModelNode::internal(
+                    split.clone(),
+                    Box::new(left_model),
+                    Box::new(right_model),
+                    weight,
+                    *depth,
+                ))
+            }
+        }
+    }
+
+    /// The weighted mean of each output over the training rows below the node.
+    fn mean_outputs(&self) -> Vec<f64> {
+        let (sums, weight) = self.output_sums();
+        sums.into_iter().map(|s| s / weight).collect()
+    }
+
+    fn output_sums(&self) -> (Vec<f64>, f64) {
+        match self {
+            Self::Leaf { data, .. } => {
+                let mut sums = vec![];
+                let mut total = 0.0;
+                for row in data.iter() {
+                    let (outputs, weight) = (row.label.outputs(), row.weight.unwrap_or(1.0));
+                    sums.resize(outputs.len(), 0.0);
+                    sums.iter_mut().zip(outputs).for_each(|(s, y)| *s += weight * y);
+                    total += weight;
+                }
+                (sums, total)
+            }
+            Self::Internal { left, right, .. } => {
+                let (mut sums, left_weight) = left.output_sums();
+                let (right_sums, right_weight) = right.output_sums();
+                sums.resize(sums.len().max(right_sums.len()), 0.0);
+                sums.iter_mut().zip(right_sums).for_each(|(s, r)| *s += r);
+                (sums, left_weight + right_weight)
+            }
+        }
+    }
+}
+
+/// A leaf model whose expected outputs are clamped to bounds.
+struct BoundedModel<T> {
+    model: Box<dyn Model<T>>,
+    bounds: Bounds,
+}
+
+impl<T: RegressionLabel + 'static> Model<T> for BoundedModel<T> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<T>>> {
+        let prediction = self.model.transform(inputs)?;
+        let result = prediction
+            .expected()
+            .iter()
+            .map(|y| T::from_outputs(self.bounds.clamp(y.outputs())))
+            .collect();
+        Ok(Box::new(BoundedPrediction { result, uncertainty: prediction.uncertainty() }))
+    }
+
+    fn constant_outputs(&self) -> Option<Vec<f64>> {
+        self.model.constant_outputs().map(|outputs| self.bounds.clamp(&outputs))
+    }
+}
+
+/// The clamped predictions of a bounded leaf, keeping the uncertainty of the leaf model.
+struct BoundedPrediction<T> {
+    result: Vec<T>,
+    uncertainty: Option<Vec<T>>,
+}
+
+impl<T: Clone> Prediction<T> for BoundedPrediction<T> {
+    fn expected(&self) -> Vec<T> {
+        self.result.clone()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<T>> {
+        self.uncertainty.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::core::TrainingRow;
+    use crate::ensemble::BaggedLearner;
+    use crate::linear::GuessTheMeanLearner;
+    use crate::trees::leaf::RegressionLeafLearner;
+    use crate::trees::learners::{DecisionTreeParameters, RegressionTreeLearner};
+    use crate::trees::splits::RegressionSplitter;
+
+    #[test]
+    fn test_monotonic_trees() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Increasing in the first feature and decreasing in the second, under heavy noise
+        let data: Vec<TrainingRow<f64>> = (0..300)
+            .map(|_| {
+                let (a, b) = (rng.gen::<f64>(), rng.gen::<f64>());
+                TrainingRow::new(vec![a, b], a - b + 2.0 * rng.gen::<f64>(), None)
+            })
+            .collect();
+
+        let constraints = MonotonicConstraints::default()
+            .with_feature(0, Monotonicity::Increasing)
+            .with_feature(1, Monotonicity::Decreasing);
+        let leaf = RegressionLeafLearner::mean(GuessTheMeanLearner::default());
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(2);
+        let splitter = RegressionSplitter::new(false);
+        let unconstrained = RegressionTreeLearner::new(splitter, leaf, params);
+        let constrained = unconstrained.clone().with_constraints(constraints);
+
+        let grid: Vec<FeatureRow> = (0..100).map(|i| vec![i as f64 / 100.0, 0.5].into()).collect();
+        let is_increasing =
+            |values: &[f64]| values.windows(2).all(|pair| pair[0] <= pair[1] + 1e-12);
+
+        let model = unconstrained.fit_model(&data, &mut rng).unwrap();
+        assert!(!is_increasing(&model.transform(&grid).unwrap().expected()));
+
+        let model = constrained.fit_model(&data, &mut rng).unwrap();
+        assert!(is_increasing(&model.transform(&grid).unwrap().expected()));
+        let grid: Vec<FeatureRow> =
+            (0..100).map(|i| vec![0.5, 1.0 - i as f64 / 100.0].into()).collect();
+        assert!(is_increasing(&model.transform(&grid).unwrap().expected()));
+
+        // The mean over constrained trees is monotonic as well
+        let forest = BaggedLearner::new(constrained, 10).fit_model(&data, &mut rng).unwrap();
+        assert!(is_increasing(&forest.predict(&grid).unwrap().expected()));
+    }
+}
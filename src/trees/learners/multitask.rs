@@ -0,0 +1,129 @@
+use rand::Rng;
+
+use crate::core::{
+    FeatureRow, Learner, Model, ModelingError, MultiTaskLabel, Prediction, Result, TrainingRow,
+};
+use crate::trees::leaf::{MultiTaskLeafLearner, MultiTaskPrediction};
+use crate::trees::splits::{MultiTaskSplitter, Splitter};
+
+use super::{
+    CompileTree, CompiledTree, DecisionTreeParameters, ModelNode, TrainingNode, TreeBuilder,
+};
+
+/// A decision tree learner shared across tasks with mixed real and categorical labels.
+///
+/// Any task label may be missing on a row, and only contributes to the impurity where present.
+#[derive(Clone, Debug)]
+pub struct MultiTaskTreeLearner<S = MultiTaskSplitter> {
+    splitter: S,
+    learner: MultiTaskLeafLearner,
+    params: DecisionTreeParameters,
+}
+
+impl<S: Splitter<MultiTaskLabel> + Clone> MultiTaskTreeLearner<S> {
+    pub fn new(splitter: S, learner: MultiTaskLeafLearner, params: DecisionTreeParameters) -> Self {
+        Self { splitter, learner, params }
+    }
+
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model(
+        &self,
+        data: &[TrainingRow<MultiTaskLabel>],
+        rng: &mut impl Rng,
+    ) -> Result<MultiTaskTreeModel> {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+
+        let mut builder = TreeBuilder::new(self.splitter.clone(), self.params);
+        let training_node = builder.build(data, rng);
+        let model_node = training_node.build_model(&self.learner, rng)?;
+
+        Ok(MultiTaskTreeModel { training_node, model_node })
+    }
+}
+
+impl<S: Splitter<MultiTaskLabel> + Clone> Learner<MultiTaskLabel> for MultiTaskTreeLearner<S> {
+    fn fit(
+        &self,
+        data: &[TrainingRow<MultiTaskLabel>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<MultiTaskLabel>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
+    }
+}
+
+/// A model produced by a multi-task tree learner.
+pub struct MultiTaskTreeModel {
+    training_node: TrainingNode<MultiTaskLabel>,
+    model_node: ModelNode<MultiTaskLabel>,
+}
+
+impl MultiTaskTreeModel {
+    /// The root of the tree grown on the training data.
+    pub fn training_root(&self) -> &TrainingNode<MultiTaskLabel> {
+        &self.training_node
+    }
+
+    /// The root of the tree of fitted leaf models.
+    pub fn root(&self) -> &ModelNode<MultiTaskLabel> {
+        &self.model_node
+    }
+
+    /// Predict every task for the inputs, returning the concrete prediction type.
+    pub fn predict(&self, inputs: &[FeatureRow]) -> Result<MultiTaskPrediction> {
+        let prediction = self.model_node.transform(inputs)?;
+        let uncertainty =
+            prediction.uncertainty.unwrap_or_else(|| vec![vec![]; prediction.result.len()]);
+        Ok(MultiTaskPrediction::new(prediction.result, uncertainty))
+    }
+}
+
+impl Model<MultiTaskLabel> for MultiTaskTreeModel {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<MultiTaskLabel>>> {
+        Ok(Box::new(self.predict(inputs)?))
+    }
+}
+
+impl CompileTree for MultiTaskTreeModel {
+    fn compile(&self) -> Result<CompiledTree> {
+        CompiledTree::new(&self.model_node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::core::AnyValue;
+
+    #[test]
+    fn test_sparse_mixed_tasks() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // A real task stepping at x = 10, and a categorical task stepping at x = 5,
+        // with each task measured on only some of the rows
+        let data: Vec<TrainingRow<MultiTaskLabel>> = (0..20)
+            .map(|i| {
+                let x = i as f64;
+                let real = (i % 3 != 0).then_some(AnyValue::from(if x < 10.0 { 1.0 } else { 4.0 }));
+                let class = (i % 2 == 0).then_some(AnyValue::from(if x < 5.0 { 1 } else { 2 }));
+                TrainingRow::new(vec![x], vec![real, class], None)
+            })
+            .collect();
+
+        let splitter = MultiTaskSplitter::new(false);
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(1);
+        let tree = MultiTaskTreeLearner::new(splitter, MultiTaskLeafLearner::default(), params);
+        let model = tree.fit_model(&data, &mut rng).unwrap();
+
+        let inputs: Vec<FeatureRow> = vec![vec![2.0].into(), vec![7.0].into(), vec![15.0].into()];
+        let prediction = model.predict(&inputs).unwrap();
+        assert_eq!(prediction.real_task(0), vec![Some(1.0), Some(1.0), Some(4.0)]);
+        assert_eq!(prediction.categorical_task(1), vec![Some(1), Some(2), Some(2)]);
+        assert_eq!(prediction.expected().len(), 3);
+    }
+}
@@ -1,30 +1,37 @@
-use crate::core::{Label, Model, TrainingRow};
+use std::fmt::Write;
+
+use rand::Rng;
+
+use crate::core::{
+    EmpiricalDistribution, FeatureRow, Learner, Model, ModelingError, Result, TrainingRow,
+};
 use crate::trees::splits::Split;
+use crate::utils::par_map;
 
 #[derive(Clone, Debug)]
-pub enum TrainingNode<L: Label> {
+pub enum TrainingNode<T> {
     Leaf {
-        data: Vec<TrainingRow<L>>,
+        data: Vec<TrainingRow<T>>,
         depth: usize,
     },
     Internal {
         split: Split,
-        left: Box<TrainingNode<L>>,
-        right: Box<TrainingNode<L>>,
+        left: Box<TrainingNode<T>>,
+        right: Box<TrainingNode<T>>,
         delta: f64,
         depth: usize,
     },
 }
 
-impl<L: Label> TrainingNode<L> {
-    pub fn leaf(data: Vec<TrainingRow<L>>, depth: usize) -> Self {
+impl<T> TrainingNode<T> {
+    pub fn leaf(data: Vec<TrainingRow<T>>, depth: usize) -> Self {
         TrainingNode::Leaf { data, depth }
     }
 
     pub fn internal(
         split: Split,
-        left: Box<TrainingNode<L>>,
-        right: Box<TrainingNode<L>>,
+        left: Box<TrainingNode<T>>,
+        right: Box<TrainingNode<T>>,
         delta: f64,
         depth: usize,
     ) -> Self {
@@ -33,55 +40,203 @@ impl<L: Label> TrainingNode<L> {
 
     pub fn training_weight(&self) -> f64 {
         match self {
-            Self::Leaf { data, .. } => data.iter().map(|row| row.weight).sum(),
+            Self::Leaf { data, .. } => data.iter().map(|row| row.weight.unwrap_or(1.0)).sum(),
             Self::Internal { left, right, .. } => left.training_weight() + right.training_weight(),
         }
     }
 
-    pub fn build_model(&self, learner: &LeafLearner) -> ModelNode<L> {
+    /// Fit the leaf learner to the data in every leaf.
+    pub fn build_model(
+        &self,
+        learner: &impl Learner<T>,
+        rng: &mut impl Rng,
+    ) -> Result<ModelNode<T>> {
         let weight = self.training_weight();
         match self {
             Self::Leaf { data, depth } => {
-                todo!()
+                Ok(ModelNode::leaf(learner.fit(data, rng)?, weight, *depth))
             }
             Self::Internal { split, left, right, depth, .. } => {
-                let left_model = Box::new(left.build_model(learner));
-                let right_model = Box::new(right.build_model(learner));
+                let left_model = Box::new(left.build_model(learner, rng)?);
+                let right_model = Box::new(right.build_model(learner, rng)?);
 
-                ModelNode::internal(split.clone(), left_model, right_model, weight, *depth)
+                Ok(ModelNode::internal(split.clone(), left_model, right_model, weight, *depth))
             }
         }
     }
 }
 
-#[derive(Debug)]
-pub enum ModelNode<L: Label> {
+pub enum ModelNode<T> {
     Leaf {
-        model: Box<dyn Model<L>>,
+        model: Box<dyn Model<T>>,
         training_weight: f64,
         depth: usize,
     },
     Internal {
         split: Split,
-        left: Box<ModelNode<L>>,
-        right: Box<ModelNode<L>>,
+        left: Box<ModelNode<T>>,
+        right: Box<ModelNode<T>>,
         training_weight: f64,
         depth: usize,
     },
 }
 
-impl<L: Label> ModelNode<L> {
-    pub fn leaf(model: Box<dyn Model<L>>, training_weight: f64, depth: usize) -> Self {
+impl<T> ModelNode<T> {
+    pub fn leaf(model: Box<dyn Model<T>>, training_weight: f64, depth: usize) -> Self {
         ModelNode::Leaf { model, training_weight, depth }
     }
 
     pub fn internal(
         split: Split,
-        left: Box<ModelNode<L>>,
-        right: Box<ModelNode<L>>,
+        left: Box<ModelNode<T>>,
+        right: Box<ModelNode<T>>,
         training_weight: f64,
         depth: usize,
     ) -> Self {
         ModelNode::Internal { split, left, right, training_weight, depth }
     }
+
+    /// A text rendering of the tree, with the condition for turning left at each split and the
+    /// training weight of each leaf.
+    pub fn export_text(&self) -> String {
+        let mut text = String::new();
+        self.write_text(&mut text, 0);
+        text
+    }
+
+    fn write_text(&self, text: &mut String, level: usize) {
+        let indent = "|   ".repeat(level);
+        match self {
+            Self::Leaf { training_weight, .. } => {
+                let _ = writeln!(text, "{}|--- leaf (weight {})", indent, training_weight);
+            }
+            Self::Internal { split, left, right, .. } => {
+                let _ = writeln!(text, "{}|--- {}", indent, split);
+                left.write_text(text, level + 1);
+                let _ = writeln!(text, "{}|--- else", indent);
+                right.write_text(text, level + 1);
+            }
+        }
+    }
+
+    /// Expected values for the inputs, with uncertainties, class probabilities and predictive
+    /// distributions when every leaf reports them.
+    ///
+    /// The rows are routed down the tree in batches of [`TRANSFORM_BATCH_SIZE`], which are
+    /// transformed in parallel with the `rayon` feature.
+    pub(crate) fn transform(&self, inputs: &[FeatureRow]) -> Result<NodePrediction<T>>
+    where
+        T: Send,
+    {
+        let batches: Vec<&[FeatureRow]> = inputs.chunks(TRANSFORM_BATCH_SIZE).collect();
+        let predictions = par_map(&batches, |batch| self.transform_batch(batch));
+
+        let mut combined = NodePrediction {
+            result: Vec::with_capacity(inputs.len()),
+            uncertainty: Some(vec![]),
+            probabilities: Some(vec![]),
+            distributions: Some(vec![]),
+        };
+        for prediction in predictions {
+            let prediction = prediction?;
+            combined.result.extend(prediction.result);
+            concat(&mut combined.uncertainty, prediction.uncertainty);
+            concat(&mut combined.probabilities, prediction.probabilities);
+            concat(&mut combined.distributions, prediction.distributions);
+        }
+        Ok(combined)
+    }
+
+    fn transform_batch(&self, inputs: &[FeatureRow]) -> Result<NodePrediction<T>> {
+        let n = inputs.len();
+        let mut outputs = LeafOutputs {
+            expected: (0..n).map(|_| None).collect(),
+            uncertainty: (0..n).map(|_| None).collect(),
+            probabilities: vec![None; n],
+            distributions: vec![None; n],
+        };
+        self.transform_into(inputs, (0..n).collect(), &mut outputs)?;
+
+        let result = outputs.expected.into_iter().collect::<Option<Vec<T>>>().ok_or_else(|| {
+            ModelingError::TransformError("A leaf model returned too few predictions.".into())
+        })?;
+        Ok(NodePrediction {
+            result,
+            uncertainty: outputs.uncertainty.into_iter().collect(),
+            probabilities: outputs.probabilities.into_iter().collect(),
+            distributions: outputs.distributions.into_iter().collect(),
+        })
+    }
+
+    /// Route the rows at `indices` down the tree, transforming each batch that reaches a leaf.
+    /// The leaf outputs are written at the original row positions.
+    fn transform_into(
+        &self,
+        inputs: &[FeatureRow],
+        indices: Vec<usize>,
+        outputs: &mut LeafOutputs<T>,
+    ) -> Result<()> {
+        if indices.is_empty() {
+            return Ok(());
+        }
+
+        match self {
+            Self::Leaf { model, .. } => {
+                let rows: Vec<FeatureRow> = indices.iter().map(|&i| inputs[i].clone()).collect();
+                let prediction = model.transform(&rows)?;
+                for (&i, value) in indices.iter().zip(prediction.expected()) {
+                    outputs.expected[i] = Some(value);
+                }
+                if let Some(values) = prediction.uncertainty() {
+                    for (&i, value) in indices.iter().zip(values) {
+                        outputs.uncertainty[i] = Some(value);
+                    }
+                }
+                if let Some(values) = prediction.probabilities() {
+                    for (&i, value) in indices.iter().zip(values) {
+                        outputs.probabilities[i] = Some(value);
+                    }
+                }
+                if let Some(values) = prediction.distributions() {
+                    for (&i, value) in indices.iter().zip(values) {
+                        outputs.distributions[i] = Some(value);
+                    }
+                }
+                Ok(())
+            }
+            Self::Internal { split, left, right, .. } => {
+                let (left_indices, right_indices): (Vec<usize>, Vec<usize>) =
+                    indices.into_iter().partition(|&i| split.turn_left(&inputs[i]));
+                left.transform_into(inputs, left_indices, outputs)?;
+                right.transform_into(inputs, right_indices, outputs)
+            }
+        }
+    }
+}
+
+/// The number of rows routed down a tree at once.
+pub const TRANSFORM_BATCH_SIZE: usize = 1024;
+
+/// Append the outputs of a batch, which are kept only when every batch reports them.
+fn concat<V>(outputs: &mut Option<Vec<V>>, batch: Option<Vec<V>>) {
+    *outputs = outputs.take().zip(batch).map(|(mut values, batch)| {
+        values.extend(batch);
+        values
+    });
+}
+
+/// Per-row outputs of the leaves, filled in as the rows are routed down the tree.
+struct LeafOutputs<T> {
+    expected: Vec<Option<T>>,
+    uncertainty: Vec<Option<T>>,
+    probabilities: Vec<Option<Vec<f64>>>,
+    distributions: Vec<Option<EmpiricalDistribution>>,
+}
+
+/// The combined leaf outputs for a batch of rows.
+pub(crate) struct NodePrediction<T> {
+    pub result: Vec<T>,
+    pub uncertainty: Option<Vec<T>>,
+    pub probabilities: Option<Vec<Vec<f64>>>,
+    pub distributions: Option<Vec<EmpiricalDistribution>>,
 }
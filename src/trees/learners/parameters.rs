@@ -5,6 +5,9 @@ pub struct DecisionTreeParameters {
     pub num_features: usize,
     pub min_leaf_instances: usize,
     pub min_impurity_decrease: f64,
+    pub ccp_alpha: f64,
+    pub max_leaves: usize,
+    pub min_weight_fraction_leaf: f64,
 }
 
 impl DecisionTreeParameters {
@@ -31,6 +34,24 @@ impl DecisionTreeParameters {
         self.min_impurity_decrease = min_impurity_decrease;
         self
     }
+
+    /// The complexity parameter of minimal cost-complexity pruning, where zero disables pruning.
+    pub fn with_ccp_alpha(mut self, ccp_alpha: f64) -> Self {
+        self.ccp_alpha = ccp_alpha.max(0.0);
+        self
+    }
+
+    /// The maximum number of leaves, grown best-first by impurity decrease when limited.
+    pub fn with_max_leaves(mut self, max_leaves: usize) -> Self {
+        self.max_leaves = max_leaves.max(1);
+        self
+    }
+
+    /// The minimum fraction of the total training weight required to be at a leaf node.
+    pub fn with_min_weight_fraction_leaf(mut self, min_weight_fraction_leaf: f64) -> Self {
+        self.min_weight_fraction_leaf = min_weight_fraction_leaf.clamp(0.0, 0.5);
+        self
+    }
 }
 
 impl Default for DecisionTreeParameters {
@@ -40,6 +61,9 @@ impl Default for DecisionTreeParameters {
             num_features: usize::MAX,
             min_leaf_instances: 2,
             min_impurity_decrease: 0.0,
+            ccp_alpha: 0.0,
+            max_leaves: usize::MAX,
+            min_weight_fraction_leaf: 0.0,
         }
     }
 }
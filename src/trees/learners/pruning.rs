@@ -0,0 +1,220 @@
+use crate::core::TrainingRow;
+use crate::trees::impurity::NodeImpurity;
+
+use super::TrainingNode;
+
+/// A subtree on the minimal cost-complexity pruning path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PruningStep {
+    /// The smallest complexity parameter for which this subtree is optimal.
+    pub alpha: f64,
+    /// The number of leaves of the subtree.
+    pub num_leaves: usize,
+    /// The total impurity of the leaves of the subtree.
+    pub impurity: f64,
+}
+
+impl<T> TrainingNode<T> {
+    pub fn num_leaves(&self) -> usize {
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Internal { left, right, .. } => left.num_leaves() + right.num_leaves(),
+        }
+    }
+
+    /// The minimal cost-complexity pruning path, from the full tree to the root alone.
+    ///
+    /// Weakest-link pruning repeatedly collapses the subtrees with the smallest increase in
+    /// impurity per removed leaf, which is the `alpha` at which the collapsed tree becomes optimal.
+    /// The impurity increase of a subtree is the sum of the split deltas within it.
+    pub fn pruning_path(&self) -> Vec<PruningStep>
+    where
+        T: NodeImpurity,
+    {
+        let mut pruned: Vec<bool> = vec![false; self.num_nodes()];
+        let mut impurity = self.leaf_impurity();
+        let mut path = vec![PruningStep { alpha: 0.0, num_leaves: self.num_leaves(), impurity }];
+
+        loop {
+            let mut links = vec![];
+            let (_, num_leaves) = self.weakest_links(0, &pruned, &mut links);
+            let Some(alpha) = links.iter().map(|(_, g, _)| *g).min_by(|a, b| a.total_cmp(b)) else {
+                break;
+            };
+
+            // Collapse the weakest links top-down, skipping those inside a collapsed subtree
+            links.sort_by_key(|(id, _, _)| *id);
+            let tolerance = 1e-12 * alpha.abs().max(1.0);
+            let mut removed = 0;
+            for (id, g, (delta, leaves)) in links.iter() {
+                if *g <= alpha + tolerance && !self.is_pruned_below(0, *id, &pruned) {
+                    pruned[*id] = true;
+                    impurity += delta;
+                    removed += leaves - 1;
+                }
+            }
+
+            let previous = path.last().map_or(0.0, |step| step.alpha);
+            path.push(PruningStep {
+                alpha: alpha.max(previous),
+                num_leaves: num_leaves - removed,
+                impurity,
+            });
+        }
+
+        path
+    }
+
+    /// The largest subtree minimizing the impurity plus `alpha` times the number of leaves,
+    /// where pruned subtrees become leaves with all of their training rows.
+    pub fn prune(&self, alpha: f64) -> Self
+    where
+        T: Clone,
+    {
+        self.prune_with_cost(alpha).0
+    }
+
+    /// Prune bottom-up, returning the pruned node and its cost relative to the node as a leaf.
+    fn prune_with_cost(&self, alpha: f64) -> (Self, f64)
+    where
+        T: Clone,
+    {
+        match self {
+            Self::Leaf { .. } => (self.clone(), alpha),
+            Self::Internal { split, left, right, delta, depth } => {
+                let (left, left_cost) = left.prune_with_cost(alpha);
+                let (right, right_cost) = right.prune_with_cost(alpha);
+                let cost = left_cost + right_cost - delta;
+
+                if alpha <= cost {
+                    let mut data = left.into_rows();
+                    data.extend(right.into_rows());
+                    (Self::leaf(data, *depth), alpha)
+                } else {
+                    let node = Self::internal(
+                        split.clone(),
+                        Box::new(left),
+                        Box::new(right),
+                        *delta,
+                        *depth,
+                    );
+                    (node, cost)
+                }
+            }
+        }
+    }
+
+    fn into_rows(self) -> Vec<TrainingRow<T>> {
+        match self {
+            Self::Leaf { data, .. } => data,
+            Self::Internal { left, right, .. } => {
+                let mut rows = left.into_rows();
+                rows.extend(right.into_rows());
+                rows
+            }
+        }
+    }
+
+    fn num_nodes(&self) -> usize {
+        match self {
+            Self::Leaf { .. } => 1,
+            Self::Internal { left, right, .. } => 1 + left.num_nodes() + right.num_nodes(),
+        }
+    }
+
+    fn leaf_impurity(&self) -> f64
+    where
+        T: NodeImpurity,
+    {
+        match self {
+            Self::Leaf { data, .. } => T::node_impurity(data),
+            Self::Internal { left, right, .. } => left.leaf_impurity() + right.leaf_impurity(),
+        }
+    }
+
+    /// Collect `(id, g, (delta, leaves))` for every unpruned internal node, where nodes are
+    /// numbered in pre-order from `id`, `delta` is the impurity increase of collapsing the node,
+    /// `leaves` is its number of leaves, and `g = delta / (leaves - 1)`.
+    /// Returns the summed delta and number of leaves of the subtree.
+    fn weakest_links(
+        &self,
+        id: usize,
+        pruned: &[bool],
+        links: &mut Vec<(usize, f64, (f64, usize))>,
+    ) -> (f64, usize) {
+        match self {
+            Self::Internal { left, right, delta, .. } if !pruned[id] => {
+                let (left_delta, left_leaves) = left.weakest_links(id + 1, pruned, links);
+                let right_id = id + 1 + left.num_nodes();
+                let (right_delta, right_leaves) = right.weakest_links(right_id, pruned, links);
+
+                let (delta, leaves) =
+                    (delta + left_delta + right_delta, left_leaves + right_leaves);
+                links.push((id, delta / (leaves - 1) as f64, (delta, leaves)));
+                (delta, leaves)
+            }
+            _ => (0.0, 1),
+        }
+    }
+
+    /// Whether the node `target` lies strictly below a pruned node, with nodes numbered from `id`.
+    fn is_pruned_below(&self, id: usize, target: usize, pruned: &[bool]) -> bool {
+        match self {
+            Self::Internal { left, right, .. } if id < target => {
+                let right_id = id + 1 + left.num_nodes();
+                if pruned[id] {
+                    true
+                } else if target < right_id {
+                    left.is_pruned_below(id + 1, target, pruned)
+                } else {
+                    right.is_pruned_below(right_id, target, pruned)
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::trees::learners::{DecisionTreeParameters, TreeBuilder};
+    use crate::trees::splits::RegressionSplitter;
+
+    #[test]
+    fn test_pruning_path() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<TrainingRow<f64>> = (0..60)
+            .map(|i| {
+                let x = i as f64;
+                let y = if x < 30.0 { 0.0 } else { 10.0 } + rng.gen::<f64>();
+                TrainingRow::new(vec![x], y, None)
+            })
+            .collect();
+
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(1);
+        let splitter = RegressionSplitter::new(false);
+        let root = TreeBuilder::new(splitter, params).build(&data, &mut rng);
+        let path = root.pruning_path();
+
+        // The path runs from the full tree to the root alone, with increasing alpha and impurity
+        assert_eq!(path[0].num_leaves, root.num_leaves());
+        assert!(path[0].impurity.abs() < 1e-9);
+        let last = path.last().unwrap();
+        assert_eq!(last.num_leaves, 1);
+        assert!((last.impurity - f64::node_impurity(&data)).abs() < 1e-6);
+        for pair in path.windows(2) {
+            assert!(pair[0].alpha <= pair[1].alpha && pair[0].num_leaves > pair[1].num_leaves);
+            assert!(pair[0].impurity <= pair[1].impurity + 1e-9);
+        }
+
+        // Pruning between two alphas on the path yields the earlier subtree
+        for pair in path.windows(2) {
+            let alpha = 0.5 * (pair[0].alpha + pair[1].alpha);
+            assert_eq!(root.prune(alpha).num_leaves(), pair[0].num_leaves);
+        }
+        assert_eq!(root.prune(last.alpha).num_leaves(), 1);
+    }
+}
@@ -1,133 +1,408 @@
-use rand::prelude::StdRng;
-use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
 
-use crate::core::{Learner, Model, TrainingRow};
-use crate::trees::splits::{Split, Splitter};
+use crate::core::{
+    EmpiricalDistribution, FeatureRow, Learner, Model, ModelingError, Prediction,
+    QuantilePrediction, RegressionLabel, Result, TrainingRow,
+};
+use crate::trees::impurity::NodeImpurity;
+use crate::trees::leaf::RegressionLeafLearner;
+use crate::trees::splits::{MonotonicConstraints, RegressionSplitter, Splitter};
 
-use super::{DecisionTreeParameters, LeafLearner, ModelNode, TrainingNode};
+use super::{
+    CompileTree, CompiledTree, DecisionTreeParameters, ModelNode, TrainingNode, TreeBuilder,
+};
 
-#[derive(Debug)]
-pub struct RegressionTreeLearner {
-    splitter: Box<dyn Splitter<f64>>,
+/// A regression tree learner, supporting both single (`f64`) and multiple (`Vec<f64>`) outputs.
+///
+/// With multiple outputs, a single tree is grown on the impurity summed across the outputs.
+#[derive(Clone, Debug)]
+pub struct RegressionTreeLearner<S = RegressionSplitter> {
+    splitter: S,
     learner: RegressionLeafLearner,
     params: DecisionTreeParameters,
-    rng: StdRng,
+    constraints: MonotonicConstraints,
 }
 
-impl RegressionTreeLearner {
+impl<S> RegressionTreeLearner<S> {
     pub fn new(
-        splitter: Box<dyn Splitter<f64>>,
-        learner: LeafLearner,
+        splitter: S,
+        learner: RegressionLeafLearner,
         params: DecisionTreeParameters,
-        seed_rng: Option<&mut StdRng>,
     ) -> Self {
-        let rng = match seed_rng {
-            Some(r) => SeedableRng::from_rng(r).expect("Seeding RNG failed."),
-            None => SeedableRng::from_entropy(),
-        };
-        Self { splitter, learner, params, rng }
-    }
-
-    fn split_internal(
-        &mut self,
-        data: Vec<TrainingRow<f64>>,
-        split: Split,
-        delta_impurity: f64,
-        num_features: usize,
-        remaining_depth: usize,
-    ) -> TrainingNode<f64> {
-        let (left_data, right_data): (Vec<_>, Vec<_>) =
-            data.into_iter().partition(|row| split.turn_left(&row.features));
-
-        let left_child = self.build_child(left_data, num_features, remaining_depth);
-        let right_child = self.build_child(right_data, num_features, remaining_depth);
-
-        TrainingNode::internal(
-            split,
-            Box::new(left_child),
-            Box::new(right_child),
-            delta_impurity,
-            self.params.max_depth - remaining_depth,
-        )
+        Self { splitter, learner, params, constraints: MonotonicConstraints::default() }
+    }
+
+    /// Keep the predictions monotonic in some real features, which requires constant leaves.
+    pub fn with_constraints(mut self, constraints: MonotonicConstraints) -> Self {
+        self.constraints = constraints;
+        self
     }
 
-    fn build_child(
-        &mut self,
-        data: Vec<TrainingRow<f64>>,
-        num_features: usize,
-        remaining_depth: usize,
-    ) -> TrainingNode<f64> {
-        let splitter = &mut self.splitter;
-        let current_depth = self.params.max_depth - remaining_depth;
-        let min_instances = self.params.min_leaf_instances;
-
-        if data.len() >= 2 * min_instances && remaining_depth > 0 {
-            let (split, delta) = splitter.find_best_split(&data, num_features, min_instances);
-            if split != Split::None && delta > self.params.min_purity_increase {
-                self.split_internal(data, split, delta, num_features, remaining_depth - 1)
-            } else {
-                TrainingNode::leaf(data, current_depth)
+    /// Fit a model to the data, returning the concrete model type.
+    pub fn fit_model<T>(
+        &self,
+        data: &[TrainingRow<T>],
+        rng: &mut impl Rng,
+    ) -> Result<RegressionTreeModel<T>>
+    where
+        T: RegressionLabel + 'static,
+        S: Splitter<T> + Clone,
+        RegressionLeafLearner: Learner<T>,
+    {
+        if data.is_empty() {
+            return Err(ModelingError::FitError(
+                "Cannot fit a model without training data.".into(),
+            ));
+        }
+
+        let mut builder = TreeBuilder::new(self.constrained_splitter()?, self.params);
+        let training_node = builder.build(data, rng);
+        let model_node = self.build_model_node(&training_node, rng)?;
+
+        Ok(RegressionTreeModel { training_node, model_node })
+    }
+
+    /// Select the cost-complexity parameter by `num_folds`-fold cross-validation.
+    ///
+    /// The candidates are the geometric means of consecutive alphas on the pruning path of the
+    /// tree grown on all of the data. The candidate with the lowest held-out weighted squared error
+    /// is returned, preferring the larger alpha on ties.
+    pub fn cross_validate_alpha<T>(
+        &self,
+        data: &[TrainingRow<T>],
+        num_folds: usize,
+        rng: &mut impl Rng,
+    ) -> Result<f64>
+    where
+        T: RegressionLabel + NodeImpurity + 'static,
+        S: Splitter<T> + Clone,
+        RegressionLeafLearner: Learner<T>,
+    {
+        if num_folds < 2 || data.len() < num_folds {
+            return Err(ModelingError::FitError(
+                "Cross-validation needs at least two folds and one row per fold.".into(),
+            ));
+        }
+
+        let params = self.params.with_ccp_alpha(0.0);
+        let splitter = self.constrained_splitter()?;
+        let path = TreeBuilder::new(splitter.clone(), params).build(data, rng).pruning_path();
+        let mut candidates: Vec<f64> =
+            path.windows(2).map(|pair| (pair[0].alpha * pair[1].alpha).sqrt()).collect();
+        candidates.push(path.last().map_or(0.0, |step| step.alpha));
+
+        let mut order: Vec<usize> = (0..data.len()).collect();
+        order.shuffle(rng);
+
+        let mut errors = vec![0.0; candidates.len()];
+        for fold in 0..num_folds {
+            let (held_out, training): (Vec<_>, Vec<_>) =
+                order.iter().enumerate().partition(|(i, _)| i % num_folds == fold);
+            let training: Vec<TrainingRow<T>> =
+                training.into_iter().map(|(_, &j)| data[j].clone()).collect();
+            let held_out: Vec<&TrainingRow<T>> =
+                held_out.into_iter().map(|(_, &j)| &data[j]).collect();
+            let inputs: Vec<FeatureRow> = held_out.iter().map(|row| row.features.clone()).collect();
+
+            let full = TreeBuilder::new(splitter.clone(), params).build(&training, rng);
+            for (candidate, error) in candidates.iter().zip(errors.iter_mut()) {
+                let model_node = self.build_model_node(&full.prune(*candidate), rng)?;
+                let predicted = model_node.transform(&inputs)?.result;
+                *error += predicted
+                    .iter()
+                    .zip(held_out.iter())
+                    .map(|(p, row)| {
+                        let se: f64 = p
+                            .outputs()
+                            .iter()
+                            .zip(row.label.outputs())
+                            .map(|(p, y)| (p - y).powi(2))
+                            .sum();
+                        row.weight.unwrap_or(1.0) * se
+                    })
+                    .sum::<f64>();
             }
-        } else {
-            TrainingNode::leaf(data, current_depth)
         }
+
+        let (best, _) = candidates.iter().zip(errors.iter()).fold(
+            (0.0, f64::INFINITY),
+            |(best, best_error), (&alpha, &error)| {
+                if error <= best_error {
+                    (alpha, error)
+                } else {
+                    (best, best_error)
+                }
+            },
+        );
+        Ok(best)
+    }
+
+    /// Fit a model pruned with the cost-complexity parameter selected by cross-validation.
+    pub fn fit_cross_validated<T>(
+        &self,
+        data: &[TrainingRow<T>],
+        num_folds: usize,
+        rng: &mut impl Rng,
+    ) -> Result<RegressionTreeModel<T>>
+    where
+        T: RegressionLabel + NodeImpurity + 'static,
+        S: Splitter<T> + Clone,
+        RegressionLeafLearner: Learner<T>,
+    {
+        let alpha = self.cross_validate_alpha(data, num_folds, rng)?;
+        let mut learner = self.clone();
+        learner.params = self.params.with_ccp_alpha(alpha);
+        learner.fit_model(data, rng)
+    }
+
+    /// A copy of the splitter enforcing the monotonic constraints, if any.
+    fn constrained_splitter<T>(&self) -> Result<S>
+    where
+        S: Splitter<T> + Clone,
+    {
+        let mut splitter = self.splitter.clone();
+        if !self.constraints.is_empty() {
+            if let RegressionLeafLearner::LinearRegression { .. } = self.learner {
+                return Err(ModelingError::FitError(
+                    "Monotonic constraints are not supported with linear leaves.".into(),
+                ));
+            }
+            splitter.set_constraints(&self.constraints)?;
+        }
+        Ok(splitter)
     }
-}
 
-impl Learner<f64> for RegressionTreeLearner {
-    fn fit(&mut self, data: &[TrainingRow<f64>]) -> Box<dyn Model<f64>> {
-        let params = self.params;
-        let my_data: Vec<TrainingRow<f64>> = data.to_vec();
-
-        let root_node = if params.max_depth == 0 {
-            TrainingNode::leaf(my_data, 0)
-        } else {
-            let data_nf = data[0].features.num_features();
-            let actual_nf = data_nf.min(params.num_features);
-            let min_count = params.min_leaf_instances;
-            let max_depth = params.max_depth;
-
-            let (split, delta) = self.splitter.find_best_split(&my_data, actual_nf, min_count);
-
-            match split {
-                Split::None => TrainingNode::leaf(my_data, 0),
-                _split if delta < params.min_purity_increase => TrainingNode::leaf(my_data, 0),
-                split => self.split_internal(my_data, split, delta, actual_nf, max_depth - 1),
+    fn build_model_node<T>(
+        &self,
+        training_node: &TrainingNode<T>,
+        rng: &mut impl Rng,
+    ) -> Result<ModelNode<T>>
+    where
+        T: RegressionLabel + 'static,
+        RegressionLeafLearner: Learner<T>,
+    {
+        match &self.learner {
+            RegressionLeafLearner::LinearRegression { learner, params } => {
+                training_node.build_linear_model(learner, *params)
             }
-        };
+            _ if !self.constraints.is_empty() => {
+                training_node.build_monotonic_model(&self.learner, &self.constraints, rng)
+            }
+            _ => training_node.build_model(&self.learner, rng),
+        }
+    }
+}
+
+impl<S: Splitter<f64> + Clone> Learner<f64> for RegressionTreeLearner<S> {
+    fn fit(&self, data: &[TrainingRow<f64>], rng: &mut impl Rng) -> Result<Box<dyn Model<f64>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
+    }
+}
 
-        todo!()
+impl<S: Splitter<Vec<f64>> + Clone> Learner<Vec<f64>> for RegressionTreeLearner<S> {
+    fn fit(
+        &self,
+        data: &[TrainingRow<Vec<f64>>],
+        rng: &mut impl Rng,
+    ) -> Result<Box<dyn Model<Vec<f64>>>> {
+        Ok(Box::new(self.fit_model(data, rng)?))
     }
 }
 
-pub struct RegressionTreeModel {
-    training_node: TrainingNode<f64>,
-    model_node: ModelNode<f64>,
+/// A model produced by a regression tree learner.
+pub struct RegressionTreeModel<T> {
+    training_node: TrainingNode<T>,
+    model_node: ModelNode<T>,
+}
+
+impl<T> RegressionTreeModel<T> {
+    /// The root of the tree grown on the training data.
+    pub fn training_root(&self) -> &TrainingNode<T> {
+        &self.training_node
+    }
+
+    /// The root of the tree of fitted leaf models.
+    pub fn root(&self) -> &ModelNode<T> {
+        &self.model_node
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Model<T> for RegressionTreeModel<T> {
+    fn transform(&self, inputs: &[FeatureRow]) -> Result<Box<dyn Prediction<T>>> {
+        let prediction = self.model_node.transform(inputs)?;
+        Ok(Box::new(RegressionTreePrediction {
+            result: prediction.result,
+            uncertainty: prediction.uncertainty,
+            distributions: prediction.distributions,
+        }))
+    }
+}
+
+impl<T> CompileTree for RegressionTreeModel<T> {
+    fn compile(&self) -> Result<CompiledTree> {
+        CompiledTree::new(&self.model_node)
+    }
+}
+
+impl RegressionTreeModel<f64> {
+    /// Predict the distribution of the label in the leaf reached by each row.
+    ///
+    /// This requires leaves that keep their training labels, such as
+    /// [`RegressionLeafLearner::empirical`].
+    pub fn predict_quantiles(&self, inputs: &[FeatureRow]) -> Result<QuantilePrediction> {
+        let distributions = self.model_node.transform(inputs)?.distributions.ok_or_else(|| {
+            ModelingError::PredictError("The leaf models do not keep their training labels.".into())
+        })?;
+        Ok(QuantilePrediction::new(distributions))
+    }
+}
+
+/// A prediction result for a regression tree, with the uncertainty and distributions reported
+/// by the leaf models.
+#[derive(Clone, Debug)]
+pub struct RegressionTreePrediction<T> {
+    result: Vec<T>,
+    uncertainty: Option<Vec<T>>,
+    distributions: Option<Vec<EmpiricalDistribution>>,
+}
+
+impl<T: Clone> Prediction<T> for RegressionTreePrediction<T> {
+    fn expected(&self) -> Vec<T> {
+        self.result.clone()
+    }
+
+    fn uncertainty(&self) -> Option<Vec<T>> {
+        self.uncertainty.clone()
+    }
+
+    fn distributions(&self) -> Option<Vec<EmpiricalDistribution>> {
+        self.distributions.clone()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
     use super::*;
+    use crate::linear::LinearRegressionLearner;
+    use crate::trees::leaf::LinearLeafParameters;
     use crate::utils::linear_training_data;
-    use crate::{trees::leaf::GuessTheMeanLearner, trees::splits::RegressionSplitter};
 
     #[test]
     fn test_regression_tree() {
-        let data = linear_training_data(100, &[0.0, 1.0, 2.0, 3.0], 5.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let data = linear_training_data(100, &[0.0, 1.0, 2.0, 3.0], 5.0, &mut rng);
+        let features: Vec<FeatureRow> = data.iter().map(|row| row.features.clone()).collect();
 
-        let params: DecisionTreeParameters = Default::default();
-        let splitter = RegressionSplitter::new(true, None);
-        let leaf_learner = LeafLearner::mean(GuessTheMeanLearner::new(None));
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(1);
+        let splitter = RegressionSplitter::new(true);
+        let tree = RegressionTreeLearner::new(splitter, RegressionLeafLearner::default(), params);
+
+        // A fully grown tree reproduces the training labels
+        let model = tree.fit(&data, &mut rng).unwrap();
+        let predicted = model.transform(&features).unwrap().expected();
+        predicted
+            .iter()
+            .zip(data.iter())
+            .for_each(|(p, row)| assert!((p - row.label).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_multiple_outputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<TrainingRow<Vec<f64>>> = (0..40)
+            .map(|i| {
+                let x = i as f64;
+                let step = if x < 20.0 { 0.0 } else { 1.0 };
+                TrainingRow::new(vec![x], vec![step, 10.0 - 5.0 * step], None)
+            })
+            .collect();
+
+        let params = DecisionTreeParameters::default().with_max_depth(1);
+        let splitter = RegressionSplitter::new(false);
+        let tree = RegressionTreeLearner::new(splitter, RegressionLeafLearner::default(), params);
+        let model = tree.fit_model(&data, &mut rng).unwrap();
+
+        let inputs: Vec<FeatureRow> = vec![vec![3.0].into(), vec![30.0].into()];
+        let prediction = model.transform(&inputs).unwrap();
+        assert_eq!(prediction.expected(), vec![vec![0.0, 10.0], vec![1.0, 5.0]]);
+        assert_eq!(prediction.uncertainty().unwrap(), vec![vec![0.0, 0.0]; 2]);
+    }
+
+    #[test]
+    fn test_linear_leaves() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let tent = |x: f64| if x < 25.0 { x } else { 50.0 - x };
+        let data: Vec<TrainingRow<f64>> =
+            (0..50).map(|i| TrainingRow::new(vec![i as f64], tent(i as f64), None)).collect();
+
+        // Pruning collapses the fully grown tree to a few linear models around the peak
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(2);
+        let leaf_params = LinearLeafParameters::default().with_smoothing(0.0);
+        let leaf = RegressionLeafLearner::linreg_with_params(
+            LinearRegressionLearner::new(true, None),
+            leaf_params,
+        );
+        let tree = RegressionTreeLearner::new(RegressionSplitter::new(false), leaf, params);
+        let model = tree.fit_model(&data, &mut rng).unwrap();
+
+        fn num_leaves<T>(node: &ModelNode<T>) -> usize {
+            match node {
+                ModelNode::Leaf { .. } => 1,
+                ModelNode::Internal { left, right, .. } => num_leaves(left) + num_leaves(right),
+            }
+        }
+        let unpruned = RegressionTreeLearner::new(
+            RegressionSplitter::new(false),
+            RegressionLeafLearner::linreg_with_params(
+                LinearRegressionLearner::new(true, None),
+                leaf_params.with_pruning(false),
+            ),
+            params,
+        )
+        .fit_model(&data, &mut rng)
+        .unwrap();
+        assert!(2 * num_leaves(model.root()) < num_leaves(unpruned.root()));
+
+        // Linear leaves extrapolate beyond the training range
+        let inputs: Vec<FeatureRow> = vec![vec![-10.0].into(), vec![60.0].into()];
+        let predicted = model.transform(&inputs).unwrap().expected();
+        assert!((predicted[0] + 10.0).abs() < 1e-6 && (predicted[1] + 10.0).abs() < 1e-6);
+
+        // With smoothing, the leaves are pulled towards the model of the root
+        let leaf = RegressionLeafLearner::linreg(LinearRegressionLearner::new(true, None));
+        let tree = RegressionTreeLearner::new(RegressionSplitter::new(false), leaf, params);
+        let smoothed = tree.fit_model(&data, &mut rng).unwrap();
+        let predicted = smoothed.transform(&inputs).unwrap().expected();
+        assert!(predicted[0] > -10.0 && predicted[1] > -10.0);
+    }
+
+    #[test]
+    fn test_cross_validated_pruning() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<TrainingRow<f64>> = (0..80)
+            .map(|i| {
+                let x = i as f64;
+                let y = if x < 40.0 { 0.0 } else { 5.0 } + rng.gen::<f64>();
+                TrainingRow::new(vec![x], y, None)
+            })
+            .collect();
 
-        let mut tree = RegressionTreeLearner::new(Box::new(splitter), leaf_learner, params, None);
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(1);
+        let splitter = RegressionSplitter::new(false);
+        let tree = RegressionTreeLearner::new(splitter, RegressionLeafLearner::default(), params);
+        let full = tree.fit_model(&data, &mut rng).unwrap();
+        let pruned = tree.fit_cross_validated(&data, 5, &mut rng).unwrap();
 
-        let tic = std::time::SystemTime::now();
-        (0..10).for_each(|_| {
-            let _ = tree.fit(&data);
-        });
-        let toc = std::time::SystemTime::now();
-        let since = toc.duration_since(tic).expect("");
+        // Cross-validation removes most of the splits fit to the noise, but keeps the step
+        let (full_leaves, pruned_leaves) =
+            (full.training_root().num_leaves(), pruned.training_root().num_leaves());
+        assert!(pruned_leaves > 1 && 4 * pruned_leaves < full_leaves);
 
-        println!("{}", since.as_secs_f64() / 10.0);
+        let inputs: Vec<FeatureRow> = vec![vec![10.0].into(), vec![70.0].into()];
+        let predicted = pruned.transform(&inputs).unwrap().expected();
+        assert!((predicted[0] - 0.5).abs() < 0.5 && (predicted[1] - 5.5).abs() < 0.5);
     }
 }
@@ -1,4 +1,4 @@
 pub mod impurity;
 pub mod leaf;
-//pub mod learners;
+pub mod learners;
 pub mod splits;
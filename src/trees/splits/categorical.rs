@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use super::CategorySet;
+use crate::core::TrainingRow;
+
+/// Features with at most this many categories at a node are split by searching every subset.
+pub(crate) const MAX_EXHAUSTIVE_CATEGORIES: usize = 8;
+
+/// The rows of a node sharing a category, with their labels aggregated into `mass`.
+#[derive(Clone, Debug)]
+pub(crate) struct CategoryGroup<M> {
+    pub category: usize,
+    pub mass: M,
+    pub weight: f64,
+    pub count: usize,
+}
+
+/// Aggregate the rows at `indices` by their category of feature `idx`, in order of category.
+/// Rows missing the category are grouped together under `usize::MAX`.
+pub(crate) fn group_categories<'a, T, M: Default>(
+    data: &'a [TrainingRow<T>],
+    indices: &[usize],
+    idx: usize,
+    accumulate: impl Fn(&mut M, &'a T, f64),
+) -> Vec<CategoryGroup<M>> {
+    let mut groups: BTreeMap<usize, CategoryGroup<M>> = BTreeMap::new();
+    for row in indices.iter().map(|&i| &data[i]) {
+        let category = row.features[idx].as_categorical().unwrap_or(usize::MAX);
+        let weight = row.weight.unwrap_or(1.0);
+        let group = groups.entry(category).or_insert_with(|| CategoryGroup {
+            category,
+            mass: M::default(),
+            weight: 0.0,
+            count: 0,
+        });
+        accumulate(&mut group.mass, &row.label, weight);
+        group.weight += weight;
+        group.count += 1;
+    }
+    groups.into_values().collect()
+}
+
+/// Find the set of categories to send left with the lowest impurity.
+///
+/// `step` resets the calculator on `None` and otherwise moves the mass of a group to the left,
+/// returning the impurity of the split. Every subset is tried for up to
+/// [`MAX_EXHAUSTIVE_CATEGORIES`] groups, and beyond that the groups are swept in increasing
+/// `order`. Both sides must hold at least `min_count` rows and `min_weight` of the row weight.
+pub(crate) fn best_category_subset<M>(
+    mut groups: Vec<CategoryGroup<M>>,
+    order: impl Fn(&CategoryGroup<M>) -> f64,
+    min_count: usize,
+    min_weight: f64,
+    mut step: impl FnMut(Option<&M>) -> f64,
+) -> (CategorySet, f64) {
+    let mut best_impurity = f64::INFINITY;
+    let mut best_set = CategorySet::new();
+
+    // If too many values are trivial, return an empty split
+    let total_weight: f64 = groups.iter().map(|g| g.weight).sum();
+    let non_trivial: f64 = groups.iter().filter(|g| g.count > 1).map(|g| g.weight).sum();
+    if groups.len() < 2 || non_trivial / total_weight < 0.5 {
+        return (best_set, best_impurity);
+    }
+
+    let total_count: usize = groups.iter().map(|g| g.count).sum();
+    let is_valid = |left: usize, left_weight: f64| {
+        left >= min_count
+            && total_count - left >= min_count
+            && left_weight >= min_weight
+            && total_weight - left_weight >= min_weight
+    };
+
+    if groups.len() <= MAX_EXHAUSTIVE_CATEGORIES {
+        // The last group stays right, so each split is visited once
+        let last = groups.len() - 1;
+        for mask in 1usize..(1 << last) {
+            let left: Vec<&CategoryGroup<M>> =
+                (0..last).filter(|j| mask & (1 << j) != 0).map(|j| &groups[j]).collect();
+            let left_weight = left.iter().map(|g| g.weight).sum();
+            if !is_valid(left.iter().map(|g| g.count).sum(), left_weight) {
+                continue;
+            }
+
+            step(None);
+            let impurity = left.iter().fold(f64::INFINITY, |_, g| step(Some(&g.mass)));
+            if impurity < best_impurity {
+                best_impurity = impurity;
+                best_set = left.iter().map(|g| g.category).collect();
+            }
+        }
+    } else {
+        // Add categories one at a time in increasing order
+        groups.sort_by(|g1, g2| order(g1).total_cmp(&order(g2)));
+        let (mut left_count, mut left_weight) = (0, 0.0);
+        step(None);
+        for j in 0..(groups.len() - 1) {
+            left_count += groups[j].count;
+            left_weight += groups[j].weight;
+            let impurity = step(Some(&groups[j].mass));
+            if impurity < best_impurity && is_valid(left_count, left_weight) {
+                best_impurity = impurity;
+                best_set = groups[..(j + 1)].iter().map(|g| g.category).collect();
+            }
+        }
+    }
+
+    (best_set, best_impurity)
+}
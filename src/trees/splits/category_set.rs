@@ -0,0 +1,80 @@
+/// A set of category codes for categorical splits.
+///
+/// Codes below [`CategorySet::MAX_DENSE_CODE`] are stored as a bitset, so membership is a shift
+/// and a mask, and larger codes fall back to a sorted list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CategorySet {
+    bits: Vec<u64>,
+    sparse: Vec<usize>,
+}
+
+impl CategorySet {
+    /// Codes from this value on are stored in the sorted list.
+    pub const MAX_DENSE_CODE: usize = 1024;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, category: usize) {
+        if category < Self::MAX_DENSE_CODE {
+            let (word, bit) = (category / 64, category % 64);
+            if self.bits.len() <= word {
+                self.bits.resize(word + 1, 0);
+            }
+            self.bits[word] |= 1 << bit;
+        } else if let Err(position) = self.sparse.binary_search(&category) {
+            self.sparse.insert(position, category);
+        }
+    }
+
+    pub fn contains(&self, category: usize) -> bool {
+        if category < Self::MAX_DENSE_CODE {
+            self.bits.get(category / 64).is_some_and(|word| word & (1 << (category % 64)) != 0)
+        } else {
+            self.sparse.binary_search(&category).is_ok()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum::<usize>() + self.sparse.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The categories in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        let dense = self.bits.iter().enumerate().flat_map(|(word, bits)| {
+            (0..64).filter(move |bit| bits & (1 << bit) != 0).map(move |bit| 64 * word + bit)
+        });
+        dense.chain(self.sparse.iter().copied())
+    }
+}
+
+impl FromIterator<usize> for CategorySet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = Self::new();
+        iter.into_iter().for_each(|category| set.insert(category));
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dense_and_sparse_codes() {
+        let large = CategorySet::MAX_DENSE_CODE + 5;
+        let set: CategorySet = [3, 64, large, 3, 1].into_iter().collect();
+        assert_eq!(set.len(), 4);
+        assert!(set.contains(1) && set.contains(64) && set.contains(large));
+        assert!(!set.contains(0) && !set.contains(2) && !set.contains(large + 1));
+        assert!(!set.contains(10_000));
+        assert_eq!(set.iter().collect::<Vec<usize>>(), vec![1, 3, 64, large]);
+        assert_eq!(set, [large, 64, 1, 3].into_iter().collect());
+    }
+}
@@ -0,0 +1,39 @@
+use crate::core::Monotonicity;
+
+/// Monotonic constraints on the predictions of a regression tree, by real feature index.
+///
+/// Splits on a constrained feature are rejected when the mean label moves against the constraint,
+/// and the leaves below them are bounded so that every output of the tree is monotonic.
+///
+/// Any sum or positively weighted average of trees sharing the same constraints stays monotonic,
+/// so bagged ensembles such as [`BaggedModel`](crate::ensemble::BaggedModel) keep them too. This
+/// would equally hold for boosted ensembles, which the crate does not provide.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MonotonicConstraints {
+    directions: Vec<Monotonicity>,
+}
+
+impl MonotonicConstraints {
+    pub fn new(directions: Vec<Monotonicity>) -> Self {
+        Self { directions }
+    }
+
+    /// Constrain a single feature.
+    pub fn with_feature(mut self, idx: usize, direction: Monotonicity) -> Self {
+        if self.directions.len() <= idx {
+            self.directions.resize(idx + 1, Monotonicity::None);
+        }
+        self.directions[idx] = direction;
+        self
+    }
+
+    /// The constraint on a feature.
+    pub fn direction(&self, idx: usize) -> Monotonicity {
+        self.directions.get(idx).copied().unwrap_or_default()
+    }
+
+    /// Whether no feature is constrained.
+    pub fn is_empty(&self) -> bool {
+        self.directions.iter().all(|d| *d == Monotonicity::None)
+    }
+}
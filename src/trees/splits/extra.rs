@@ -0,0 +1,239 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::{MonotonicConstraints, RegressionSplitter, Split, Splitter};
+use crate::core::{RegressionLabel, Result, TrainingRow};
+use crate::trees::impurity::{GiniCalculator, ImpurityCalculator, VarianceCalculator};
+
+/// A splitter for extremely randomized trees, for both regression and classification.
+///
+/// Each candidate feature gets a single random split, a uniform threshold between the smallest
+/// and largest values at the node or a random subset of the categories present, and the candidate
+/// with the lowest impurity is kept.
+#[derive(Clone, Debug, Default)]
+pub struct ExtraTreesSplitter {
+    constraints: MonotonicConstraints,
+}
+
+impl ExtraTreesSplitter {
+    pub fn new() -> Self {
+        Self { constraints: Default::default() }
+    }
+
+    /// Draw a random split on a feature, or none when the feature is constant at the node.
+    fn random_split<T>(
+        data: &[TrainingRow<T>],
+        indices: &[usize],
+        idx: usize,
+        rng: &mut impl Rng,
+    ) -> Option<Split> {
+        if data[indices[0]].features[idx].is_real() {
+            let (min, max) = indices
+                .iter()
+                .filter_map(|&i| data[i].features[idx].as_real())
+                .filter(|x| !x.is_nan())
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+            if min >= max {
+                return None;
+            }
+            Some(Split::Real(idx, min + (max - min) * rng.gen::<f64>()))
+        } else {
+            let mut categories: Vec<usize> =
+                indices.iter().filter_map(|&i| data[i].features[idx].as_categorical()).collect();
+            categories.sort_unstable();
+            categories.dedup();
+            if categories.len() < 2 {
+                return None;
+            }
+            categories.shuffle(rng);
+            let size = rng.gen_range(1..categories.len());
+            Some(Split::Categorical(idx, categories.into_iter().take(size).collect()))
+        }
+    }
+
+    /// Draw a split for each of `num_features` random features and keep the best of those the
+    /// calculator `accepts`. Features constant at the node do not count towards `num_features`,
+    /// so further features are drawn until enough have a split or none are left.
+    #[allow(clippy::too_many_arguments)]
+    fn find_random_split<'a, T, V, C: ImpurityCalculator<V>>(
+        data: &'a [TrainingRow<T>],
+        indices: &[usize],
+        calc: &mut C,
+        accepts: impl Fn(&C, &Split) -> bool,
+        label: impl Fn(&'a T) -> V,
+        num_features: usize,
+        min_count: usize,
+        min_weight: f64,
+        rng: &mut impl Rng,
+    ) -> (Split, f64) {
+        let init_impurity = calc.impurity();
+        let total_weight: f64 = indices.iter().map(|&i| data[i].weight.unwrap_or(1.0)).sum();
+
+        let mut best_split = Split::None;
+        let mut best_impurity = f64::INFINITY;
+
+        let nf = data[indices[0]].features.data.len();
+        let mut features: Vec<usize> = (0..nf).collect();
+        features.shuffle(rng);
+
+        let min_count = min_count.max(1);
+        let mut num_drawn = 0;
+        for idx in features {
+            if num_drawn == num_features {
+                break;
+            }
+            let Some(split) = Self::random_split(data, indices, idx, rng) else {
+                continue;
+            };
+            num_drawn += 1;
+
+            calc.reset();
+            let (mut left_count, mut left_weight) = (0, 0.0);
+            for row in indices.iter().map(|&i| &data[i]) {
+                if split.turn_left(&row.features) {
+                    calc.add(label(&row.label), row.weight.unwrap_or(1.0));
+                    left_count += 1;
+                    left_weight += row.weight.unwrap_or(1.0);
+                }
+            }
+            if left_count < min_count || indices.len() - left_count < min_count {
+                continue;
+            }
+            if left_weight < min_weight || total_weight - left_weight < min_weight {
+                continue;
+            }
+
+            let impurity = calc.impurity();
+            if impurity < best_impurity && accepts(calc, &split) {
+                best_impurity = impurity;
+                best_split = split;
+            }
+        }
+
+        if best_impurity.is_infinite() {
+            (Split::None, 0.0)
+        } else {
+            (best_split, init_impurity - best_impurity)
+        }
+    }
+}
+
+impl<L: RegressionLabel> Splitter<L> for ExtraTreesSplitter {
+    type NodeStats = ();
+
+    fn set_constraints(&mut self, constraints: &MonotonicConstraints) -> Result<()> {
+        self.constraints = constraints.clone();
+        Ok(())
+    }
+
+    fn find_best_split_with_stats(
+        &mut self,
+        data: &[TrainingRow<L>],
+        indices: &[usize],
+        _stats: &(),
+        num_features: usize,
+        min_count: usize,
+        min_weight: f64,
+        rng: &mut impl Rng,
+    ) -> (Split, f64) {
+        let mut calc = VarianceCalculator::from_indexed_data(data, indices);
+        let constraints = self.constraints.clone();
+        let accepts = |calc: &VarianceCalculator, split: &Split| match split {
+            Split::Real(idx, _) => {
+                RegressionSplitter::is_allowed(calc, constraints.direction(*idx))
+            }
+            _ => true,
+        };
+        Self::find_random_split(
+            data,
+            indices,
+            &mut calc,
+            accepts,
+            L::outputs,
+            num_features,
+            min_count,
+            min_weight,
+            rng,
+        )
+    }
+}
+
+impl Splitter<usize> for ExtraTreesSplitter {
+    type NodeStats = ();
+
+    fn find_best_split_with_stats(
+        &mut self,
+        data: &[TrainingRow<usize>],
+        indices: &[usize],
+        _stats: &(),
+        num_features: usize,
+        min_count: usize,
+        min_weight: f64,
+        rng: &mut impl Rng,
+    ) -> (Split, f64) {
+        let mut calc = GiniCalculator::from_indexed_data(data, indices);
+        Self::find_random_split(
+            data,
+            indices,
+            &mut calc,
+            |_, _| true,
+            |&label| label,
+            num_features,
+            min_count,
+            min_weight,
+            rng,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn random_splits() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Only the first feature is informative, and the second is constant
+        let data: Vec<TrainingRow<usize>> = (0..100)
+            .map(|_| {
+                let x = rng.gen_range(1.0..3.0);
+                TrainingRow::new(vec![x, 5.0], if x < 2.0 { 1 } else { 2 }, None)
+            })
+            .collect();
+
+        let mut splitter = ExtraTreesSplitter::new();
+        for _ in 0..10 {
+            let (split, delta) = splitter.find_best_split(&data, 2, 1, &mut rng);
+            match split {
+                Split::Real(0, pivot) => assert!((1.0..3.0).contains(&pivot)),
+                _ => panic!("Only the first feature can be split, got {:?}", split),
+            }
+            assert!(delta > 0.0);
+        }
+
+        // A feature that is constant everywhere cannot be split
+        let constant: Vec<TrainingRow<f64>> =
+            (0..10).map(|i| TrainingRow::new(vec![1.0], i as f64, None)).collect();
+        assert_eq!(splitter.find_best_split(&constant, 1, 1, &mut rng).0, Split::None);
+    }
+
+    #[test]
+    fn skip_constant_features() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Only the last of five features varies, yet a single feature per split finds it
+        let data: Vec<TrainingRow<f64>> = (0..20)
+            .map(|i| TrainingRow::new(vec![1.0, 2.0, 3.0, 4.0, i as f64], i as f64, None))
+            .collect();
+
+        let mut splitter = ExtraTreesSplitter::new();
+        for _ in 0..10 {
+            let (split, delta) = splitter.find_best_split(&data, 1, 1, &mut rng);
+            assert!(matches!(split, Split::Real(4, _)), "{:?}", split);
+            assert!(delta > 0.0);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::{MonotonicConstraints, Monotonicity, RegressionSplitter, Split, Splitter};
+use crate::core::{RegressionLabel, Result, TrainingRow};
+use crate::trees::impurity::{ImpurityCalculator, VarianceCalculator};
+use crate::utils::par_map;
+
+/// Label statistics of the rows falling in one bin of a real feature.
+#[derive(Clone, Debug, Default)]
+struct Bin {
+    count: usize,
+    weight: f64,
+    sums: Vec<f64>,
+    sq_sum: f64,
+}
+
+impl Bin {
+    fn add(&mut self, outputs: &[f64], weight: f64) {
+        self.sums.resize(outputs.len(), 0.0);
+        self.count += 1;
+        self.weight += weight;
+        for (s, y) in self.sums.iter_mut().zip(outputs) {
+            *s += weight * y;
+            self.sq_sum += weight * y * y;
+        }
+    }
+
+    fn subtract(&mut self, other: &Bin) {
+        self.sums.resize(other.sums.len().max(self.sums.len()), 0.0);
+        self.count -= other.count;
+        self.weight -= other.weight;
+        self.sq_sum -= other.sq_sum;
+        self.sums.iter_mut().zip(other.sums.iter()).for_each(|(s, o)| *s -= o);
+    }
+}
+
+/// The histograms of the real features over the rows of a node.
+#[derive(Clone, Debug, Default)]
+pub struct NodeHistograms {
+    features: Vec<Option<Vec<Bin>>>,
+}
+
+/// A regression splitter searching real features over histograms of quantile bins.
+///
+/// Bin edges are computed once from the rows at the root, and each node accumulates the label
+/// statistics per bin in a single pass over its rows. The histograms of the larger child of a
+/// split are the parent's minus those of its sibling. Categorical features are searched exactly.
+#[derive(Clone, Debug)]
+pub struct HistogramSplitter {
+    max_bins: usize,
+    edges: Vec<Option<Vec<f64>>>,
+    constraints: MonotonicConstraints,
+}
+
+impl HistogramSplitter {
+    /// A splitter with at most `max_bins` bins per real feature, up to 255.
+    pub fn new(max_bins: usize) -> Self {
+        Self { max_bins: max_bins.clamp(2, 255), edges: vec![], constraints: Default::default() }
+    }
+
+    /// The upper edges of the bins of a real feature, once the splitter has seen the root.
+    pub fn edges(&self, idx: usize) -> Option<&[f64]> {
+        self.edges.get(idx).and_then(|edges| edges.as_deref())
+    }
+
+    /// Place the bin edges at midpoints between distinct values when there are few enough,
+    /// and at quantiles of the values otherwise.
+    fn fit_edges<L>(&mut self, data: &[TrainingRow<L>], indices: &[usize]) {
+        let rep = &data[indices[0]];
+        self.edges = (0..rep.features.data.len())
+            .map(|idx| {
+                if !rep.features[idx].is_real() {
+                    return None;
+                }
+
+                let mut values: Vec<f64> = indices
+                    .iter()
+                    .filter_map(|&i| data[i].features[idx].as_real())
+                    .filter(|x| !x.is_nan())
+                    .collect();
+                values.sort_by(|a, b| a.total_cmp(b));
+                let mut distinct = values.clone();
+                distinct.dedup();
+
+                let mut edges: Vec<f64> = if distinct.len() <= self.max_bins {
+                    distinct.windows(2).map(|pair| 0.5 * (pair[0] + pair[1])).collect()
+                } else {
+                    (1..self.max_bins).map(|k| values[k * values.len() / self.max_bins]).collect()
+                };
+                edges.dedup();
+                Some(edges)
+            })
+            .collect();
+    }
+
+    /// The bin of a value, where missing values fall in the last bin.
+    fn bin(edges: &[f64], x: f64) -> usize {
+        if x.is_nan() {
+            edges.len() + 1
+        } else {
+            edges.partition_point(|e| *e < x)
+        }
+    }
+
+    /// Find the best split on a real feature by sweeping its bins from left to right.
+    /// Missing values always go right.
+    fn best_real_split(
+        edges: &[f64],
+        bins: &[Bin],
+        idx: usize,
+        min_count: usize,
+        min_weight: f64,
+        direction: Monotonicity,
+    ) -> (Split, f64) {
+        let mut total = Bin::default();
+        bins.iter().for_each(|bin| {
+            total.sums.resize(bin.sums.len().max(total.sums.len()), 0.0);
+            total.count += bin.count;
+            total.weight += bin.weight;
+            total.sq_sum += bin.sq_sum;
+            total.sums.iter_mut().zip(bin.sums.iter()).for_each(|(t, s)| *t += s);
+        });
+
+        let mut best_impurity = f64::INFINITY;
+        let mut best_pivot = f64::INFINITY;
+
+        let min_count = min_count.max(1);
+        let (mut left_count, mut left_weight) = (0, 0.0);
+        let mut left_sums = vec![0.0; total.sums.len()];
+        for (b, bin) in bins.iter().take(edges.len()).enumerate() {
+            if bin.count == 0 {
+                continue;
+            }
+            left_count += bin.count;
+            left_weight += bin.weight;
+            left_sums.iter_mut().zip(bin.sums.iter()).for_each(|(l, s)| *l += s);
+
+            let right_weight = total.weight - left_weight;
+            if left_count < min_count || total.count - left_count < min_count {
+                continue;
+            }
+            if left_weight <= 0.0 || right_weight <= 0.0 {
+                continue;
+            }
+            if left_weight < min_weight || right_weight < min_weight {
+                continue;
+            }
+
+            let ls: f64 = left_sums.iter().map(|s| s * s).sum();
+            let rs: f64 =
+                total.sums.iter().zip(left_sums.iter()).map(|(t, l)| (t - l).powi(2)).sum();
+            let impurity = total.sq_sum - ls / left_weight - rs / right_weight;
+            if impurity < best_impurity && direction != Monotonicity::None {
+                let left_means: Vec<f64> = left_sums.iter().map(|l| l / left_weight).collect();
+                let right_means: Vec<f64> = total
+                    .sums
+                    .iter()
+                    .zip(left_sums.iter())
+                    .map(|(t, l)| (t - l) / right_weight)
+                    .collect();
+                if !direction.allows(&left_means, &right_means) {
+                    continue;
+                }
+            }
+            if impurity < best_impurity {
+                best_impurity = impurity;
+                best_pivot = edges[b];
+            }
+        }
+
+        (Split::Real(idx, best_pivot), best_impurity)
+    }
+}
+
+impl<L: RegressionLabel> Splitter<L> for HistogramSplitter {
+    type NodeStats = NodeHistograms;
+
+    fn node_stats(&mut self, data: &[TrainingRow<L>], indices: &[usize]) -> NodeHistograms {
+        if indices.is_empty() {
+            return NodeHistograms::default();
+        }
+        if self.edges.is_empty() {
+            self.fit_edges(data, indices);
+        }
+
+        let mut features: Vec<Option<Vec<Bin>>> = self
+            .edges
+            .iter()
+            .map(|edges| edges.as_ref().map(|edges| vec![Bin::default(); edges.len() + 2]))
+            .collect();
+        for row in indices.iter().map(|&i| &data[i]) {
+            let (outputs, weight) = (row.label.outputs(), row.weight.unwrap_or(1.0));
+            for (idx, (bins, edges)) in features.iter_mut().zip(self.edges.iter()).enumerate() {
+                if let (Some(bins), Some(edges)) = (bins, edges) {
+                    let x = row.features[idx].as_real().unwrap_or(f64::NAN);
+                    bins[Self::bin(edges, x)].add(outputs, weight);
+                }
+            }
+        }
+
+        NodeHistograms { features }
+    }
+
+    fn subtract_stats(&self, parent: NodeHistograms, sibling: &NodeHistograms) -> NodeHistograms {
+        let mut features = parent.features;
+        for (bins, sibling_bins) in features.iter_mut().zip(sibling.features.iter()) {
+            if let (Some(bins), Some(sibling_bins)) = (bins, sibling_bins) {
+                bins.iter_mut().zip(sibling_bins.iter()).for_each(|(b, s)| b.subtract(s));
+            }
+        }
+        NodeHistograms { features }
+    }
+
+    fn set_constraints(&mut self, constraints: &MonotonicConstraints) -> Result<()> {
+        self.constraints = constraints.clone();
+        Ok(())
+    }
+
+    fn find_best_split_with_stats(
+        &mut self,
+        data: &[TrainingRow<L>],
+        indices: &[usize],
+        stats: &NodeHistograms,
+        num_features: usize,
+        min_count: usize,
+        min_weight: f64,
+        rng: &mut impl Rng,
+    ) -> (Split, f64) {
+        let calc = VarianceCalculator::from_indexed_data(data, indices);
+        let init_variance = ImpurityCalculator::<&[f64]>::impurity(&calc);
+
+        let mut best_split = Split::None;
+        let mut best_variance = f64::INFINITY;
+
+        let nf = data[indices[0]].features.data.len();
+        let mut features: Vec<usize> = (0..nf).collect();
+        features.shuffle(rng);
+        features.truncate(num_features);
+
+        let candidates = par_map(&features, |&idx| {
+            let bins = stats.features.get(idx).and_then(|bins| bins.as_ref());
+            let edges = self.edges.get(idx).and_then(|edges| edges.as_ref());
+            match (edges, bins) {
+                (Some(edges), Some(bins)) => {
+                    let direction = self.constraints.direction(idx);
+                    Self::best_real_split(edges, bins, idx, min_count, min_weight, direction)
+                }
+                _ => RegressionSplitter::best_categorical_split(
+                    data,
+                    indices,
+                    &mut calc.clone(),
+                    idx,
+                    min_count,
+                    min_weight,
+                ),
+            }
+        });
+
+        for (trial_split, trial_variance) in candidates {
+            if trial_variance < best_variance {
+                best_variance = trial_variance;
+                best_split = trial_split;
+            }
+        }
+
+        if best_variance.is_infinite() {
+            (Split::None, 0.0)
+        } else {
+            (best_split, init_variance - best_variance)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn split_matches_exact() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // With fewer distinct values than bins, the histogram search is exact
+        let data: Vec<TrainingRow<f64>> = (0..200)
+            .map(|_| {
+                let x: Vec<f64> = (0..3).map(|_| rng.gen_range(0..50) as f64).collect();
+                let y = (x[1] - 20.0).abs() + rng.gen::<f64>();
+                TrainingRow::new(x, y, None)
+            })
+            .collect();
+
+        let mut exact = RegressionSplitter::new(false);
+        let mut histogram = HistogramSplitter::new(255);
+        let (split, delta) = exact.find_best_split(&data, 3, 1, &mut rng);
+        let (hist_split, hist_delta) = histogram.find_best_split(&data, 3, 1, &mut rng);
+        assert_eq!(split, hist_split);
+        assert!((delta - hist_delta).abs() < 1e-6 * delta);
+    }
+
+    #[test]
+    fn subtract_sibling() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let data: Vec<TrainingRow<f64>> = (0..1000)
+            .map(|_| {
+                let x = rng.gen::<f64>();
+                TrainingRow::new(vec![x], x.sin(), None)
+            })
+            .collect();
+
+        let mut splitter = HistogramSplitter::new(32);
+        let indices: Vec<usize> = (0..data.len()).collect();
+        let parent = Splitter::<f64>::node_stats(&mut splitter, &data, &indices);
+        assert_eq!(splitter.edges(0).unwrap().len(), 31);
+
+        let (left, right): (Vec<usize>, Vec<usize>) =
+            indices.into_iter().partition(|&i| data[i].features[0].as_real().unwrap() < 0.3);
+        let left_stats = Splitter::<f64>::node_stats(&mut splitter, &data, &left);
+        let derived = Splitter::<f64>::subtract_stats(&splitter, parent, &left_stats);
+        let direct = Splitter::<f64>::node_stats(&mut splitter, &data, &right);
+
+        // The smaller (left) sibling leaves the same histograms as the right rows on their own
+        let (derived, direct) = (derived.features[0].as_ref(), direct.features[0].as_ref());
+        for (d, r) in derived.unwrap().iter().zip(direct.unwrap().iter()) {
+            assert_eq!(d.count, r.count);
+            assert!((d.weight - r.weight).abs() < 1e-9 && (d.sq_sum - r.sq_sum).abs() < 1e-9);
+        }
+    }
+}
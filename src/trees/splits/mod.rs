@@ -1,7 +1,23 @@
+mod categorical;
+pub mod category_set;
+pub mod classification;
+pub mod constraints;
+pub mod extra;
+pub mod histogram;
+pub mod multitask;
+pub mod oblique;
 pub mod regression;
 pub mod split;
 pub mod splitter;
 
-pub use self::regression::RegressionSplitter;
+pub use self::category_set::CategorySet;
+pub use self::classification::ClassificationSplitter;
+pub use self::constraints::MonotonicConstraints;
+pub use self::extra::ExtraTreesSplitter;
+pub use self::histogram::{HistogramSplitter, NodeHistograms};
+pub use self::multitask::MultiTaskSplitter;
+pub use self::oblique::{ObliqueDirection, ObliqueSplitter};
+pub use self::regression::{RegressionSplitter, SortedColumns};
 pub use self::split::Split;
 pub use self::splitter::Splitter;
+pub use crate::core::Monotonicity;
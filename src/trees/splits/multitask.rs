@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+
+use float_cmp::approx_eq;
+use rand::prelude::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use super::categorical::{best_category_subset, group_categories, CategoryGroup};
+use super::{Split, Splitter};
+use crate::core::{AnyValue, MultiTaskLabel, TrainingRow};
+use crate::trees::impurity::{ImpurityCalculator, MultiTaskCalculator};
+use crate::utils::{par_map, task_seeds};
+
+/// The labels and weights of the rows in a category.
+type TaskRows<'a> = Vec<(&'a MultiTaskLabel, f64)>;
+
+/// A splitter for multi-task labels, minimizing the combined normalized impurity of the tasks.
+#[derive(Clone, Debug)]
+pub struct MultiTaskSplitter {
+    randomize_pivot: bool,
+}
+
+impl MultiTaskSplitter {
+    pub fn new(randomize_pivot: bool) -> Self {
+        Self { randomize_pivot }
+    }
+
+    /// Find the best split on a continuous feature.
+    #[allow(clippy::too_many_arguments)]
+    fn best_real_split(
+        &self,
+        data: &[TrainingRow<MultiTaskLabel>],
+        indices: &[usize],
+        calc: &mut MultiTaskCalculator,
+        idx: usize,
+        seed: u64,
+        min_count: usize,
+        min_weight: f64,
+    ) -> (Split, f64) {
+        let mut thin_data: Vec<(f64, &[Option<AnyValue>], f64)> = indices
+            .iter()
+            .map(|&i| {
+                let row = &data[i];
+                let x = row.features[idx].as_real().unwrap_or(f64::NAN);
+                (x, row.label.as_slice(), row.weight.unwrap_or(1.0))
+            })
+            .collect();
+        thin_data.sort_by(|(a, _, _), (b, _, _)| a.total_cmp(b));
+
+        let mut best_impurity = f64::INFINITY;
+        let mut best_pivot = f64::INFINITY;
+
+        calc.reset();
+        let min_count = min_count.max(1);
+        let total_weight: f64 = thin_data.iter().map(|(_, _, w)| w).sum();
+        let mut left_weight = 0.0;
+        let mut rng = self.randomize_pivot.then(|| StdRng::seed_from_u64(seed));
+        let jmax = thin_data.len().saturating_sub(min_count);
+        for j in 0..jmax {
+            calc.add(thin_data[j].1, thin_data[j].2);
+            left_weight += thin_data[j].2;
+            let impurity = calc.impurity();
+
+            let left = thin_data[j + 1].0;
+            let right = thin_data[j].0;
+            let lr_equal = approx_eq!(f64, left, right, epsilon = 1e-10);
+            let heavy = left_weight >= min_weight && total_weight - left_weight >= min_weight;
+            if impurity < best_impurity && j + 1 >= min_count && heavy && !lr_equal {
+                best_impurity = impurity;
+                best_pivot = match rng.as_mut() {
+                    Some(rng) => right + (left - right) * rng.gen::<f64>(),
+                    None => 0.5 * (left + right),
+                }
+            }
+        }
+
+        (Split::Real(idx, best_pivot), best_impurity)
+    }
+
+    /// Find the best split on a categorical feature.
+    ///
+    /// Categories are ordered by a score summing the standardized mean of each real task
+    /// and the share of the most common class of each categorical task, for the sweep used when
+    /// there are too many categories to try every subset.
+    fn best_categorical_split(
+        data: &[TrainingRow<MultiTaskLabel>],
+        indices: &[usize],
+        calc: &mut MultiTaskCalculator,
+        idx: usize,
+        min_count: usize,
+        min_weight: f64,
+    ) -> (Split, f64) {
+        // The labels of the rows in each category, which move between the sides together
+        let accumulate = |rows: &mut Vec<_>, label, weight| rows.push((label, weight));
+        let groups = group_categories(data, indices, idx, accumulate);
+
+        let nt = indices.iter().map(|&i| data[i].label.len()).max().unwrap_or(0);
+        let task_values = |rows: &[(&MultiTaskLabel, f64)], k: usize| -> Vec<(AnyValue, f64)> {
+            rows.iter()
+                .filter_map(|(label, w)| label.get(k).copied().flatten().map(|y| (y, *w)))
+                .collect()
+        };
+
+        // Summaries of each task over all rows at the node
+        let all_rows: TaskRows = groups.iter().flat_map(|g| g.mass.iter().copied()).collect();
+        let summaries: Vec<Option<(AnyValue, f64)>> = (0..nt)
+            .map(|k| {
+                let values = task_values(&all_rows, k);
+                let total: f64 = values.iter().map(|(_, w)| w).sum();
+                match values.first() {
+                    Some((AnyValue::Real(_), _)) => {
+                        let mean =
+                            values.iter().map(|(y, w)| w * y.as_real().unwrap_or(0.0)).sum::<f64>()
+                                / total;
+                        let var = values
+                            .iter()
+                            .map(|(y, w)| w * (y.as_real().unwrap_or(mean) - mean).powi(2))
+                            .sum::<f64>()
+                            / total;
+                        Some((AnyValue::Real(mean), var.sqrt()))
+                    }
+                    Some((AnyValue::Categorical(_), _)) => {
+                        let mut counts: BTreeMap<usize, f64> = BTreeMap::new();
+                        for (y, w) in values.iter() {
+                            *counts.entry(y.as_categorical().unwrap_or(0)).or_insert(0.0) += w;
+                        }
+                        let mode = counts
+                            .into_iter()
+                            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                            .map_or(0, |(c, _)| c);
+                        Some((AnyValue::Categorical(mode), 0.0))
+                    }
+                    None => None,
+                }
+            })
+            .collect();
+
+        let order = |group: &CategoryGroup<TaskRows>| -> f64 {
+            summaries
+                .iter()
+                .enumerate()
+                .filter_map(|(k, summary)| {
+                    let values = task_values(&group.mass, k);
+                    let total: f64 = values.iter().map(|(_, w)| w).sum();
+                    if total == 0.0 {
+                        return None;
+                    }
+                    match summary {
+                        Some((AnyValue::Real(mean), std)) if *std > 0.0 => {
+                            let group_mean = values
+                                .iter()
+                                .map(|(y, w)| w * y.as_real().unwrap_or(*mean))
+                                .sum::<f64>()
+                                / total;
+                            Some((group_mean - mean) / std)
+                        }
+                        Some((AnyValue::Categorical(mode), _)) => {
+                            let share = values
+                                .iter()
+                                .filter(|(y, _)| y.as_categorical() == Some(*mode))
+                                .map(|(_, w)| w)
+                                .sum::<f64>();
+                            Some(share / total)
+                        }
+                        _ => None,
+                    }
+                })
+                .sum()
+        };
+        let step = |rows: Option<&TaskRows>| match rows {
+            Some(rows) => {
+                rows.iter().for_each(|(label, weight)| calc.add(label, *weight));
+                calc.impurity()
+            }
+            None => {
+                calc.reset();
+                f64::INFINITY
+            }
+        };
+        let (best_set, best_impurity) =
+            best_category_subset(groups, order, min_count, min_weight, step);
+
+        (Split::Categorical(idx, best_set), best_impurity)
+    }
+}
+
+impl Splitter<MultiTaskLabel> for MultiTaskSplitter {
+    type NodeStats = ();
+
+    fn find_best_split_with_stats(
+        &mut self,
+        data: &[TrainingRow<MultiTaskLabel>],
+        indices: &[usize],
+        _stats: &(),
+        nfeatures: usize,
+        min_count: usize,
+        min_weight: f64,
+        rng: &mut impl Rng,
+    ) -> (Split, f64) {
+        let calc = MultiTaskCalculator::from_indexed_data(data, indices);
+        let init_impurity = calc.impurity();
+        if calc.num_active_tasks() == 0 {
+            return (Split::None, 0.0);
+        }
+
+        let mut best_split = Split::None;
+        let mut best_impurity = f64::INFINITY;
+
+        let rep = &data[indices[0]];
+        let nf = rep.features.data.len();
+        let mut features: Vec<usize> = (0..nf).collect();
+        features.shuffle(rng);
+
+        // Each feature is searched with its own calculator and random stream
+        let seeds = task_seeds(rng, nfeatures.min(nf));
+        let tasks: Vec<(usize, u64)> = features.into_iter().zip(seeds).collect();
+        let candidates = par_map(&tasks, |&(index, seed)| {
+            let mut calc = calc.clone();
+            match index {
+                idx if rep.features[idx].is_real() => {
+                    self.best_real_split(data, indices, &mut calc, idx, seed, min_count, min_weight)
+                }
+                idx => Self::best_categorical_split(
+                    data, indices, &mut calc, idx, min_count, min_weight,
+                ),
+            }
+        });
+
+        for (trial_split, trial_impurity) in candidates {
+            if trial_impurity < best_impurity {
+                best_impurity = trial_impurity;
+                best_split = trial_split;
+            }
+        }
+
+        if best_impurity.is_infinite() {
+            (Split::None, 0.0)
+        } else {
+            (best_split, init_impurity - best_impurity)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn split_on_sparse_tasks() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // The real task only depends on the first feature, and the categorical task on the second.
+        // Both tasks are missing on half of the rows.
+        let data: Vec<TrainingRow<MultiTaskLabel>> = (0..16)
+            .map(|i| {
+                let (a, b) = ((i % 2) as f64, (i / 8) as f64);
+                let real = (i % 4 < 2).then_some(AnyValue::from(1000.0 * a));
+                let class = (i % 4 >= 2).then_some(AnyValue::from(1 + b as usize));
+                TrainingRow::new(vec![a, b], vec![real, class], None)
+            })
+            .collect();
+
+        let mut splitter = MultiTaskSplitter::new(false);
+        let (split, delta) = splitter.find_best_split(&data, 2, 1, &mut rng);
+
+        // Either split purifies exactly one of the two normalized tasks
+        assert!(split == Split::Real(0, 0.5) || split == Split::Real(1, 0.5));
+        assert!((delta - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn split_categories() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Even categories have high real labels and class 2, with each task on half of the rows
+        let data: Vec<TrainingRow<MultiTaskLabel>> = (0..40)
+            .map(|i| {
+                let category = 1 + i % 4;
+                let even = category % 2 == 0;
+                let real = (i % 8 < 4).then_some(AnyValue::from(if even { 10.0 } else { 0.0 }));
+                let class = (i % 8 >= 4).then_some(AnyValue::from(1 + even as usize));
+                TrainingRow::new(vec![AnyValue::Categorical(category)], vec![real, class], None)
+            })
+            .collect();
+
+        let mut splitter = MultiTaskSplitter::new(false);
+        let (split, delta) = splitter.find_best_split(&data, 1, 1, &mut rng);
+        match split {
+            Split::Categorical(0, ref set) => {
+                let left: Vec<usize> = set.iter().collect();
+                assert!(left == vec![1, 3] || left == vec![2, 4], "{:?}", left);
+            }
+            _ => panic!("Expected a categorical split, got {:?}", split),
+        }
+        assert!((delta - 2.0).abs() < 1e-9);
+    }
+}
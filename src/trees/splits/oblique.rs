@@ -0,0 +1,266 @@
+use float_cmp::approx_eq;
+use nalgebra::{DMatrix, DVector};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_distr::{Distribution, StandardNormal};
+
+use super::{RegressionSplitter, Split, Splitter};
+use crate::core::{RegressionLabel, TrainingRow};
+use crate::linear::LinearRegressionLearner;
+use crate::trees::impurity::{ImpurityCalculator, VarianceCalculator};
+
+/// How the directions of oblique splits are chosen at each node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObliqueDirection {
+    /// The given number of directions with random Gaussian weights.
+    Random(usize),
+    /// The coefficients of a ridge regression of the label summed over its outputs, with the
+    /// given penalty.
+    Ridge(f64),
+}
+
+/// A regression splitter searching hyperplane splits over weighted sums of the real features.
+///
+/// The real features are standardized at each node, and the rows are projected onto each
+/// direction and swept in order of their projection. Rows missing any of the features go right.
+/// Categorical features are searched as by the [`RegressionSplitter`].
+#[derive(Clone, Debug)]
+pub struct ObliqueSplitter {
+    direction: ObliqueDirection,
+}
+
+impl ObliqueSplitter {
+    pub fn new(direction: ObliqueDirection) -> Self {
+        Self { direction }
+    }
+
+    /// The mean and standard deviation of each non-constant real feature over the rows.
+    fn standardize<L>(
+        data: &[TrainingRow<L>],
+        indices: &[usize],
+        features: &[usize],
+    ) -> Vec<(usize, f64, f64)> {
+        features
+            .iter()
+            .filter_map(|&idx| {
+                let values: Vec<f64> = indices
+                    .iter()
+                    .filter_map(|&i| data[i].features[idx].as_real())
+                    .filter(|x| x.is_finite())
+                    .collect();
+                let n = values.len() as f64;
+                let mean = values.iter().sum::<f64>() / n;
+                let std = (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+                (std > 0.0).then_some((idx, mean, std))
+            })
+            .collect()
+    }
+
+    /// The standardized weights of a ridge regression on the rows with every feature present.
+    fn ridge_direction<L: RegressionLabel>(
+        data: &[TrainingRow<L>],
+        indices: &[usize],
+        scales: &[(usize, f64, f64)],
+        penalty: f64,
+    ) -> Option<Vec<f64>> {
+        let standardized = |row: &TrainingRow<L>| -> Option<Vec<f64>> {
+            scales
+                .iter()
+                .map(|(idx, mean, std)| {
+                    let x = row.features[*idx].as_real().filter(|x| x.is_finite())?;
+                    Some((x - mean) / std)
+                })
+                .collect()
+        };
+        let rows: Vec<(Vec<f64>, f64, f64)> = indices
+            .iter()
+            .filter_map(|&i| {
+                let row = &data[i];
+                let y: f64 = row.label.outputs().iter().sum();
+                standardized(row).map(|x| (x, y, row.weight.unwrap_or(1.0)))
+            })
+            .collect();
+        if rows.len() < 2 {
+            return None;
+        }
+
+        let total_weight: f64 = rows.iter().map(|(_, _, w)| w).sum();
+        let y_mean = rows.iter().map(|(_, y, w)| w * y).sum::<f64>() / total_weight;
+        let X = DMatrix::from_fn(rows.len(), scales.len(), |i, j| rows[i].0[j]);
+        let y = DVector::from_iterator(rows.len(), rows.iter().map(|(_, y, _)| y - y_mean));
+        let w = DVector::from_iterator(rows.len(), rows.iter().map(|(_, _, w)| *w));
+
+        let learner = LinearRegressionLearner::new(false, Some(penalty));
+        let beta = learner.solve_normal_equation(&X, &y, &w).ok()?;
+        beta.iter().any(|b| *b != 0.0).then(|| beta.iter().copied().collect())
+    }
+
+    /// Find the best threshold on the projection of the rows onto the weights.
+    fn best_oblique_split<L: RegressionLabel>(
+        data: &[TrainingRow<L>],
+        indices: &[usize],
+        calc: &mut VarianceCalculator,
+        weights: Vec<(usize, f64)>,
+        min_count: usize,
+        min_weight: f64,
+    ) -> (Split, f64) {
+        let mut thin_data: Vec<(f64, &[f64], f64)> = indices
+            .iter()
+            .map(|&i| {
+                let row = &data[i];
+                let projection: f64 = weights
+                    .iter()
+                    .map(|(idx, w)| w * row.features[*idx].as_real().unwrap_or(f64::NAN))
+                    .sum();
+                (projection, row.label.outputs(), row.weight.unwrap_or(1.0))
+            })
+            .collect();
+        thin_data.sort_by(|(a, _, _), (b, _, _)| a.total_cmp(b));
+
+        let mut best_variance = f64::INFINITY;
+        let mut best_pivot = f64::INFINITY;
+
+        // Only rows with a projection may move left
+        ImpurityCalculator::<&[f64]>::reset(calc);
+        let min_count = min_count.max(1);
+        let total_weight: f64 = thin_data.iter().map(|(_, _, w)| w).sum();
+        let mut left_weight = 0.0;
+        let present = thin_data.iter().filter(|(x, _, _)| !x.is_nan()).count();
+        let jmax = thin_data.len().saturating_sub(min_count).min(present.saturating_sub(1));
+        for j in 0..jmax {
+            calc.add(thin_data[j].1, thin_data[j].2);
+            left_weight += thin_data[j].2;
+            let total_variance = ImpurityCalculator::<&[f64]>::impurity(calc);
+
+            let left = thin_data[j + 1].0;
+            let right = thin_data[j].0;
+            let lr_equal = approx_eq!(f64, left, right, epsilon = 1e-10);
+            let heavy = left_weight >= min_weight && total_weight - left_weight >= min_weight;
+            if total_variance < best_variance && j + 1 >= min_count && heavy && !lr_equal {
+                best_variance = total_variance;
+                best_pivot = 0.5 * (left + right);
+            }
+        }
+
+        (Split::Oblique(weights, best_pivot), best_variance)
+    }
+}
+
+impl<L: RegressionLabel> Splitter<L> for ObliqueSplitter {
+    type NodeStats = ();
+
+    fn find_best_split_with_stats(
+        &mut self,
+        data: &[TrainingRow<L>],
+        indices: &[usize],
+        _stats: &(),
+        num_features: usize,
+        min_count: usize,
+        min_weight: f64,
+        rng: &mut impl Rng,
+    ) -> (Split, f64) {
+        let mut calc = VarianceCalculator::from_indexed_data(data, indices);
+        let init_variance = ImpurityCalculator::<&[f64]>::impurity(&calc);
+
+        let mut best_split = Split::None;
+        let mut best_variance = f64::INFINITY;
+
+        let rep = &data[indices[0]];
+        let nf = rep.features.data.len();
+        let mut features: Vec<usize> = (0..nf).collect();
+        features.shuffle(rng);
+        let (real, categorical): (Vec<usize>, Vec<usize>) =
+            features.into_iter().take(num_features).partition(|&idx| rep.features[idx].is_real());
+
+        let scales = Self::standardize(data, indices, &real);
+        let directions: Vec<Vec<f64>> = match self.direction {
+            _ if scales.is_empty() => vec![],
+            ObliqueDirection::Random(num_directions) => (0..num_directions)
+                .map(|_| scales.iter().map(|_| StandardNormal.sample(rng)).collect())
+                .collect(),
+            ObliqueDirection::Ridge(penalty) => {
+                Self::ridge_direction(data, indices, &scales, penalty).into_iter().collect()
+            }
+        };
+
+        let mut candidates: Vec<(Split, f64)> = directions
+            .into_iter()
+            .map(|direction| {
+                // Undo the standardization, which only shifts the threshold by a constant
+                let weights: Vec<(usize, f64)> = scales
+                    .iter()
+                    .zip(direction)
+                    .map(|((idx, _, std), w)| (*idx, w / std))
+                    .collect();
+                Self::best_oblique_split(data, indices, &mut calc, weights, min_count, min_weight)
+            })
+            .collect();
+        for idx in categorical {
+            let candidate = RegressionSplitter::best_categorical_split(
+                data, indices, &mut calc, idx, min_count, min_weight,
+            );
+            candidates.push(candidate);
+        }
+
+        for (trial_split, trial_variance) in candidates {
+            if trial_variance < best_variance {
+                best_variance = trial_variance;
+                best_split = trial_split;
+            }
+        }
+
+        if best_variance.is_infinite() {
+            (Split::None, 0.0)
+        } else {
+            (best_split, init_variance - best_variance)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::linear::GuessTheMeanLearner;
+    use crate::trees::leaf::RegressionLeafLearner;
+    use crate::trees::learners::{DecisionTreeParameters, RegressionTreeLearner};
+
+    #[test]
+    fn split_on_rotated_features() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // A step across the diagonal, which axis-aligned splits can only approximate
+        let data: Vec<TrainingRow<f64>> = (0..400)
+            .map(|_| {
+                let (a, b) = (rng.gen::<f64>(), rng.gen::<f64>());
+                TrainingRow::new(vec![a, b], if a + b > 1.0 { 1.0 } else { 0.0 }, None)
+            })
+            .collect();
+        let indices: Vec<usize> = (0..data.len()).collect();
+        let calc = VarianceCalculator::from_indexed_data(&data, &indices);
+        let total = ImpurityCalculator::<&[f64]>::impurity(&calc);
+
+        let mut axis = RegressionSplitter::new(false);
+        let mut ridge = ObliqueSplitter::new(ObliqueDirection::Ridge(1.0));
+        let mut random = ObliqueSplitter::new(ObliqueDirection::Random(20));
+        let (_, axis_delta) = axis.find_best_split(&data, 2, 1, &mut rng);
+        let (split, ridge_delta) = ridge.find_best_split(&data, 2, 1, &mut rng);
+        let (_, random_delta) = random.find_best_split(&data, 2, 1, &mut rng);
+        assert!(matches!(split, Split::Oblique(ref weights, _) if weights.len() == 2));
+        assert!(ridge_delta > 0.95 * total && ridge_delta > axis_delta);
+        assert!(random_delta > axis_delta);
+
+        // The oblique tree needs fewer leaves, and exports its hyperplanes
+        let leaf = RegressionLeafLearner::mean(GuessTheMeanLearner::default());
+        let params = DecisionTreeParameters::default().with_min_leaf_instances(1);
+        let oblique = RegressionTreeLearner::new(ridge, leaf.clone(), params);
+        let oblique = oblique.fit_model(&data, &mut rng).unwrap();
+        let axis = RegressionTreeLearner::new(axis, leaf, params).fit_model(&data, &mut rng);
+        let (oblique_leaves, axis_leaves) =
+            (oblique.training_root().num_leaves(), axis.unwrap().training_root().num_leaves());
+        assert!(oblique_leaves < axis_leaves);
+        let text = oblique.root().export_text();
+        assert!(text.starts_with("|--- ") && text.contains("* x[0]") && text.contains("* x[1]"));
+    }
+}
@@ -1,63 +1,103 @@
-use std::collections::HashSet;
-
 use float_cmp::approx_eq;
-use itertools::Itertools;
 use rand::prelude::StdRng;
 use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
 
-use super::{Split, Splitter};
-use crate::core::TrainingRow;
+use super::categorical::{best_category_subset, group_categories, CategoryGroup};
+use super::{MonotonicConstraints, Monotonicity, Split, Splitter};
+use crate::core::{RegressionLabel, Result, TrainingRow};
 use crate::trees::impurity::{ImpurityCalculator, VarianceCalculator};
+use crate::utils::{par_map, task_seeds};
+
+/// The rows of a node sorted by each real feature, as indices into the training data.
+///
+/// Columns are sorted once at the root, and the order is kept stable while partitioning the rows
+/// between children, so each node is searched in linear time.
+#[derive(Clone, Debug, Default)]
+pub struct SortedColumns {
+    orders: Vec<Option<Vec<usize>>>,
+}
+
+impl SortedColumns {
+    /// The rows sorted by a real feature, if it was presorted.
+    pub fn order(&self, idx: usize) -> Option<&[usize]> {
+        self.orders.get(idx).and_then(|order| order.as_deref())
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct RegressionSplitter {
     randomize_pivot: bool,
-    rng: StdRng,
+    is_left: Vec<bool>,
+    constraints: MonotonicConstraints,
 }
 
 impl RegressionSplitter {
-    pub fn new(randomize_pivot: bool, rng: Option<&mut StdRng>) -> Self {
-        let new_rng = match rng {
-            Some(r) => SeedableRng::from_rng(r).expect("Seeding RNG failed."),
-            None => SeedableRng::from_entropy(),
-        };
-        Self { randomize_pivot, rng: new_rng }
+    pub fn new(randomize_pivot: bool) -> Self {
+        Self { randomize_pivot, is_left: vec![], constraints: Default::default() }
     }
 
-    /// Find the best split on a continuous feature.
-    fn best_real_split(
-        &mut self,
-        data: &[TrainingRow<f64>],
+    /// Sort the rows at `indices` by a real feature, with missing values last.
+    fn sort_column<L>(data: &[TrainingRow<L>], indices: &[usize], idx: usize) -> Vec<usize> {
+        let value = |i: usize| data[i].features[idx].as_real().unwrap_or(f64::NAN);
+        let mut order = indices.to_vec();
+        order.sort_by(|&a, &b| value(a).total_cmp(&value(b)));
+        order
+    }
+
+    /// Find the best split on a continuous feature, sweeping the rows in sorted order.
+    #[allow(clippy::too_many_arguments)]
+    fn best_real_split<L: RegressionLabel>(
+        &self,
+        data: &[TrainingRow<L>],
+        order: &[usize],
         calc: &mut VarianceCalculator,
         idx: usize,
+        seed: u64,
         min_count: usize,
+        min_weight: f64,
     ) -> (Split, f64) {
-        // Pull out the feature to consider and sort by it
-        let mut thin_data: Vec<(f64, f64, f64)> =
-            data.iter().map(|row| (row.features.real_at(idx), row.label, row.weight)).collect();
-        thin_data.sort_by(|(a, _, _), (b, _, _)| a.partial_cmp(b).unwrap());
+        let thin_data: Vec<(f64, &[f64], f64)> = order
+            .iter()
+            .map(|&i| {
+                let row = &data[i];
+                let x = row.features[idx].as_real().unwrap_or(f64::NAN);
+                (x, row.label.outputs(), row.weight.unwrap_or(1.0))
+            })
+            .collect();
 
         // Best cases for iteration
         let mut best_variance = f64::INFINITY;
         let mut best_pivot = f64::INFINITY;
 
         // Move the data from right to left partition one value at a time
-        calc.reset();
-        let jmax = data.len() - min_count;
+        let mut rng = self.randomize_pivot.then(|| StdRng::seed_from_u64(seed));
+        let direction = self.constraints.direction(idx);
+        ImpurityCalculator::<&[f64]>::reset(calc);
+        let min_count = min_count.max(1);
+        let total_weight: f64 = thin_data.iter().map(|(_, _, w)| w).sum();
+        let mut left_weight = 0.0;
+        let jmax = thin_data.len().saturating_sub(min_count);
         for j in 0..jmax {
             calc.add(thin_data[j].1, thin_data[j].2);
-            let total_variance = calc.impurity();
+            left_weight += thin_data[j].2;
+            let total_variance = ImpurityCalculator::<&[f64]>::impurity(calc);
 
             // Keep track of the best split, avoiding splits in the middle of constant features
             let left = thin_data[j + 1].0;
             let right = thin_data[j].0;
             let lr_equal = approx_eq!(f64, left, right, epsilon = 1e-10);
-            if total_variance < best_variance && j + 1 >= min_count && !lr_equal {
+            if total_variance < best_variance
+                && j + 1 >= min_count
+                && left_weight >= min_weight
+                && total_weight - left_weight >= min_weight
+                && !lr_equal
+                && Self::is_allowed(calc, direction)
+            {
                 best_variance = total_variance;
-                best_pivot = match self.randomize_pivot {
-                    true => right + (left - right) * self.rng.gen::<f64>(),
-                    false => 0.5 * (left + right),
+                best_pivot = match rng.as_mut() {
+                    Some(rng) => right + (left - right) * rng.gen::<f64>(),
+                    None => 0.5 * (left + right),
                 }
             }
         }
@@ -65,107 +105,151 @@ impl RegressionSplitter {
         (Split::Real(idx, best_pivot), best_variance)
     }
 
+    /// Whether the current split of the calculator respects a monotonic constraint.
+    pub(crate) fn is_allowed(calc: &VarianceCalculator, direction: Monotonicity) -> bool {
+        if direction == Monotonicity::None {
+            return true;
+        }
+        let (left, right) = calc.means();
+        direction.allows(&left, &right)
+    }
+
     /// Find the best split on a categorical variable.
-    fn best_categorical_split(
-        &mut self,
-        data: &[TrainingRow<f64>],
+    ///
+    /// The labels are summed per category, and the categories are ordered by their mean label,
+    /// summed over the outputs, when there are too many to search every subset.
+    pub(crate) fn best_categorical_split<L: RegressionLabel>(
+        data: &[TrainingRow<L>],
+        indices: &[usize],
         calc: &mut VarianceCalculator,
         idx: usize,
         min_count: usize,
+        min_weight: f64,
     ) -> (Split, f64) {
-        let thin_data: Vec<(usize, f64, f64)> = data
-            .iter()
-            .map(|row| (row.features.categorical_at(idx), row.label, row.weight))
-            .collect();
-        let total_weight: f64 = thin_data.iter().fold(0.0, |state, (_, _, w)| state + w);
-
-        // Group the data by categorical feature
-        struct CategoryAvg {
-            category: usize,
-            label_avg: f64,
-            weight: f64,
-            size: usize,
-        }
+        // Weighted sums of the outputs and their weight, skipping rows the calculator ignores
+        let accumulate = |(sums, total): &mut (Vec<f64>, f64), label: &L, weight: f64| {
+            let outputs = label.outputs();
+            if !outputs.iter().any(|y| y.is_nan()) && !weight.is_nan() {
+                sums.resize(outputs.len(), 0.0);
+                sums.iter_mut().zip(outputs).for_each(|(s, y)| *s += weight * y);
+                *total += weight;
+            }
+        };
+        let groups = group_categories(data, indices, idx, accumulate);
 
-        // TODO: This is incorrect b/c group_by is consecutive. Rewrite with manual loop and group
-        let mut category_averages: Vec<CategoryAvg> = thin_data
-            .iter()
-            .group_by(|(category, _, _)| category)
-            .into_iter()
-            .map(|(&category, groups)| {
-                let mut label_sum = 0.0;
-                let mut weight = 0.0;
-                let mut size = 0;
-                for g in groups {
-                    label_sum += g.1 * g.2;
-                    weight += g.2;
-                    size += 1;
-                }
-                CategoryAvg { category, label_avg: label_sum / weight, weight, size }
-            })
-            .collect();
+        let order = |group: &CategoryGroup<(Vec<f64>, f64)>| {
+            let (sums, total) = &group.mass;
+            sums.iter().sum::<f64>() / total
+        };
+        let step = |mass: Option<&(Vec<f64>, f64)>| match mass {
+            Some((sums, total)) => {
+                calc.add_sums(sums, *total);
+                ImpurityCalculator::<&[f64]>::impurity(calc)
+            }
+            None => {
+                ImpurityCalculator::<&[f64]>::reset(calc);
+                f64::INFINITY
+            }
+        };
+        let (best_set, best_variance) =
+            best_category_subset(groups, order, min_count, min_weight, step);
 
-        // If too many values are trivial, return an empty split
-        let non_trivial: f64 =
-            category_averages.iter().map(|avg| if avg.size > 1 { avg.weight } else { 0.0 }).sum();
-        if non_trivial / total_weight < 0.5 {
-            return (Split::Categorical(idx, HashSet::new()), f64::INFINITY);
-        }
+        (Split::Categorical(idx, best_set), best_variance)
+    }
+}
 
-        // Best cases for iteration
-        let mut left_num: usize = 0;
-        let mut best_variance = f64::INFINITY;
-        let mut best_set: HashSet<usize> = HashSet::new();
+impl<L: RegressionLabel> Splitter<L> for RegressionSplitter {
+    type NodeStats = SortedColumns;
 
-        // Sort by ascending label avg per category
-        category_averages.sort_by(|c1, c2| c1.label_avg.partial_cmp(&c2.label_avg).unwrap());
+    fn node_stats(&mut self, data: &[TrainingRow<L>], indices: &[usize]) -> SortedColumns {
+        let Some(&first) = indices.first() else {
+            return SortedColumns::default();
+        };
+        let rep = &data[first];
+        let orders = (0..rep.features.data.len())
+            .map(|idx| rep.features[idx].is_real().then(|| Self::sort_column(data, indices, idx)))
+            .collect();
+        SortedColumns { orders }
+    }
 
-        // Add categories one at a time in order of avg label
-        calc.reset();
-        for j in 0..(category_averages.len() - 1) {
-            let avg = &category_averages[j];
-            left_num += avg.size;
+    fn partition_stats(
+        &mut self,
+        data: &[TrainingRow<L>],
+        parent: SortedColumns,
+        left: &[usize],
+        _right: &[usize],
+    ) -> (SortedColumns, SortedColumns) {
+        self.is_left.resize(data.len(), false);
+        left.iter().for_each(|&i| self.is_left[i] = true);
 
-            calc.add(avg.label_avg, avg.weight);
-            let total_variance = calc.impurity();
+        // A stable partition of each sorted column keeps the children sorted
+        let (left_orders, right_orders) = parent
+            .orders
+            .into_iter()
+            .map(|order| match order {
+                Some(order) => {
+                    let (l, r): (Vec<usize>, Vec<usize>) =
+                        order.into_iter().partition(|&i| self.is_left[i]);
+                    (Some(l), Some(r))
+                }
+                None => (None, None),
+            })
+            .unzip();
 
-            if total_variance < best_variance
-                && left_num >= min_count
-                && (thin_data.len() - left_num) >= min_count
-            {
-                best_variance = total_variance;
-                best_set = category_averages[..(j + 1)].iter().map(|avg| avg.category).collect();
-            }
-        }
+        left.iter().for_each(|&i| self.is_left[i] = false);
+        (SortedColumns { orders: left_orders }, SortedColumns { orders: right_orders })
+    }
 
-        (Split::Categorical(idx, best_set), best_variance)
+    fn set_constraints(&mut self, constraints: &MonotonicConstraints) -> Result<()> {
+        self.constraints = constraints.clone();
+        Ok(())
     }
-}
 
-impl Splitter<f64> for RegressionSplitter {
-    fn find_best_split(
+    fn find_best_split_with_stats(
         &mut self,
-        data: &[TrainingRow<f64>],
+        data: &[TrainingRow<L>],
+        indices: &[usize],
+        stats: &SortedColumns,
         nfeatures: usize,
         min_count: usize,
+        min_weight: f64,
+        rng: &mut impl Rng,
     ) -> (Split, f64) {
-        let mut calc = VarianceCalculator::from_training_data(data);
-        let init_variance = calc.impurity();
+        let calc = VarianceCalculator::from_indexed_data(data, indices);
+        let init_variance = ImpurityCalculator::<&[f64]>::impurity(&calc);
 
         let mut best_split = Split::None;
         let mut best_variance = f64::INFINITY;
 
-        let rep = &data[0];
-        let (nf, nr) = (rep.features.num_features(), rep.features.num_reals());
-        let mut indices: Vec<usize> = (0..nf).collect();
-        indices.shuffle(&mut self.rng);
+        let rep = &data[indices[0]];
+        let nf = rep.features.data.len();
+        let mut features: Vec<usize> = (0..nf).collect();
+        features.shuffle(rng);
 
-        for index in indices.into_iter().take(nfeatures) {
-            let (trial_split, trial_variance): (Split, f64) = match index {
-                idx if idx < nr => self.best_real_split(data, &mut calc, idx, min_count),
-                idx => self.best_categorical_split(data, &mut calc, idx - nr, min_count),
-            };
+        // Each feature is searched with its own calculator and random stream
+        let seeds = task_seeds(rng, nfeatures.min(nf));
+        let tasks: Vec<(usize, u64)> = features.into_iter().zip(seeds).collect();
+        let candidates = par_map(&tasks, |&(index, seed)| {
+            let mut calc = calc.clone();
+            match index {
+                idx if rep.features[idx].is_real() => {
+                    let sorted;
+                    let order = match stats.order(idx) {
+                        Some(order) => order,
+                        None => {
+                            sorted = Self::sort_column(data, indices, idx);
+                            &sorted
+                        }
+                    };
+                    self.best_real_split(data, order, &mut calc, idx, seed, min_count, min_weight)
+                }
+                idx => Self::best_categorical_split(
+                    data, indices, &mut calc, idx, min_count, min_weight,
+                ),
+            }
+        });
 
+        for (trial_split, trial_variance) in candidates {
             if trial_variance < best_variance {
                 best_variance = trial_variance;
                 best_split = trial_split;
@@ -183,23 +267,122 @@ impl Splitter<f64> for RegressionSplitter {
 
 #[cfg(test)]
 mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
     use super::*;
-    use crate::core::FeatureRow;
+    use crate::core::{AnyValue, FeatureRow};
 
     #[test]
     fn split_real() {
+        let mut rng = StdRng::seed_from_u64(0);
         let data = vec![
-            TrainingRow::new(vec![1.0], vec![], 1.0, 1.0),
-            TrainingRow::new(vec![2.0], vec![], 2.0, 1.0),
+            TrainingRow::new(vec![1.0], 1.0, Some(1.0)),
+            TrainingRow::new(vec![2.0], 2.0, Some(1.0)),
         ];
 
-        let mut splitter = RegressionSplitter::new(false, None);
-        let (split, _) = splitter.find_best_split(&data, 10, 1);
+        let mut splitter = RegressionSplitter::new(false);
+        let (split, _) = splitter.find_best_split(&data, 10, 1, &mut rng);
 
-        let row1 = FeatureRow::new(vec![1.49], vec![]);
+        let row1 = FeatureRow::from(vec![1.49]);
         assert!(split.turn_left(&row1));
 
-        let row2 = FeatureRow::new(vec![1.51], vec![]);
+        let row2 = FeatureRow::from(vec![1.51]);
         assert!(!split.turn_left(&row2));
     }
+
+    #[test]
+    fn split_multiple_outputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // The second output only varies with the second feature, and dominates the impurity
+        let data: Vec<TrainingRow<Vec<f64>>> = (0..8)
+            .map(|i| {
+                let (a, b) = ((i % 2) as f64, (i / 4) as f64);
+                TrainingRow::new(vec![a, b], vec![a, 100.0 * b], None)
+            })
+            .collect();
+
+        let mut splitter = RegressionSplitter::new(false);
+        let (split, delta) = splitter.find_best_split(&data, 2, 1, &mut rng);
+        assert_eq!(split, Split::Real(1, 0.5));
+        assert!((delta - 8.0 * 2500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn split_min_leaf_weight() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Two light outliers at the low end, and a smaller step in the middle
+        let data: Vec<TrainingRow<f64>> = (0..10)
+            .map(|i| match i {
+                0 | 1 => TrainingRow::new(vec![i as f64], 100.0, Some(0.5)),
+                _ => TrainingRow::new(vec![i as f64], (i >= 6) as usize as f64, Some(1.0)),
+            })
+            .collect();
+
+        let mut splitter = RegressionSplitter::new(false);
+        let (split, _) = splitter.find_best_split(&data, 1, 1, &mut rng);
+        assert_eq!(split, Split::Real(0, 1.5));
+
+        // Isolating the outliers leaves too little weight on the left, so another pivot is chosen
+        let indices: Vec<usize> = (0..data.len()).collect();
+        let stats = Splitter::<f64>::node_stats(&mut splitter, &data, &indices);
+        let (split, delta) =
+            splitter.find_best_split_with_stats(&data, &indices, &stats, 1, 1, 2.0, &mut rng);
+        assert_ne!(split, Split::Real(0, 1.5));
+        assert!(delta > 0.0);
+        let left: f64 = data
+            .iter()
+            .filter(|row| split.turn_left(&row.features))
+            .map(|row| row.weight.unwrap())
+            .sum();
+        assert!(left >= 2.0 && 9.0 - left >= 2.0);
+    }
+
+    #[test]
+    fn split_interleaved_categories() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Rows of a category are not consecutive, and the two halves differ in their labels
+        let data: Vec<TrainingRow<f64>> = (0..40)
+            .map(|i| {
+                let category = 1 + i % 4;
+                let features = FeatureRow::new(vec![AnyValue::Categorical(category)]);
+                TrainingRow::new(features, if category % 2 == 0 { 10.0 } else { 0.0 }, None)
+            })
+            .collect();
+
+        let mut splitter = RegressionSplitter::new(false);
+        let (split, delta) = splitter.find_best_split(&data, 1, 1, &mut rng);
+        match split {
+            Split::Categorical(0, set) => {
+                assert!(set.len() == 2 && set.contains(1) == set.contains(3))
+            }
+            other => panic!("Unexpected split {:?}", other),
+        }
+        assert!((delta - 40.0 * 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn presorted_partition() {
+        let data: Vec<TrainingRow<f64>> = (0..50)
+            .map(|i| {
+                let (a, b) = (((i * 7) % 13) as f64, ((i * 11) % 17) as f64);
+                TrainingRow::new(vec![a, b], a + b, None)
+            })
+            .collect();
+
+        let mut splitter = RegressionSplitter::new(false);
+        let indices: Vec<usize> = (0..data.len()).collect();
+        let parent = Splitter::<f64>::node_stats(&mut splitter, &data, &indices);
+
+        let (left, right): (Vec<usize>, Vec<usize>) = indices.into_iter().partition(|i| i % 3 == 0);
+        let (left_stats, right_stats) =
+            Splitter::<f64>::partition_stats(&mut splitter, &data, parent, &left, &right);
+
+        // Partitioning the sorted columns gives the same orders as sorting each child directly
+        for (stats, rows) in [(left_stats, left), (right_stats, right)] {
+            let direct = Splitter::<f64>::node_stats(&mut splitter, &data, &rows);
+            for idx in 0..2 {
+                assert_eq!(stats.order(idx), direct.order(idx));
+            }
+        }
+    }
 }
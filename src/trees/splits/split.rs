@@ -1,19 +1,28 @@
+use super::CategorySet;
 use crate::core::FeatureRow;
-use std::collections::HashSet;
+use std::fmt;
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Split {
     None,
     Real(usize, f64),
-    Categorical(usize, HashSet<usize>),
+    Categorical(usize, CategorySet),
+    /// A hyperplane split on a weighted sum of real features, as `(index, weight)` pairs.
+    Oblique(Vec<(usize, f64)>, f64),
 }
 
 impl Split {
-    /// The index of the data row used to obtain this split
+    /// The index of the data row used to obtain this split, or the feature with the largest
+    /// absolute weight of an oblique split
     pub fn index(&self) -> usize {
         match self {
             Self::Real(index, _) => *index,
             Self::Categorical(index, _) => *index,
+            Self::Oblique(weights, _) => weights
+                .iter()
+                .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+                .map_or(usize::MAX, |(index, _)| *index),
             Self::None => usize::MAX,
         }
     }
@@ -21,16 +30,71 @@ impl Split {
     /// Should a new row turn left at this split?
     pub fn turn_left(&self, input: &FeatureRow) -> bool {
         if let Self::Real(index, pivot) = self {
-            let rv = input.real_at(*index);
+            let rv = input[*index].as_real().unwrap_or(f64::NAN);
             if pivot.is_nan() {
                 !rv.is_nan()
             } else {
                 rv <= *pivot
             }
         } else if let Self::Categorical(index, included) = self {
-            included.contains(&input.categorical_at(*index))
+            input[*index].as_categorical().is_some_and(|c| included.contains(c))
+        } else if let Self::Oblique(weights, pivot) = self {
+            // Rows missing any of the features go right
+            let projection: f64 = weights
+                .iter()
+                .map(|(index, w)| w * input[*index].as_real().unwrap_or(f64::NAN))
+                .sum();
+            projection <= *pivot
         } else {
             false
         }
     }
 }
+
+/// Writes the condition for turning left, as in `x[0] <= 1.5` or `x[2] in {1, 3}`.
+impl fmt::Display for Split {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Real(index, pivot) if pivot.is_nan() => write!(f, "x[{}] is present", index),
+            Self::Real(index, pivot) => write!(f, "x[{}] <= {}", index, pivot),
+            Self::Categorical(index, included) => {
+                let categories: Vec<String> = included.iter().map(|c| c.to_string()).collect();
+                write!(f, "x[{}] in {{{}}}", index, categories.join(", "))
+            }
+            Self::Oblique(weights, pivot) => {
+                for (k, (index, w)) in weights.iter().enumerate() {
+                    match k {
+                        0 => write!(f, "{} * x[{}]", w, index)?,
+                        _ if *w < 0.0 => write!(f, " - {} * x[{}]", -w, index)?,
+                        _ => write!(f, " + {} * x[{}]", w, index)?,
+                    }
+                }
+                write!(f, " <= {}", pivot)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oblique_split() {
+        let split = Split::Oblique(vec![(0, 1.0), (2, -0.5)], 1.0);
+        assert!(split.turn_left(&vec![1.0, 9.0, 0.5].into()));
+        assert!(!split.turn_left(&vec![2.0, 0.0, 1.0].into()));
+        assert!(!split.turn_left(&vec![0.0, 0.0, f64::NAN].into()));
+        assert_eq!(split.index(), 0);
+        assert_eq!(split.to_string(), "1 * x[0] - 0.5 * x[2] <= 1");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_oblique() {
+        let split = Split::Oblique(vec![(1, 0.25), (3, 2.0)], -1.5);
+        let json = serde_json::to_string(&split).unwrap();
+        assert_eq!(serde_json::from_str::<Split>(&json).unwrap(), split);
+    }
+}
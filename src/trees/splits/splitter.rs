@@ -1,14 +1,88 @@
 use std::fmt::Debug;
 
-use super::Split;
-use crate::core::{Label, TrainingRow};
+use rand::Rng;
+
+use super::{MonotonicConstraints, Split};
+use crate::core::{ModelingError, Result, TrainingRow};
+
+/// Searches the best split of a node, whose rows are given by their indices into the training
+/// data shared by the whole tree.
+///
+/// Splitters hold no random state of their own. Every random choice, such as the features to
+/// consider, is drawn from the `rng` passed to the search.
+pub trait Splitter<T>: Debug + Send + Sync {
+    /// Statistics of the rows of a node, kept by the tree builder to speed up the search of its
+    /// children. Splitters without such statistics use `()`.
+    type NodeStats: Default;
 
-pub trait Splitter<L: Label>: Debug {
     ///  Get the best split, considering num_features random features (w/o replacement)
     fn find_best_split(
         &mut self,
-        data: &[TrainingRow<L>],
+        data: &[TrainingRow<T>],
+        num_features: usize,
+        min_count: usize,
+        rng: &mut impl Rng,
+    ) -> (Split, f64) {
+        let indices: Vec<usize> = (0..data.len()).collect();
+        let stats = self.node_stats(data, &indices);
+        self.find_best_split_with_stats(data, &indices, &stats, num_features, min_count, 0.0, rng)
+    }
+
+    /// Compute the statistics of the rows of `data` at `indices`.
+    fn node_stats(&mut self, _data: &[TrainingRow<T>], _indices: &[usize]) -> Self::NodeStats {
+        Self::NodeStats::default()
+    }
+
+    /// Derive the statistics of a node from those of its parent and its sibling.
+    fn subtract_stats(
+        &self,
+        _parent: Self::NodeStats,
+        _sibling: &Self::NodeStats,
+    ) -> Self::NodeStats {
+        Self::NodeStats::default()
+    }
+
+    /// Split the statistics of a node between its children, given the rows on each side.
+    ///
+    /// By default, the statistics of the smaller child are computed directly and those of the
+    /// larger child are derived from the parent.
+    fn partition_stats(
+        &mut self,
+        data: &[TrainingRow<T>],
+        parent: Self::NodeStats,
+        left: &[usize],
+        right: &[usize],
+    ) -> (Self::NodeStats, Self::NodeStats) {
+        if left.len() <= right.len() {
+            let left_stats = self.node_stats(data, left);
+            let right_stats = self.subtract_stats(parent, &left_stats);
+            (left_stats, right_stats)
+        } else {
+            let right_stats = self.node_stats(data, right);
+            let left_stats = self.subtract_stats(parent, &right_stats);
+            (left_stats, right_stats)
+        }
+    }
+
+    /// Reject splits moving the mean label against the monotonic constraints.
+    /// Splitters that cannot enforce the constraints return an error.
+    fn set_constraints(&mut self, _constraints: &MonotonicConstraints) -> Result<()> {
+        Err(ModelingError::FitError("The splitter does not support monotonic constraints.".into()))
+    }
+
+    /// Get the best split of the rows of `data` at `indices`, given their statistics.
+    ///
+    /// Candidates leaving fewer than `min_count` rows or less than `min_weight` of the row weight
+    /// on either side are skipped, so another feature or threshold may be chosen instead.
+    #[allow(clippy::too_many_arguments)]
+    fn find_best_split_with_stats(
+        &mut self,
+        data: &[TrainingRow<T>],
+        indices: &[usize],
+        stats: &Self::NodeStats,
         num_features: usize,
         min_count: usize,
+        min_weight: f64,
+        rng: &mut impl Rng,
     ) -> (Split, f64);
 }
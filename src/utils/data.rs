@@ -1,24 +1,25 @@
-use ndarray::{Array, Array1, Array2};
-use ndarray_rand::{rand_distr::Uniform, RandomExt};
+use nalgebra::{DMatrix, DVector};
+use rand::distributions::uniform::SampleUniform;
+use rand::Rng;
 
-use crate::core::{Label, TrainingRow};
+use crate::core::{AnyValue, FeatureRow, TrainingRow};
 
-pub fn check_dimensions<X, Y, W>(X: &Array2<X>, y: &Array1<Y>, w: &Array1<W>) {
-    let (nx, ny, nw) = (X.nrows(), y.ndim(), w.ndim());
+pub fn check_dimensions<X, Y, W>(X: &DMatrix<X>, y: &DVector<Y>, w: &DVector<W>) {
+    let (nx, ny, nw) = (X.nrows(), y.len(), w.len());
     if (nx, ny) != (ny, nw) {
         panic!["Input dimensions do not match: X = {}, y = {}, w = {}", nx, ny, nw];
     }
 }
 
-pub fn build_training_data<L: Label>(
-    X: &Array2<f64>,
-    y: &Array1<L>,
-    w: Option<&Array1<f64>>,
-) -> Vec<TrainingRow<L>> {
+pub fn build_training_data<T: Copy>(
+    X: &DMatrix<f64>,
+    y: &DVector<T>,
+    w: Option<&DVector<f64>>,
+) -> Vec<TrainingRow<T>> {
     // Unit weights if not provided
     let w = match w {
-        Some(weights) => weights.to_owned(),
-        None => Array::from_elem(y.len(), 1.0),
+        Some(weights) => weights.clone(),
+        None => DVector::from_element(y.len(), 1.0),
     };
 
     // Panic if mismatched dimensions
@@ -26,41 +27,44 @@ pub fn build_training_data<L: Label>(
 
     (0..X.nrows())
         .map(|idx| {
-            let reals = X.row(idx).iter().copied().collect();
-            let yi = y[idx];
-            let wi = w[idx];
-
-            TrainingRow::new(reals, vec![], yi, wi)
+            let reals: Vec<f64> = X.row(idx).iter().copied().collect();
+            TrainingRow::new(reals, y[idx], Some(w[idx]))
         })
         .collect()
 }
 
-pub fn random_training_data<L: Label>(ns: usize, nr: usize, nc: usize) -> Vec<TrainingRow<L>> {
-    let real_range = Uniform::new(-10.0, 10.0);
-    let cat_range = Uniform::new(0, 5);
-
+pub fn random_training_data<T: SampleUniform + PartialOrd + From<u8>>(
+    ns: usize,
+    nr: usize,
+    nc: usize,
+    rng: &mut impl Rng,
+) -> Vec<TrainingRow<T>> {
     let mut data = Vec::with_capacity(ns);
     for _ in 0..ns {
-        let reals = Array::random(nr, real_range).to_vec();
-        let cats = Array::random(nc, cat_range).to_vec();
-        let y = L::gen_range(L::from(0).unwrap()..L::from(5).unwrap());
-        let w = rand::random();
-        data.push(TrainingRow::new(reals, cats, y, w));
+        let mut values: Vec<AnyValue> =
+            (0..nr).map(|_| rng.gen_range(-10.0..10.0).into()).collect();
+        values.extend((0..nc).map(|_| AnyValue::from(rng.gen_range(0..5))));
+        let y = rng.gen_range(T::from(0)..T::from(5));
+        let w = rng.gen();
+        data.push(TrainingRow::new(FeatureRow::new(values), y, Some(w)));
     }
 
     data
 }
 
-pub fn linear_training_data(ns: usize, coeffs: &[f64], intercept: f64) -> Vec<TrainingRow<f64>> {
-    let real_range = Uniform::new(-10.0, 10.0);
-
+pub fn linear_training_data(
+    ns: usize,
+    coeffs: &[f64],
+    intercept: f64,
+    rng: &mut impl Rng,
+) -> Vec<TrainingRow<f64>> {
     let mut data = Vec::with_capacity(ns);
     for _ in 0..ns {
-        let reals = Array::random(coeffs.len(), real_range).to_vec();
+        let reals: Vec<f64> = (0..coeffs.len()).map(|_| rng.gen_range(-10.0..10.0)).collect();
         let y =
             intercept + coeffs.iter().zip(reals.iter()).fold(0.0, |state, (c, x)| state + c * x);
-        let w = rand::random();
-        data.push(TrainingRow::new(reals, vec![], y, w));
+        let w = rng.gen();
+        data.push(TrainingRow::new(reals, y, Some(w)));
     }
 
     data
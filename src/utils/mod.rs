@@ -1,3 +1,7 @@
 mod data;
+mod optimize;
+mod parallel;
 
 pub use self::data::*;
+pub use self::optimize::*;
+pub(crate) use self::parallel::*;
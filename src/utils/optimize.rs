@@ -0,0 +1,94 @@
+/// Minimize a function with the Nelder-Mead simplex method, returning the best point and its value.
+///
+/// The initial simplex is built by stepping `step` along each coordinate axis from `x0`.
+/// Non-finite function values are treated as infinitely bad.
+pub fn nelder_mead(
+    f: impl Fn(&[f64]) -> f64,
+    x0: &[f64],
+    step: f64,
+    max_iter: usize,
+    tolerance: f64,
+) -> (Vec<f64>, f64) {
+    let eval = |x: &[f64]| {
+        let value = f(x);
+        if value.is_nan() {
+            f64::INFINITY
+        } else {
+            value
+        }
+    };
+
+    let n = x0.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = Vec::with_capacity(n + 1);
+    simplex.push((x0.to_vec(), eval(x0)));
+    for i in 0..n {
+        let mut x = x0.to_vec();
+        x[i] += step;
+        let value = eval(&x);
+        simplex.push((x, value));
+    }
+
+    // Combine two points as a + t * (b - a)
+    let lerp = |a: &[f64], b: &[f64], t: f64| -> Vec<f64> {
+        a.iter().zip(b.iter()).map(|(ai, bi)| ai + t * (bi - ai)).collect()
+    };
+
+    for _ in 0..max_iter {
+        simplex.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+
+        let (best, worst) = (simplex[0].1, simplex[n].1);
+        if (worst - best).abs() <= tolerance * (best.abs() + tolerance) {
+            break;
+        }
+
+        // Centroid of all points except the worst
+        let mut centroid = vec![0.0; n];
+        for (x, _) in simplex.iter().take(n) {
+            centroid.iter_mut().zip(x.iter()).for_each(|(c, xi)| *c += xi / (n as f64));
+        }
+
+        let reflected = lerp(&centroid, &simplex[n].0, -1.0);
+        let fr = eval(&reflected);
+        if fr < simplex[0].1 {
+            let expanded = lerp(&centroid, &simplex[n].0, -2.0);
+            let fe = eval(&expanded);
+            simplex[n] = if fe < fr { (expanded, fe) } else { (reflected, fr) };
+        } else if fr < simplex[n - 1].1 {
+            simplex[n] = (reflected, fr);
+        } else {
+            let contracted = if fr < worst {
+                lerp(&centroid, &reflected, 0.5)
+            } else {
+                lerp(&centroid, &simplex[n].0, 0.5)
+            };
+            let fc = eval(&contracted);
+            if fc < fr.min(worst) {
+                simplex[n] = (contracted, fc);
+            } else {
+                // Shrink everything towards the best point
+                let head = simplex[0].0.clone();
+                for (x, value) in simplex.iter_mut().skip(1) {
+                    *x = lerp(&head, x, 0.5);
+                    *value = eval(x);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+    simplex.swap_remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rosenbrock() {
+        let rosenbrock = |x: &[f64]| (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2);
+        let (x, value) = nelder_mead(rosenbrock, &[-1.0, 2.0], 0.5, 5000, 1e-14);
+
+        assert!(value < 1e-8);
+        assert!((x[0] - 1.0).abs() < 1e-3 && (x[1] - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,26 @@
+use rand::Rng;
+
+/// Map `f` over `items` in order, in parallel with the `rayon` feature.
+pub(crate) fn par_map<I, R, F>(items: &[I], f: F) -> Vec<R>
+where
+    I: Sync,
+    R: Send,
+    F: Fn(&I) -> R + Send + Sync,
+{
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        items.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
+/// Seeds for the random streams of `n` tasks, drawn in order from `rng`.
+///
+/// Each task seeds its own generator, so results do not depend on how tasks are scheduled.
+pub(crate) fn task_seeds(rng: &mut impl Rng, n: usize) -> Vec<u64> {
+    (0..n).map(|_| rng.gen()).collect()
+}